@@ -11,22 +11,28 @@
 (defvar irl-mode-syntax-table
   (let ((table (make-syntax-table)))
 	(modify-syntax-entry ?/ ". 124b" table)
+	(modify-syntax-entry ?* ". 23" table)
     (modify-syntax-entry ?\n "> b" table)
 	table))
 
 (defvar irl-keywords
-  '("function" "arg" "goto" "label" "if" "param" "ret" "call"))
+  '("function" "arg" "goto" "label" "if" "ifnot" "else" "switch" "default" "while" "break" "continue" "param" "ret" "call" "alloca" "load" "store" "global" "const" "extern" "import" "export" "module" "target" "datalayout"))
+
+(defvar irl-types
+  '("i1" "i8" "i16" "i32" "i64" "f32" "f64" "ptr" "void"))
 
 (defvar irl-operators
-  '("=" "\\+" "-" "\\*" "/" "<=" "==" "!=" "<" ">" ">="))
+  '("=" "\\+" "->" "-" "\\*" "/" "%" "&" "|" "^" "~" "!" "<<" ">>" ">>>" "<=" "==" "!=" "<" ">" ">=" ":"))
 
-(defvar irl-iden-regexp "\\b[a-zA-Z][a-zA-Z0-9]*\\b")
-(defvar irl-num-regexp "\\b[0-9]+\\b")
+(defvar irl-iden-regexp "[%@][a-zA-Z0-9_.]+\\|\\b[a-zA-Z_][a-zA-Z0-9_.]*\\|`[^`\n]+`")
+(defvar irl-num-regexp "\\b[0-9][0-9_]*\\(\\.[0-9][0-9_]*\\)?\\([eE][+-]?[0-9]+\\)?\\b")
 (defvar irl-keyword-regexp (regexp-opt irl-keywords 'words))
+(defvar irl-type-regexp (regexp-opt irl-types 'words))
 (defvar irl-operator-regexp (regexp-opt irl-operators))
 
 (defvar irl-font-lock-keywords
   `((,irl-keyword-regexp . font-lock-keyword-face)
+    (,irl-type-regexp . font-lock-type-face)
     (,irl-operator-regexp . font-lock-operator-face)
     (,irl-iden-regexp . font-lock-variable-name-face)
     (,irl-num-regexp . font-lock-constant-face)
@@ -1,4 +1,6 @@
+use std::io::IsTerminal;
 use clap::{Arg, Command, ArgAction};
+use crate::fe::diagnostic::ErrorFormat;
 
 pub struct CliOptions {
 	pub filepath: String,
@@ -9,6 +11,8 @@ pub struct CliOptions {
 	pub wasm: bool,
 	pub fasm: bool,
 	pub run: bool,
+	pub color: bool,
+	pub error_format: ErrorFormat,
 }
 
 impl CliOptions {
@@ -24,6 +28,16 @@ impl CliOptions {
 			wasm: *compile_args.unwrap().get_one::<bool>("wasm").unwrap(),
 			fasm: *compile_args.unwrap().get_one::<bool>("fasm").unwrap(),
 			run: *compile_args.unwrap().get_one::<bool>("run").unwrap(),
+			color: match compile_args.unwrap().get_one::<String>("color").unwrap().as_str() {
+				"always" => true,
+				"never" => false,
+				_ => std::io::stderr().is_terminal(),
+			},
+			error_format: match compile_args.unwrap().get_one::<String>("error-format").unwrap().as_str() {
+				"json" => ErrorFormat::Json,
+				"sarif" => ErrorFormat::Sarif,
+				_ => ErrorFormat::Human,
+			},
 		}
 	}
 	pub fn verbose_message(&self, message: String) {
@@ -35,13 +49,19 @@ impl CliOptions {
 	pub fn run_command(&self, args:  &[&str]) {
 		if args.is_empty() {return;}
 
-		let binpath = args.get(0).cloned().unwrap_or_default();
+		let binpath = args.first().cloned().unwrap_or_default();
 		let mut command = std::process::Command::new(binpath);
 		for arg in &args[1..] {
 			command.arg(arg);
 		}
 		
-		let output = command.output().expect("failed to execute command");
+		let output = match command.output() {
+			Ok(output) => output,
+			Err(err) => {
+				self.verbose_error(format!("could not run {}: {}", binpath, err));
+				return;
+			},
+		};
 		if !output.status.success() {
 			let stderr = std::str::from_utf8(&output.stderr).expect("Invalid UTF-8 in stderr");
 			self.verbose_message(format!("error: {}", stderr));
@@ -49,6 +69,12 @@ impl CliOptions {
 	}
 }
 
+impl Default for CliOptions {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 pub fn cli() -> Command {
     Command::new("irl")
 		.version("1.0")
@@ -106,5 +132,17 @@ pub fn cli() -> Command {
                      .required(false)
                      .action(ArgAction::SetTrue)
                      .help("Generates FASM (Flat Assembly)"))
+				.arg(Arg::new("color")
+                     .long("color")
+                     .required(false)
+                     .value_parser(["auto", "always", "never"])
+                     .default_value("auto")
+                     .help("Coloring of diagnostics"))
+				.arg(Arg::new("error-format")
+                     .long("error-format")
+                     .required(false)
+                     .value_parser(["human", "json", "sarif"])
+                     .default_value("human")
+                     .help("Format of diagnostics written to stderr"))
 		)
 }
@@ -1,5 +1,6 @@
 use std::collections::{HashMap, HashSet};
-use crate::fe::{loc::Loc, token::{Token, TokenKind}};
+use crate::fe::{loc::Loc, diagnostic::Diagnostic, token::{Token, TokenKind, format_iden, format_str}, types::Type,
+				target::{Target, DataLayout}};
 
 fn print_indent(f: &mut std::fmt::Formatter, indent_sz: usize) {	
 	for _ in 0..indent_sz {
@@ -7,33 +8,86 @@ fn print_indent(f: &mut std::fmt::Formatter, indent_sz: usize) {
 	}
 }
 
+/// Type annotation as written in source; `i32` being the default is left out.
+fn format_annotation(prefix: &str, ty: Option<Type>) -> String {
+	match ty {
+		Some(ty) if ty != Type::I32 => format!("{}{}", prefix, ty),
+		_ => String::new(),
+	}
+}
+
+/// Return type as written in a signature, `void` for functions returning nothing.
+pub fn format_ret_ty(ret_ty: Option<Type>) -> String {
+	match ret_ty {
+		Some(ty) => ty.to_string(),
+		None => String::from("void"),
+	}
+}
+
+/// Return type annotation of a function header; `-> i32` being the default is left out.
+fn format_ret_annotation(ret_ty: Option<Type>) -> String {
+	match ret_ty {
+		Some(ty) => format_annotation(" -> ", Some(ty)),
+		None => String::from(" -> void"),
+	}
+}
+
+struct Indented<'a>(&'a AstNode, usize);
+
+impl std::fmt::Display for Indented<'_> {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		self.0.print(f, self.1)
+	}
+}
+
+/// Prints `header` followed by `nodes`; trailing comments are joined onto the
+/// line they were written on.
+fn print_body(f: &mut std::fmt::Formatter, header: String, nodes: &[AstNode],
+			  indent_sz: usize) -> std::fmt::Result {
+	let mut line: String = header;
+	for node in nodes.iter() {
+		match node {
+			AstNode::Comment(comment_node) if comment_node.trailing && !line.is_empty() => {
+				line = format!("{} {}\n", line.trim_end_matches('\n'), comment_node.text);
+			},
+			_ => {
+				let _ = write!(f, "{}", line);
+				line = Indented(node, indent_sz).to_string();
+			},
+		}
+	}
+	write!(f, "{}", line)
+}
+
 #[derive(Clone)]
 pub struct IdenAstNode {
 	pub name: String,
+	pub ty: Type,
 	pub loc: Loc,
 }
 
 impl PartialEq for IdenAstNode {
     fn eq(&self, other: &Self) -> bool {
-        self.name == other.name
+        self.name == other.name && self.ty == other.ty
     }
 }
 
 impl IdenAstNode {
 	fn print(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-		write!(f, "{}", self.name)
+		write!(f, "{}", format_iden(&self.name))
 	}
 }
 
 #[derive(Clone)]
 pub struct NumAstNode {
-	pub num: i32,
+	pub num: i64,
+	pub ty: Type,
 	pub loc: Loc,
 }
 
 impl PartialEq for NumAstNode {
     fn eq(&self, other: &Self) -> bool {
-        self.num == other.num
+        self.num == other.num && self.ty == other.ty
     }
 }
 
@@ -43,17 +97,124 @@ impl NumAstNode {
 	}
 }
 
+#[derive(Clone)]
+pub struct FloatAstNode {
+	pub num: f64,
+	pub ty: Type,
+	pub loc: Loc,
+}
+
+impl PartialEq for FloatAstNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.num.to_bits() == other.num.to_bits() && self.ty == other.ty
+    }
+}
+
+impl FloatAstNode {
+	fn print(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		// `{:?}` keeps the fraction of whole numbers so the literal lexes back as a float
+		write!(f, "{:?}", self.num)
+	}
+}
+
+/// NUL terminated string constant, its value is a `ptr` to the first byte.
+#[derive(Clone)]
+pub struct StrAstNode {
+	pub bytes: Vec<u8>,
+	pub loc: Loc,
+}
+
+impl PartialEq for StrAstNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl StrAstNode {
+	fn print(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{}", format_str(&self.bytes))
+	}
+}
+
+#[derive(Clone)]
+pub struct CommentAstNode {
+	pub text: String,
+	pub trailing: bool,
+	pub loc: Loc,
+}
+
+impl PartialEq for CommentAstNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text && self.trailing == other.trailing
+    }
+}
+
+impl CommentAstNode {
+	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
+		print_indent(f, indent_sz);
+		writeln!(f, "{}", self.text)
+	}
+	/// Comment text without its delimiters, one entry per line.
+	pub fn lines(&self) -> Vec<String> {
+		let text: &str = match self.text.strip_prefix("//") {
+			Some(text) => text,
+			None => self.text.trim_start_matches("/*").trim_end_matches("*/"),
+		};
+		text.lines().map(|line| line.trim().to_string()).filter(|line| !line.is_empty()).collect()
+	}
+}
+
+/// Placeholder for a statement the parser could not make sense of; the
+/// corresponding diagnostic has already been reported.
+#[derive(Clone)]
+pub struct ErrorAstNode {
+	pub loc: Loc,
+}
+
+impl PartialEq for ErrorAstNode {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl ErrorAstNode {
+	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
+		print_indent(f, indent_sz);
+		writeln!(f, "<error>")
+	}
+}
+
+/// Function a `call` transfers control to.
+#[derive(Clone, PartialEq)]
+pub enum Callee {
+	/// a function or extern called by name
+	Direct(String),
+	/// `call *f`, through the function pointer held by `f`
+	Indirect(Box<AstNode>),
+}
+
+impl std::fmt::Display for Callee {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Callee::Direct(name) => write!(f, "{}", format_iden(name)),
+			Callee::Indirect(ptr) => write!(f, "*{}", ptr),
+		}
+	}
+}
+
 #[derive(Clone)]
 pub struct CallAstNode {
-	pub id: String,
-	pub name: String,
+	/// `None` for a bare `call` whose result, if any, is discarded
+	pub id: Option<String>,
+	pub callee: Callee,
 	pub params: Vec<AstNode>,
+	pub ty: Option<Type>,
 	pub loc: Loc,
 }
 
 impl PartialEq for CallAstNode {
     fn eq(&self, other: &Self) -> bool {
-        self.name == other.name && self.params == other.params
+        self.callee == other.callee && self.params == other.params && self.ty == other.ty
     }
 }
 
@@ -61,41 +222,61 @@ impl CallAstNode {
 	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
 		for param in self.params.iter() {
 			print_indent(f, indent_sz);
-			let _ = write!(f, "param {}\n", param);
+			let _ = writeln!(f, "param {}", param);
 		}
 		print_indent(f, indent_sz);
-		write!(f, "{} = call {}, {}\n", self.id, self.name, self.params.len())
+		if let Some(id) = &self.id {
+			let _ = write!(f, "{}{} = ", format_iden(id), format_annotation(": ", self.ty));
+		}
+		writeln!(f, "call {}, {}", self.callee, self.params.len())
 	}
 }
 
 #[derive(Clone, PartialEq)]
 pub enum ArithOp {
-	Sum, Sub, Mul, Div,
+	Sum, Sub, Mul, Div, Rem, And, Or, Xor, Shl, Shr, LShr,
 }
 
 impl ArithOp {
-	pub fn new(token: &Token) -> Self {
+	pub fn new(token: &Token) -> Result<Self, Diagnostic> {
 		match token.kind {
-			TokenKind::Plus => ArithOp::Sum,
-			TokenKind::Minus => ArithOp::Sub,
-			TokenKind::Mul => ArithOp::Mul,
-			TokenKind::Div => ArithOp::Div,
-			_ => {
-				token.error_token_kind_mismatch(
-					vec![TokenKind::Plus, TokenKind::Minus,
-						 TokenKind::Mul, TokenKind::Div]);
-				unreachable!()
-			},
+			TokenKind::Plus => Ok(ArithOp::Sum),
+			TokenKind::Minus => Ok(ArithOp::Sub),
+			TokenKind::Mul => Ok(ArithOp::Mul),
+			TokenKind::Div => Ok(ArithOp::Div),
+			TokenKind::Rem => Ok(ArithOp::Rem),
+			TokenKind::And => Ok(ArithOp::And),
+			TokenKind::Or => Ok(ArithOp::Or),
+			TokenKind::Xor => Ok(ArithOp::Xor),
+			TokenKind::Shl => Ok(ArithOp::Shl),
+			TokenKind::Shr => Ok(ArithOp::Shr),
+			TokenKind::LShr => Ok(ArithOp::LShr),
+			_ => Err(token.error_token_kind_mismatch(
+				vec![TokenKind::Plus, TokenKind::Minus, TokenKind::Mul, TokenKind::Div,
+					 TokenKind::Rem, TokenKind::And, TokenKind::Or, TokenKind::Xor,
+					 TokenKind::Shl, TokenKind::Shr, TokenKind::LShr])),
 		}	
 	}
-	pub fn to_string(&self) -> String {
+	/// x86 mnemonic of the operation; the SSE ones once suffixed by the operand size.
+	pub fn opcode(&self) -> String {
 		String::from(match self {
 			ArithOp::Sum => "add",
 			ArithOp::Sub => "sub",
 			ArithOp::Mul => "mul",
 			ArithOp::Div => "div",
+			ArithOp::Rem => "div",
+			ArithOp::And => "and",
+			ArithOp::Or => "or",
+			ArithOp::Xor => "xor",
+			ArithOp::Shl => "shl",
+			ArithOp::Shr => "sar",
+			ArithOp::LShr => "shr",
 		})
 	}
+	/// Whether the operation is defined on floats.
+	pub fn is_float_op(&self) -> bool {
+		matches!(self, ArithOp::Sum | ArithOp::Sub | ArithOp::Mul | ArithOp::Div)
+	}
 }
 
 impl std::fmt::Display for ArithOp {
@@ -105,6 +286,13 @@ impl std::fmt::Display for ArithOp {
 			ArithOp::Sub => "-",
 			ArithOp::Mul => "*",
 			ArithOp::Div => "/",
+			ArithOp::Rem => "%",
+			ArithOp::And => "&",
+			ArithOp::Or => "|",
+			ArithOp::Xor => "^",
+			ArithOp::Shl => "<<",
+			ArithOp::Shr => ">>",
+			ArithOp::LShr => ">>>",
 		})
 	}
 }
@@ -123,9 +311,17 @@ impl PartialEq for ArithAstNode {
     }
 }
 
+/// Operand of an expression, parenthesised when it is a binary expression itself.
+fn format_subexpr(node: &AstNode) -> String {
+	match node {
+		AstNode::Arith(_) | AstNode::Relop(_) => format!("({})", node),
+		_ => node.to_string(),
+	}
+}
+
 impl ArithAstNode {
 	fn print(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-		write!(f, "{} {} {}", self.lhs, self.op, self.rhs)
+		write!(f, "{} {} {}", format_subexpr(&self.lhs), self.op, format_subexpr(&self.rhs))
 	}
 }
 
@@ -135,20 +331,17 @@ pub enum RelOp {
 }
 
 impl RelOp {
-	pub fn new(token: &Token) -> Self {
+	pub fn new(token: &Token) -> Result<Self, Diagnostic> {
 		match token.kind {
-			TokenKind::Eq => RelOp::Eq,
-			TokenKind::Neq => RelOp::Neq,
-			TokenKind::Gt => RelOp::Gt,
-			TokenKind::Lt => RelOp::Lt,
-			TokenKind::Ge => RelOp::Ge,
-			TokenKind::Le => RelOp::Le,
-			_ => {
-				token.error_token_kind_mismatch(
-					vec![TokenKind::Eq, TokenKind::Neq, TokenKind::Gt,
-						 TokenKind::Lt, TokenKind::Ge, TokenKind::Le]);
-				unreachable!()
-			},
+			TokenKind::Eq => Ok(RelOp::Eq),
+			TokenKind::Neq => Ok(RelOp::Neq),
+			TokenKind::Gt => Ok(RelOp::Gt),
+			TokenKind::Lt => Ok(RelOp::Lt),
+			TokenKind::Ge => Ok(RelOp::Ge),
+			TokenKind::Le => Ok(RelOp::Le),
+			_ => Err(token.error_token_kind_mismatch(
+				vec![TokenKind::Eq, TokenKind::Neq, TokenKind::Gt,
+					 TokenKind::Lt, TokenKind::Ge, TokenKind::Le])),
 		}
 	}
 }
@@ -182,23 +375,24 @@ impl PartialEq for RelopAstNode {
 
 impl RelopAstNode {
 	fn print(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-		write!(f, "{} {} {}", self.lhs, self.op, self.rhs)
+		write!(f, "{} {} {}", format_subexpr(&self.lhs), self.op, format_subexpr(&self.rhs))
 	}
 }
 
 #[derive(Clone, PartialEq)]
 pub enum UnaryOp {
 	Neg,
+	Not,
+	BitNot,
 }
 
 impl UnaryOp {
-	pub fn new(token: &Token) -> Self {
+	pub fn new(token: &Token) -> Result<Self, Diagnostic> {
 		match token.kind {
-			TokenKind::Minus => UnaryOp::Neg,
-			_ => {
-				token.error_token_kind_mismatch(vec![TokenKind::Minus]);
-				unreachable!()
-			},
+			TokenKind::Minus => Ok(UnaryOp::Neg),
+			TokenKind::Not => Ok(UnaryOp::Not),
+			TokenKind::Tilde => Ok(UnaryOp::BitNot),
+			_ => Err(token.error_token_kind_mismatch(vec![TokenKind::Minus, TokenKind::Not, TokenKind::Tilde])),
 		}
 	}
 }
@@ -206,7 +400,9 @@ impl UnaryOp {
 impl std::fmt::Display for UnaryOp {
 	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
 		write!(f, "{}", match self {
-			UnaryOp::Neg => "-"
+			UnaryOp::Neg => "-",
+			UnaryOp::Not => "!",
+			UnaryOp::BitNot => "~",
 		})
 	}
 }
@@ -226,56 +422,94 @@ impl PartialEq for UnaryAstNode {
 
 impl UnaryAstNode {
 	fn print(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-		write!(f, "{}{}", self.op, self.var)
+		write!(f, "{}{}", self.op, format_subexpr(&self.var))
 	}
 }
 
 #[derive(Clone)]
 pub struct FunctionAstNode {
 	pub name: String,
+	/// `export function`, callable from the other files of the program under its own name
+	pub exported: bool,
 	pub args: Vec<AstNode>,
+	/// `None` for a function returning nothing
+	pub ret_ty: Option<Type>,
 	pub body: Vec<AstNode>,
 	pub loc: Loc,
 }
 
 impl PartialEq for FunctionAstNode {
     fn eq(&self, other: &Self) -> bool {
-        self.name == other.name && self.args == other.args && self.body == other.body
+        self.name == other.name && self.exported == other.exported && self.args == other.args
+			&& self.ret_ty == other.ret_ty && self.body == other.body
     }
 }
 
 impl FunctionAstNode {
 	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
-		print_indent(f, indent_sz);
-		let _ = write!(f, "function {}, {}\n", self.name, self.args.len());
+		let mut header: String = String::new();
+		for _ in 0..indent_sz {header.push_str("    ");}
+		header.push_str(&format!("{}function {}, {}{}\n", if self.exported {"export "} else {""},
+								 format_iden(&self.name), self.args.len(), format_ret_annotation(self.ret_ty)));
 		for arg in self.args.iter() {
-			print_indent(f, indent_sz+1);
-			let _ = write!(f, "arg {}\n", arg);
+			for _ in 0..indent_sz+1 {header.push_str("    ");}
+			if let AstNode::Iden(iden_node) = arg {
+				header.push_str(&format!("arg {}{}\n", arg, format_annotation(": ", Some(iden_node.ty))));
+			}
 		}
-		for node in self.body.iter() {
-			let _ = node.print(f, indent_sz+1);
+		print_body(f, header, &self.body, indent_sz+1)
+	}
+}
+
+/// Function provided by the runtime or the host rather than defined in the program.
+#[derive(Clone)]
+pub struct ExternAstNode {
+	pub name: String,
+	pub args: Vec<AstNode>,
+	pub ret_ty: Option<Type>,
+	pub loc: Loc,
+}
+
+impl PartialEq for ExternAstNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.args == other.args && self.ret_ty == other.ret_ty
+    }
+}
+
+impl ExternAstNode {
+	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
+		let mut header: String = String::new();
+		for _ in 0..indent_sz {header.push_str("    ");}
+		header.push_str(&format!("extern {}, {}{}\n", format_iden(&self.name), self.args.len(),
+								 format_ret_annotation(self.ret_ty)));
+		for arg in self.args.iter() {
+			for _ in 0..indent_sz+1 {header.push_str("    ");}
+			if let AstNode::Iden(iden_node) = arg {
+				header.push_str(&format!("arg {}{}\n", arg, format_annotation(": ", Some(iden_node.ty))));
+			}
 		}
-		write!(f, "")
+		write!(f, "{}", header)
 	}
 }
 
 #[derive(Clone)]
 pub struct AssignmentAstNode {
 	pub name: String,
+	pub ty: Option<Type>,
 	pub var: Box<AstNode>,
 	pub loc: Loc,
 }
 
 impl PartialEq for AssignmentAstNode {
     fn eq(&self, other: &Self) -> bool {
-        self.name == other.name && self.var == other.var
+        self.name == other.name && self.ty == other.ty && self.var == other.var
     }
 }
 
 impl AssignmentAstNode {
 	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
 		print_indent(f, indent_sz);
-		write!(f, "{} = {}\n", self.name, self.var)
+		writeln!(f, "{}{} = {}", format_iden(&self.name), format_annotation(": ", self.ty), self.var)
 	}
 }
 
@@ -294,7 +528,7 @@ impl PartialEq for GotoAstNode {
 impl GotoAstNode {
 	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
 		print_indent(f, indent_sz);
-		write!(f, "goto {}\n", self.name)
+		writeln!(f, "goto {}", format_iden(&self.name))
 	}
 }
 
@@ -302,6 +536,8 @@ impl GotoAstNode {
 pub struct LabelAstNode {
 	pub name: String,
 	pub body: Vec<AstNode>,
+	/// placed by lower_control_flow_pass, continues the scope of the label before it
+	pub lowered: bool,
 	pub loc: Loc,
 }
 
@@ -313,38 +549,163 @@ impl PartialEq for LabelAstNode {
 
 impl LabelAstNode {
 	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
-		print_indent(f, indent_sz);
-		let _ = write!(f, "label {}\n", self.name);
-		for node in self.body.iter() {
-			let _ = node.print(f, indent_sz+1);
-		}
-		write!(f, "")
+		let mut header: String = String::new();
+		for _ in 0..indent_sz {header.push_str("    ");}
+		header.push_str(&format!("label {}\n", format_iden(&self.name)));
+		print_body(f, header, &self.body, indent_sz+1)
 	}
 }
 
 #[derive(Clone)]
 pub struct IfAstNode {
+	/// a comparison, or an operand which holds when it is not zero
 	pub condition: Box<AstNode>,
 	pub label: String,
+	/// label jumped to when the condition does not hold, instead of falling through
+	pub otherwise: Option<String>,
+	/// `ifnot`, jumps to `label` when the condition does not hold
+	pub negated: bool,
 	pub loc: Loc,
 }
 
 impl PartialEq for IfAstNode {
     fn eq(&self, other: &Self) -> bool {
         self.condition == other.condition && self.label == other.label
+			&& self.otherwise == other.otherwise && self.negated == other.negated
     }
 }
 
 impl IfAstNode {
 	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
 		print_indent(f, indent_sz);
-		write!(f, "if ({}) goto {}\n", self.condition, self.label)
+		let _ = write!(f, "{} ({}) goto {}", if self.negated {"ifnot"} else {"if"}, self.condition,
+					   format_iden(&self.label));
+		if let Some(otherwise) = &self.otherwise {
+			let _ = write!(f, " else {}", format_iden(otherwise));
+		}
+		writeln!(f)
+	}
+}
+
+#[derive(Clone, PartialEq)]
+pub struct SwitchCase {
+	pub value: NumAstNode,
+	pub label: String,
+}
+
+#[derive(Clone)]
+pub struct SwitchAstNode {
+	/// integer operand compared against every case value
+	pub scrutinee: Box<AstNode>,
+	pub cases: Vec<SwitchCase>,
+	/// label jumped to when no case value matches
+	pub default: String,
+	pub loc: Loc,
+}
+
+impl PartialEq for SwitchAstNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.scrutinee == other.scrutinee && self.cases == other.cases && self.default == other.default
+    }
+}
+
+impl SwitchAstNode {
+	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
+		print_indent(f, indent_sz);
+		let cases: Vec<String> = self.cases.iter()
+			.map(|case| format!("{}: {}", case.value.num, format_iden(&case.label))).collect();
+		writeln!(f, "switch {} [{}] default {}", self.scrutinee, cases.join(", "), format_iden(&self.default))
+	}
+}
+
+/// Prints `header` followed by `nodes` and the closing brace of the block they make up.
+fn print_block(f: &mut std::fmt::Formatter, header: String, nodes: &[AstNode],
+			   indent_sz: usize) -> std::fmt::Result {
+	let _ = print_body(f, header, nodes, indent_sz+1);
+	print_indent(f, indent_sz);
+	writeln!(f, "}}")
+}
+
+#[derive(Clone)]
+pub struct WhileAstNode {
+	/// a comparison, or an operand which holds when it is not zero
+	pub condition: Box<AstNode>,
+	pub body: Vec<AstNode>,
+	pub loc: Loc,
+}
+
+impl PartialEq for WhileAstNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.condition == other.condition && self.body == other.body
+    }
+}
+
+impl WhileAstNode {
+	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
+		let mut header: String = String::new();
+		for _ in 0..indent_sz {header.push_str("    ");}
+		header.push_str(&format!("while ({}) {{\n", self.condition));
+		print_block(f, header, &self.body, indent_sz)
+	}
+}
+
+#[derive(Clone)]
+pub struct IfBlockAstNode {
+	/// a comparison, or an operand which holds when it is not zero
+	pub condition: Box<AstNode>,
+	pub then_body: Vec<AstNode>,
+	pub else_body: Option<Vec<AstNode>>,
+	pub loc: Loc,
+}
+
+impl PartialEq for IfBlockAstNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.condition == other.condition && self.then_body == other.then_body
+			&& self.else_body == other.else_body
+    }
+}
+
+impl IfBlockAstNode {
+	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
+		let mut header: String = String::new();
+		for _ in 0..indent_sz {header.push_str("    ");}
+		header.push_str(&format!("if ({}) {{\n", self.condition));
+		let else_body: &Vec<AstNode> = match &self.else_body {
+			Some(else_body) => else_body,
+			None => return print_block(f, header, &self.then_body, indent_sz),
+		};
+		let _ = print_body(f, header, &self.then_body, indent_sz+1);
+		let mut header: String = String::new();
+		for _ in 0..indent_sz {header.push_str("    ");}
+		header.push_str("} else {\n");
+		print_block(f, header, else_body, indent_sz)
+	}
+}
+
+/// `break`, or `continue` when `exit` is false, of the innermost enclosing `while`.
+#[derive(Clone)]
+pub struct LoopJumpAstNode {
+	pub exit: bool,
+	pub loc: Loc,
+}
+
+impl PartialEq for LoopJumpAstNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.exit == other.exit
+    }
+}
+
+impl LoopJumpAstNode {
+	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
+		print_indent(f, indent_sz);
+		writeln!(f, "{}", if self.exit {"break"} else {"continue"})
 	}
 }
 
 #[derive(Clone)]
 pub struct RetAstNode {
-	pub var: Box<AstNode>,
+	/// `None` for a plain `ret` from a function returning nothing
+	pub var: Option<Box<AstNode>>,
 	pub loc: Loc,
 }
 
@@ -357,13 +718,240 @@ impl PartialEq for RetAstNode {
 impl RetAstNode {
 	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
 		print_indent(f, indent_sz);
-		write!(f, "ret {}\n", self.var)
+		match &self.var {
+			Some(var) => writeln!(f, "ret {}", var),
+			None => writeln!(f, "ret"),
+		}
+	}
+}
+
+#[derive(Clone)]
+pub struct AllocaAstNode {
+	pub size: usize,
+	pub loc: Loc,
+}
+
+impl PartialEq for AllocaAstNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size
+    }
+}
+
+impl AllocaAstNode {
+	fn print(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "alloca {}", self.size)
+	}
+}
+
+/// `&name`, the address of a function or extern.
+#[derive(Clone)]
+pub struct FuncAddrAstNode {
+	pub name: String,
+	pub loc: Loc,
+}
+
+impl PartialEq for FuncAddrAstNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl FuncAddrAstNode {
+	fn print(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "&{}", format_iden(&self.name))
+	}
+}
+
+#[derive(Clone)]
+pub struct LoadAstNode {
+	pub ptr: Box<AstNode>,
+	pub ty: Type,
+	pub loc: Loc,
+}
+
+impl PartialEq for LoadAstNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr && self.ty == other.ty
+    }
+}
+
+impl LoadAstNode {
+	fn print(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "load {}", self.ptr)
+	}
+}
+
+#[derive(Clone)]
+pub struct StoreAstNode {
+	pub ptr: Box<AstNode>,
+	pub var: Box<AstNode>,
+	pub loc: Loc,
+}
+
+impl PartialEq for StoreAstNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.ptr == other.ptr && self.var == other.var
+    }
+}
+
+impl StoreAstNode {
+	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
+		print_indent(f, indent_sz);
+		writeln!(f, "store {}, {}", self.ptr, self.var)
+	}
+}
+
+#[derive(Clone)]
+pub struct GlobalAstNode {
+	pub name: String,
+	pub ty: Option<Type>,
+	pub init: Box<AstNode>,
+	pub loc: Loc,
+}
+
+impl PartialEq for GlobalAstNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.ty == other.ty && self.init == other.init
+    }
+}
+
+impl GlobalAstNode {
+	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
+		print_indent(f, indent_sz);
+		writeln!(f, "global {}{} = {}", format_iden(&self.name), format_annotation(": ", self.ty), self.init)
+	}
+}
+
+#[derive(Clone)]
+pub struct ConstAstNode {
+	pub name: String,
+	/// expression over literals and other constants, a literal once substitute_const_pass has run
+	pub var: Box<AstNode>,
+	pub loc: Loc,
+}
+
+impl PartialEq for ConstAstNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.var == other.var
+    }
+}
+
+impl ConstAstNode {
+	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
+		print_indent(f, indent_sz);
+		writeln!(f, "const {} = {}", format_iden(&self.name), self.var)
+	}
+}
+
+/// `import "path"`, resolved relative to the file it is written in by the loader.
+#[derive(Clone)]
+pub struct ImportAstNode {
+	pub path: String,
+	pub loc: Loc,
+}
+
+impl PartialEq for ImportAstNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+impl ImportAstNode {
+	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
+		print_indent(f, indent_sz);
+		writeln!(f, "import {}", format_str(self.path.as_bytes()))
+	}
+}
+
+#[derive(Clone, PartialEq)]
+pub enum Directive {
+	Module(String),
+	Target(Target),
+	Datalayout(DataLayout),
+}
+
+impl Directive {
+	pub fn keyword(&self) -> &'static str {
+		match self {
+			Directive::Module(_) => "module",
+			Directive::Target(_) => "target",
+			Directive::Datalayout(_) => "datalayout",
+		}
+	}
+}
+
+/// `module "name"`, `target "triple"` or `datalayout "layout"`, which the loader moves
+/// onto the `ModuleAstNode` of the program.
+#[derive(Clone)]
+pub struct DirectiveAstNode {
+	pub directive: Directive,
+	pub loc: Loc,
+}
+
+impl PartialEq for DirectiveAstNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.directive == other.directive
+    }
+}
+
+impl DirectiveAstNode {
+	fn print(&self, f: &mut std::fmt::Formatter, indent_sz: usize) -> std::fmt::Result {
+		print_indent(f, indent_sz);
+		let value: String = match &self.directive {
+			Directive::Module(name) => name.clone(),
+			Directive::Target(target) => target.to_string(),
+			Directive::Datalayout(layout) => layout.text.clone(),
+		};
+		writeln!(f, "{} {}", self.directive.keyword(), format_str(value.as_bytes()))
+	}
+}
+
+/// Root of a program, the top level nodes of all its files and the directives saying
+/// what it is for; each directive is given at most once.
+#[derive(Clone)]
+pub struct ModuleAstNode {
+	pub directives: Vec<DirectiveAstNode>,
+	pub nodes: Vec<AstNode>,
+}
+
+impl ModuleAstNode {
+	pub fn name(&self) -> Option<&String> {
+		self.directives.iter().find_map(|node| match &node.directive {
+			Directive::Module(name) => Some(name),
+			_ => None,
+		})
+	}
+	pub fn target(&self) -> Option<(Target, &Loc)> {
+		self.directives.iter().find_map(|node| match &node.directive {
+			Directive::Target(target) => Some((*target, &node.loc)),
+			_ => None,
+		})
+	}
+	pub fn datalayout(&self) -> Option<(&DataLayout, &Loc)> {
+		self.directives.iter().find_map(|node| match &node.directive {
+			Directive::Datalayout(layout) => Some((layout, &node.loc)),
+			_ => None,
+		})
+	}
+}
+
+impl std::fmt::Display for ModuleAstNode {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		for node in self.directives.iter() {
+			node.print(f, 0)?;
+		}
+		for node in self.nodes.iter() {
+			writeln!(f, "{}", node)?;
+		}
+		Ok(())
 	}
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Value {
-	Constant(i32),
+	Constant(i64),
+	/// Never NaN; folding that would produce one gives `Nac` instead.
+	Float(f64),
 	Undefined,
 	Nac
 }
@@ -372,8 +960,12 @@ pub fn value_join(v1: Value, v2: Value) -> Value {
 	match (v1, v2) {
 		(Value::Constant(c1), Value::Constant(c2)) => if c1 == c2 {
 			Value::Constant(c1)} else {Value::Nac},
+		(Value::Float(c1), Value::Float(c2)) => if c1.to_bits() == c2.to_bits() {
+			Value::Float(c1)} else {Value::Nac},
 		(Value::Constant(c1), Value::Undefined) => Value::Constant(c1),
 		(Value::Undefined, Value::Constant(c2)) => Value::Constant(c2),
+		(Value::Float(c1), Value::Undefined) => Value::Float(c1),
+		(Value::Undefined, Value::Float(c2)) => Value::Float(c2),
 		(_, Value::Nac) | (Value::Nac, _) => Value::Nac,
 		_ => Value::Undefined
 	}
@@ -383,6 +975,8 @@ pub fn value_join(v1: Value, v2: Value) -> Value {
 pub enum AstNode {
 	Iden(IdenAstNode),
 	Num(NumAstNode),
+	Float(FloatAstNode),
+	Str(StrAstNode),
 	Call(CallAstNode),
 	Arith(ArithAstNode),
 	Relop(RelopAstNode),
@@ -392,7 +986,22 @@ pub enum AstNode {
 	Goto(GotoAstNode),
 	Label(LabelAstNode),
 	If(IfAstNode),
+	Switch(SwitchAstNode),
+	While(WhileAstNode),
+	IfBlock(IfBlockAstNode),
+	LoopJump(LoopJumpAstNode),
 	Ret(RetAstNode),
+	Alloca(AllocaAstNode),
+	FuncAddr(FuncAddrAstNode),
+	Load(LoadAstNode),
+	Store(StoreAstNode),
+	Global(GlobalAstNode),
+	Const(ConstAstNode),
+	Import(ImportAstNode),
+	Directive(DirectiveAstNode),
+	Extern(ExternAstNode),
+	Comment(CommentAstNode),
+	Error(ErrorAstNode),
 }
 
 impl AstNode {
@@ -400,6 +1009,8 @@ impl AstNode {
 		match self {
 			AstNode::Iden(node) => node.print(f),
 			AstNode::Num(node) => node.print(f),
+			AstNode::Float(node) => node.print(f),
+			AstNode::Str(node) => node.print(f),
 			AstNode::Call(node) => node.print(f, indent_sz),
 			AstNode::Arith(node) => node.print(f),
 			AstNode::Relop(node) => node.print(f),
@@ -409,23 +1020,64 @@ impl AstNode {
 			AstNode::Goto(node) => node.print(f, indent_sz),
 			AstNode::Label(node) => node.print(f, indent_sz),
 			AstNode::If(node) => node.print(f, indent_sz),
+			AstNode::Switch(node) => node.print(f, indent_sz),
+			AstNode::While(node) => node.print(f, indent_sz),
+			AstNode::IfBlock(node) => node.print(f, indent_sz),
+			AstNode::LoopJump(node) => node.print(f, indent_sz),
 			AstNode::Ret(node) => node.print(f, indent_sz),
+			AstNode::Alloca(node) => node.print(f),
+			AstNode::FuncAddr(node) => node.print(f),
+			AstNode::Load(node) => node.print(f),
+			AstNode::Store(node) => node.print(f, indent_sz),
+			AstNode::Global(node) => node.print(f, indent_sz),
+			AstNode::Const(node) => node.print(f, indent_sz),
+			AstNode::Import(node) => node.print(f, indent_sz),
+			AstNode::Directive(node) => node.print(f, indent_sz),
+			AstNode::Extern(node) => node.print(f, indent_sz),
+			AstNode::Comment(node) => node.print(f, indent_sz),
+			AstNode::Error(node) => node.print(f, indent_sz),
 		}
 	}
-	pub fn is_terminator(&self) -> bool {
+	/// Type of the value an operand or expression evaluates to; only meaningful
+	/// once type_check_pass has run.
+	pub fn ty(&self) -> Type {
 		match self {
-			AstNode::Goto(_) => true,
-			AstNode::Label(_) => true,
-			AstNode::If(_) => true,
-			_ => false,
+			AstNode::Iden(node) => node.ty,
+			AstNode::Num(node) => node.ty,
+			AstNode::Float(node) => node.ty,
+			AstNode::Str(_) => Type::Ptr,
+			AstNode::Arith(node) => node.lhs.ty(),
+			AstNode::Relop(_) => Type::I1,
+			AstNode::Unary(node) if node.op == UnaryOp::Not => Type::I1,
+			AstNode::Unary(node) => node.var.ty(),
+			AstNode::Call(node) => node.ty.unwrap_or(Type::I32),
+			AstNode::Assignment(node) => node.ty.unwrap_or(Type::I32),
+			AstNode::Alloca(_) => Type::Ptr,
+			AstNode::FuncAddr(_) => Type::Ptr,
+			AstNode::Load(node) => node.ty,
+			AstNode::Global(node) => node.ty.unwrap_or(Type::I32),
+			_ => Type::I32,
 		}
 	}
+	pub fn is_comment(&self) -> bool {
+		matches!(self, AstNode::Comment(_))
+	}
+	pub fn is_terminator(&self) -> bool {
+		matches!(self, AstNode::Goto(_) | AstNode::Label(_) | AstNode::If(_) | AstNode::Switch(_) | AstNode::LoopJump(_))
+	}
 	pub fn dependencies(&self) -> Vec<String> {
 		match self {
 			AstNode::Iden(_) => vec![],
 			AstNode::Num(_) => vec![],
+			AstNode::Float(_) => vec![],
+			AstNode::Str(_) => vec![],
 			AstNode::Call(node) => {
 				let mut res: Vec<String> = Vec::new();
+				if let Callee::Indirect(ref ptr) = node.callee {
+					if let AstNode::Iden(ref iden) = **ptr {
+						res.push(iden.name.clone());
+					}
+				}
 				for param in node.params.iter() {
 					if let AstNode::Iden(iden) = param {
 						res.push(iden.name.clone());
@@ -477,20 +1129,70 @@ impl AstNode {
 				}
 				res
 			},
+			AstNode::Switch(node) => {
+				let mut res: Vec<String> = Vec::new();
+				if let AstNode::Iden(ref iden) = *node.scrutinee {
+					res.push(iden.name.clone());
+				}
+				res
+			},
+			AstNode::While(node) => {
+				let mut res: Vec<String> = Vec::new();
+				if let AstNode::Iden(ref iden) = *node.condition {
+					res.push(iden.name.clone());
+				}
+				res
+			},
+			AstNode::IfBlock(node) => {
+				let mut res: Vec<String> = Vec::new();
+				if let AstNode::Iden(ref iden) = *node.condition {
+					res.push(iden.name.clone());
+				}
+				res
+			},
+			AstNode::LoopJump(_) => vec![],
 			AstNode::Ret(node) => {
 				let mut res: Vec<String> = Vec::new();
+				if let Some(AstNode::Iden(iden)) = node.var.as_deref() {
+					res.push(iden.name.clone());
+				}
+				res
+			},
+			AstNode::Alloca(_) => vec![],
+			AstNode::FuncAddr(_) => vec![],
+			AstNode::Load(node) => {
+				let mut res: Vec<String> = Vec::new();
+				if let AstNode::Iden(ref iden) = *node.ptr {
+					res.push(iden.name.clone());
+				}
+				res
+			},
+			AstNode::Store(node) => {
+				let mut res: Vec<String> = Vec::new();
+				if let AstNode::Iden(ref iden) = *node.ptr {
+					res.push(iden.name.clone());
+				}
 				if let AstNode::Iden(ref iden) = *node.var {
 					res.push(iden.name.clone());
 				}
 				res
 			},
+			AstNode::Global(_) => vec![],
+			AstNode::Const(_) => vec![],
+			AstNode::Import(_) => vec![],
+			AstNode::Directive(_) => vec![],
+			AstNode::Extern(_) => vec![],
+			AstNode::Comment(_) => vec![],
+			AstNode::Error(_) => vec![],
 		}
 	}
 	pub fn production(&self) -> Option<String> {
 		match self {
 			AstNode::Iden(_) => None,
 			AstNode::Num(_) => None,
-			AstNode::Call(node) => Some(node.id.clone()),
+			AstNode::Float(_) => None,
+			AstNode::Str(_) => None,
+			AstNode::Call(node) => node.id.clone(),
 			AstNode::Arith(_) => None,
 			AstNode::Relop(_) => None,
 			AstNode::Unary(_) => None,
@@ -499,57 +1201,132 @@ impl AstNode {
 			AstNode::Goto(_) => None,
 			AstNode::Label(_) => None,
 			AstNode::If(_) => None,
+			AstNode::Switch(_) => None,
+			AstNode::While(_) => None,
+			AstNode::IfBlock(_) => None,
+			AstNode::LoopJump(_) => None,
 			AstNode::Ret(_) => None,
+			AstNode::Alloca(_) => None,
+			AstNode::FuncAddr(_) => None,
+			AstNode::Load(_) => None,
+			AstNode::Store(_) => None,
+			AstNode::Global(_) => None,
+			AstNode::Const(_) => None,
+			AstNode::Import(_) => None,
+			AstNode::Directive(_) => None,
+			AstNode::Extern(_) => None,
+			AstNode::Comment(_) => None,
+			AstNode::Error(_) => None,
 		}
 	}
 	pub fn evaluate(&self) -> Value {
 		match self {
 			AstNode::Iden(_) => Value::Nac,
 			AstNode::Num(c) => Value::Constant(c.num),
+			AstNode::Float(c) => Value::Float(c.num),
+			AstNode::Str(_) => Value::Nac,
 			AstNode::Call(_) => Value::Nac,
 			AstNode::Arith(node) => {
-				if let (Value::Constant(c1), Value::Constant(c2)) = (node.lhs.evaluate(), node.rhs.evaluate()) {
+				if let (Value::Float(c1), Value::Float(c2)) = (node.lhs.evaluate(), node.rhs.evaluate()) {
+					let ty: Type = node.lhs.ty();
+					let c: f64 = ty.round(match node.op {
+						ArithOp::Sum => c1 + c2,
+						ArithOp::Sub => c1 - c2,
+						ArithOp::Mul => c1 * c2,
+						ArithOp::Div => c1 / c2,
+						// rejected by type_check_pass
+						_ => f64::NAN,
+					});
+					if c.is_nan() {Value::Nac} else {Value::Float(c)}
+				} else if let (Value::Constant(c1), Value::Constant(c2)) = (node.lhs.evaluate(), node.rhs.evaluate()) {
+					let ty: Type = node.lhs.ty();
 					match node.op {
-						ArithOp::Sum => Value::Constant(c1+c2),
-						ArithOp::Sub => Value::Constant(c1-c2),
-						ArithOp::Mul => Value::Constant(c1*c2),
-						ArithOp::Div => Value::Constant(c1/c2),
+						ArithOp::Sum => Value::Constant(ty.wrap(c1.wrapping_add(c2))),
+						ArithOp::Sub => Value::Constant(ty.wrap(c1.wrapping_sub(c2))),
+						ArithOp::Mul => Value::Constant(ty.wrap(c1.wrapping_mul(c2))),
+						// division by zero and `min / -1` are left to the target;
+						// the former is reported by constant_propagation_pass
+						ArithOp::Div => if c2 == 0 || (c1 == ty.min() && c2 == -1) {Value::Nac} else {
+							Value::Constant(ty.wrap(c1 / c2))
+						},
+						ArithOp::Rem => if c2 == 0 || (c1 == ty.min() && c2 == -1) {Value::Nac} else {
+							Value::Constant(ty.wrap(c1 % c2))
+						},
+						ArithOp::And => Value::Constant(ty.wrap(c1 & c2)),
+						ArithOp::Or => Value::Constant(ty.wrap(c1 | c2)),
+						ArithOp::Xor => Value::Constant(ty.wrap(c1 ^ c2)),
+						// shift counts are taken modulo the width of the type
+						ArithOp::Shl => Value::Constant(ty.wrap(c1 << (c2 & (ty.bits() as i64 - 1)))),
+						ArithOp::Shr => Value::Constant(ty.wrap(c1 >> (c2 & (ty.bits() as i64 - 1)))),
+						ArithOp::LShr => Value::Constant(ty.wrap(
+							((c1 as u64 & ty.mask()) >> (c2 & (ty.bits() as i64 - 1))) as i64)),
 					}
 				} else {Value::Nac}
 			},
 			AstNode::Relop(node) => {
-				if let (Value::Constant(c1), Value::Constant(c2)) = (node.lhs.evaluate(), node.rhs.evaluate()) {
+				if let (Value::Float(c1), Value::Float(c2)) = (node.lhs.evaluate(), node.rhs.evaluate()) {
 					match node.op {
-						RelOp::Eq => Value::Constant((c1 == c2) as i32),
-						RelOp::Neq => Value::Constant((c1 != c2) as i32),
-						RelOp::Le => Value::Constant((c1 < c2) as i32),
-						RelOp::Ge => Value::Constant((c1 > c2) as i32),
-						RelOp::Lt => Value::Constant((c1 <= c2) as i32),
-						RelOp::Gt => Value::Constant((c1 >= c2) as i32),
+						RelOp::Eq => Value::Constant((c1 == c2) as i64),
+						RelOp::Neq => Value::Constant((c1 != c2) as i64),
+						RelOp::Le => Value::Constant((c1 <= c2) as i64),
+						RelOp::Ge => Value::Constant((c1 >= c2) as i64),
+						RelOp::Lt => Value::Constant((c1 < c2) as i64),
+						RelOp::Gt => Value::Constant((c1 > c2) as i64),
 					}
-				} else {Value::Nac}
-			},
-			AstNode::Unary(node) => {
-				if let Value::Constant(c) = node.var.evaluate() {
+				} else if let (Value::Constant(c1), Value::Constant(c2)) = (node.lhs.evaluate(), node.rhs.evaluate()) {
 					match node.op {
-						UnaryOp::Neg => Value::Constant(-c),
+						RelOp::Eq => Value::Constant((c1 == c2) as i64),
+						RelOp::Neq => Value::Constant((c1 != c2) as i64),
+						RelOp::Le => Value::Constant((c1 <= c2) as i64),
+						RelOp::Ge => Value::Constant((c1 >= c2) as i64),
+						RelOp::Lt => Value::Constant((c1 < c2) as i64),
+						RelOp::Gt => Value::Constant((c1 > c2) as i64),
 					}
 				} else {Value::Nac}
 			},
+			AstNode::Unary(node) => {
+				match (node.var.evaluate(), &node.op) {
+					(Value::Constant(c), UnaryOp::Neg) => Value::Constant(node.var.ty().wrap(c.wrapping_neg())),
+					(Value::Float(c), UnaryOp::Neg) => Value::Float(-c),
+					(Value::Constant(c), UnaryOp::Not) => Value::Constant((c == 0) as i64),
+					(Value::Constant(c), UnaryOp::BitNot) => Value::Constant(node.var.ty().wrap(!c)),
+					_ => Value::Nac,
+				}
+			},
 			AstNode::Function(_) => Value::Nac,
 			AstNode::Assignment(node) => node.var.evaluate(),
 			AstNode::Goto(_) => Value::Nac,
 			AstNode::Label(_) => Value::Nac,
 			AstNode::If(_) => Value::Nac,
-			AstNode::Ret(node) => node.var.evaluate(),
+			AstNode::Switch(_) => Value::Nac,
+			AstNode::While(_) => Value::Nac,
+			AstNode::IfBlock(_) => Value::Nac,
+			AstNode::LoopJump(_) => Value::Nac,
+			AstNode::Ret(node) => node.var.as_ref().map_or(Value::Nac, |var| var.evaluate()),
+			// memory is not tracked, loads may observe any store
+			AstNode::Alloca(_) => Value::Nac,
+			AstNode::FuncAddr(_) => Value::Nac,
+			AstNode::Load(_) => Value::Nac,
+			AstNode::Store(_) => Value::Nac,
+			AstNode::Global(_) => Value::Nac,
+			AstNode::Const(_) => Value::Nac,
+			AstNode::Import(_) => Value::Nac,
+			AstNode::Directive(_) => Value::Nac,
+			AstNode::Extern(_) => Value::Nac,
+			AstNode::Comment(_) => Value::Nac,
+			AstNode::Error(_) => Value::Nac,
 		}
 	}
 	pub fn reduced_version(&self, state: &HashMap<String, Value>) -> AstNode {
 		match self {
-			AstNode::Iden(node) => if let Some(Value::Constant(c)) = state.get(&node.name) {
-				AstNode::Num(NumAstNode{num: *c, loc: node.loc.clone()})
-			} else {self.clone()},
+			AstNode::Iden(node) => match state.get(&node.name) {
+				Some(Value::Constant(c)) => AstNode::Num(NumAstNode{num: *c, ty: node.ty, loc: node.loc.clone()}),
+				Some(Value::Float(c)) => AstNode::Float(FloatAstNode{num: *c, ty: node.ty, loc: node.loc.clone()}),
+				_ => self.clone(),
+			},
 			AstNode::Num(_) => self.clone(),
+			AstNode::Float(_) => self.clone(),
+			AstNode::Str(_) => self.clone(),
 			AstNode::Call(_) => self.clone(),
 			AstNode::Arith(node) => {
 				let mut res = node.clone();
@@ -577,11 +1354,36 @@ impl AstNode {
 			AstNode::Goto(_) => self.clone(),
 			AstNode::Label(_) => self.clone(),
 			AstNode::If(_) => self.clone(),
+			AstNode::Switch(node) => {
+				let mut res = node.clone();
+				*res.scrutinee = res.scrutinee.reduced_version(state);
+				AstNode::Switch(*Box::new(res))
+			},
+			AstNode::While(_) => self.clone(),
+			AstNode::IfBlock(_) => self.clone(),
+			AstNode::LoopJump(_) => self.clone(),
 			AstNode::Ret(node) => {
 				let mut res = node.clone();
-				*res.var = res.var.reduced_version(state);
+				if let Some(var) = res.var.as_mut() {
+					**var = var.reduced_version(state);
+				}
 				AstNode::Ret(*Box::new(res))
 			},
+			AstNode::Alloca(_) => self.clone(),
+			AstNode::FuncAddr(_) => self.clone(),
+			AstNode::Load(_) => self.clone(),
+			AstNode::Store(node) => {
+				let mut res = node.clone();
+				*res.var = res.var.reduced_version(state);
+				AstNode::Store(*Box::new(res))
+			},
+			AstNode::Global(_) => self.clone(),
+			AstNode::Const(_) => self.clone(),
+			AstNode::Import(_) => self.clone(),
+			AstNode::Directive(_) => self.clone(),
+			AstNode::Extern(_) => self.clone(),
+			AstNode::Comment(_) => self.clone(),
+			AstNode::Error(_) => self.clone(),
 		}
 	}
 	pub fn reduce(&mut self, state: &HashMap<String, Value>) {
@@ -591,7 +1393,13 @@ impl AstNode {
 		match self {
 			AstNode::Iden(_) => {},
 			AstNode::Num(_) => {},
-			AstNode::Call(node) => {state.insert(node.id.clone(), Value::Nac);},
+			AstNode::Float(_) => {},
+			AstNode::Str(_) => {},
+			AstNode::Call(node) => {
+				if let Some(id) = &node.id {
+					state.insert(id.clone(), Value::Nac);
+				}
+			},
 			AstNode::Arith(_) => {},
 			AstNode::Relop(_) => {},
 			AstNode::Unary(_) => {},
@@ -602,14 +1410,36 @@ impl AstNode {
 			AstNode::Goto(_) => {},
 			AstNode::Label(_) => {},
 			AstNode::If(_) => {},
+			AstNode::Switch(_) => {},
+			AstNode::While(_) => {},
+			AstNode::IfBlock(_) => {},
+			AstNode::LoopJump(_) => {},
 			AstNode::Ret(_) => {},
+			AstNode::Alloca(_) => {},
+			AstNode::FuncAddr(_) => {},
+			AstNode::Load(_) => {},
+			AstNode::Store(_) => {},
+			AstNode::Global(_) => {},
+			AstNode::Const(_) => {},
+			AstNode::Import(_) => {},
+			AstNode::Directive(_) => {},
+			AstNode::Extern(_) => {},
+			AstNode::Comment(_) => {},
+			AstNode::Error(_) => {},
 		};
 	}
 	pub fn value_operands(&self) -> HashSet<String> {
 		match self {
 			AstNode::Iden(iden_node) => HashSet::from([iden_node.name.clone()]),
 			AstNode::Num(_) => HashSet::new(),
-			AstNode::Call(call_node) => HashSet::from([call_node.id.clone()]),
+			AstNode::Float(_) => HashSet::new(),
+			AstNode::Str(_) => HashSet::new(),
+			AstNode::Call(call_node) => {
+				let mut res: HashSet<String> = call_node.id.iter().cloned().collect();
+				if let Callee::Indirect(ptr) = &call_node.callee {res.extend(ptr.value_operands())}
+				for param in call_node.params.iter() {res.extend(param.value_operands())}
+				res
+			},
 			AstNode::Arith(arith_node) => {
 				let mut res = arith_node.lhs.value_operands();
 				res.extend(arith_node.rhs.value_operands());
@@ -639,13 +1469,43 @@ impl AstNode {
 				res
 			},
 			AstNode::If(if_node) => if_node.condition.value_operands(),
-			AstNode::Ret(ret_node) => ret_node.var.value_operands(),
+			AstNode::Switch(switch_node) => switch_node.scrutinee.value_operands(),
+			AstNode::While(while_node) => {
+				let mut res = while_node.condition.value_operands();
+				for node in while_node.body.iter() {res.extend(node.value_operands())}
+				res
+			},
+			AstNode::IfBlock(if_node) => {
+				let mut res = if_node.condition.value_operands();
+				for node in if_node.then_body.iter() {res.extend(node.value_operands())}
+				for node in if_node.else_body.iter().flatten() {res.extend(node.value_operands())}
+				res
+			},
+			AstNode::LoopJump(_) => HashSet::new(),
+			AstNode::Ret(ret_node) => ret_node.var.as_ref().map_or(HashSet::new(), |var| var.value_operands()),
+			AstNode::Alloca(_) => HashSet::new(),
+			AstNode::FuncAddr(_) => HashSet::new(),
+			AstNode::Load(load_node) => load_node.ptr.value_operands(),
+			AstNode::Store(store_node) => {
+				let mut res = store_node.ptr.value_operands();
+				res.extend(store_node.var.value_operands());
+				res
+			},
+			AstNode::Global(_) => HashSet::new(),
+			AstNode::Const(_) => HashSet::new(),
+			AstNode::Import(_) => HashSet::new(),
+			AstNode::Directive(_) => HashSet::new(),
+			AstNode::Extern(_) => HashSet::new(),
+			AstNode::Comment(_) => HashSet::new(),
+			AstNode::Error(_) => HashSet::new(),
 		}
 	}
 	pub fn loc(&self) -> Loc {
 		match self {
 			AstNode::Iden(node) => node.loc.clone(),
 			AstNode::Num(node) => node.loc.clone(),
+			AstNode::Float(node) => node.loc.clone(),
+			AstNode::Str(node) => node.loc.clone(),
 			AstNode::Call(node) => node.loc.clone(),
 			AstNode::Arith(node) => node.loc.clone(),
 			AstNode::Relop(node) => node.loc.clone(),
@@ -655,7 +1515,22 @@ impl AstNode {
 			AstNode::Goto(node) => node.loc.clone(),
 			AstNode::Label(node) => node.loc.clone(),
 			AstNode::If(node) => node.loc.clone(),
+			AstNode::Switch(node) => node.loc.clone(),
+			AstNode::While(node) => node.loc.clone(),
+			AstNode::IfBlock(node) => node.loc.clone(),
+			AstNode::LoopJump(node) => node.loc.clone(),
 			AstNode::Ret(node) => node.loc.clone(),
+			AstNode::Alloca(node) => node.loc.clone(),
+			AstNode::FuncAddr(node) => node.loc.clone(),
+			AstNode::Load(node) => node.loc.clone(),
+			AstNode::Store(node) => node.loc.clone(),
+			AstNode::Global(node) => node.loc.clone(),
+			AstNode::Const(node) => node.loc.clone(),
+			AstNode::Import(node) => node.loc.clone(),
+			AstNode::Directive(node) => node.loc.clone(),
+			AstNode::Extern(node) => node.loc.clone(),
+			AstNode::Comment(node) => node.loc.clone(),
+			AstNode::Error(node) => node.loc.clone(),
 		}
 	}
 }
@@ -0,0 +1,456 @@
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use crate::fe::loc::Loc;
+
+const TAB_WIDTH: usize = 4;
+const BOLD: &str = "\x1b[1m";
+const BLUE: &str = "\x1b[1;34m";
+
+#[derive(Clone, PartialEq)]
+pub enum Severity {
+	Error,
+	Warning,
+}
+
+impl Severity {
+	fn color(&self) -> &'static str {
+		match self {
+			Severity::Error => "\x1b[1;31m",
+			Severity::Warning => "\x1b[1;33m",
+		}
+	}
+}
+
+impl std::fmt::Display for Severity {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{}", match self {
+			Severity::Error => "error",
+			Severity::Warning => "warning",
+		})
+	}
+}
+
+#[derive(Clone, PartialEq)]
+pub enum ErrorFormat {
+	Human,
+	Json,
+	Sarif,
+}
+
+#[derive(Clone)]
+pub struct Label {
+	pub loc: Loc,
+	pub message: String,
+}
+
+#[derive(Clone)]
+pub struct DiagnosticData {
+	pub severity: Severity,
+	pub code: Option<String>,
+	pub message: String,
+	pub loc: Loc,
+	pub label: Option<String>,
+	pub secondary_labels: Vec<Label>,
+	pub notes: Vec<String>,
+	pub helps: Vec<String>,
+	pub pass: Option<String>,
+}
+
+/// Boxed so that the results failing with a diagnostic stay small.
+#[derive(Clone)]
+pub struct Diagnostic(Box<DiagnosticData>);
+
+impl Deref for Diagnostic {
+	type Target = DiagnosticData;
+	fn deref(&self) -> &DiagnosticData {
+		&self.0
+	}
+}
+
+impl DerefMut for Diagnostic {
+	fn deref_mut(&mut self) -> &mut DiagnosticData {
+		&mut self.0
+	}
+}
+
+struct Style {
+	color: bool,
+}
+
+impl Style {
+	fn paint(&self, code: &str, text: &str) -> String {
+		if self.color {format!("{}{}\x1b[0m", code, text)} else {text.to_string()}
+	}
+}
+
+struct Annotation<'a> {
+	loc: &'a Loc,
+	message: String,
+	mark: char,
+	color: &'static str,
+}
+
+fn expand_tabs(line: &str) -> String {
+	line.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+/// Display width of the first `chars` characters of `line`.
+fn display_width(line: &str, chars: usize) -> usize {
+	line.chars().take(chars).map(|c| if c == '\t' {TAB_WIDTH} else {1}).sum()
+}
+
+impl Diagnostic {
+	pub fn new(severity: Severity, loc: Loc, message: String) -> Self {
+		Self(Box::new(DiagnosticData{severity, code: None, message, loc, label: None, secondary_labels: Vec::new(),
+									 notes: Vec::new(), helps: Vec::new(), pass: None}))
+	}
+	pub fn with_code(mut self, code: &str) -> Self {
+		self.code = Some(code.to_string());
+		self
+	}
+	pub fn with_label(mut self, message: String) -> Self {
+		self.label = Some(message);
+		self
+	}
+	pub fn with_secondary_label(mut self, loc: Loc, message: String) -> Self {
+		self.secondary_labels.push(Label{loc, message});
+		self
+	}
+	pub fn with_note(mut self, note: String) -> Self {
+		self.notes.push(note);
+		self
+	}
+	pub fn with_help(mut self, help: String) -> Self {
+		self.helps.push(help);
+		self
+	}
+	pub fn is_error(&self) -> bool {
+		self.severity == Severity::Error
+	}
+	pub fn render(&self, sources: &HashMap<String, Vec<String>>, color: bool) -> String {
+		let style: Style = Style{color};
+		let mut annotations: Vec<Annotation> = Vec::new();
+		if !self.loc.is_null() {
+			annotations.push(Annotation{loc: &self.loc, message: self.label.clone().unwrap_or_default(),
+										mark: '^', color: self.severity.color()});
+		}
+		for label in self.secondary_labels.iter().filter(|label| !label.loc.is_null()) {
+			annotations.push(Annotation{loc: &label.loc, message: label.message.clone(),
+										mark: '-', color: BLUE});
+		}
+		let gutter_sz: usize = annotations.iter()
+			.map(|annotation| annotation.loc.row.to_string().len()).max().unwrap_or(1);
+		let gutter: String = " ".repeat(gutter_sz);
+
+		let mut out: String = String::new();
+		let severity: String = match &self.code {
+			Some(code) => format!("{}[{}]", self.severity, code),
+			None => self.severity.to_string(),
+		};
+		out += &format!("{}{}\n", style.paint(self.severity.color(), &severity),
+						style.paint(BOLD, &format!(": {}", self.message)));
+		if self.loc.is_null() && !self.loc.filepath.is_empty() {
+			out += &format!("{}{} {}\n", gutter, style.paint(BLUE, "-->"), self.loc.filepath);
+		}
+
+		// one snippet per file, the primary span's file first
+		let mut filepaths: Vec<&String> = Vec::new();
+		for annotation in annotations.iter() {
+			if !filepaths.contains(&&annotation.loc.filepath) {
+				filepaths.push(&annotation.loc.filepath);
+			}
+		}
+		for (ix, filepath) in filepaths.iter().enumerate() {
+			let mut file_annotations: Vec<&Annotation> = annotations.iter()
+				.filter(|annotation| &annotation.loc.filepath == *filepath).collect();
+			file_annotations.sort_by_key(|annotation| (annotation.loc.row, annotation.loc.col));
+			let first_loc: &Loc = if ix == 0 {&self.loc} else {file_annotations[0].loc};
+			out += &format!("{}{} {}\n", gutter, style.paint(BLUE, if ix == 0 {"-->"} else {":::"}), first_loc);
+			let lines: &Vec<String> = match sources.get(*filepath) {
+				Some(lines) => lines,
+				None => continue,
+			};
+			out += &format!("{} {}\n", gutter, style.paint(BLUE, "|"));
+			let mut prev_row: Option<usize> = None;
+			for annotation in file_annotations.iter() {
+				let row: usize = annotation.loc.row;
+				let line: &String = match lines.get(row-1) {
+					Some(line) => line,
+					None => continue,
+				};
+				if prev_row != Some(row) {
+					if prev_row.is_some_and(|prev_row| row > prev_row+1) {
+						out += &format!("{}\n", style.paint(BLUE, "..."));
+					}
+					out += &format!("{} {} {}\n", style.paint(BLUE, &format!("{:>1$}", row, gutter_sz)),
+									style.paint(BLUE, "|"), expand_tabs(line));
+					prev_row = Some(row);
+				}
+				let start: usize = display_width(line, annotation.loc.col-1);
+				let end: usize = if annotation.loc.end_row == row {
+					display_width(line, annotation.loc.end_col-1)
+				} else {
+					display_width(line, line.chars().count())
+				};
+				let marks: String = annotation.mark.to_string().repeat(std::cmp::max(1, end.saturating_sub(start)));
+				out += &format!("{} {} {}{}\n", gutter, style.paint(BLUE, "|"), " ".repeat(start),
+								style.paint(annotation.color, format!("{} {}", marks, annotation.message).trim_end()));
+			}
+		}
+
+		if !annotations.is_empty() && (!self.notes.is_empty() || !self.helps.is_empty() || self.pass.is_some()) {
+			out += &format!("{} {}\n", gutter, style.paint(BLUE, "|"));
+		}
+		for note in self.notes.iter() {
+			out += &format!("{} {} {}\n", gutter, style.paint(BLUE, "="), style.paint(BOLD, &format!("note: {}", note)));
+		}
+		for help in self.helps.iter() {
+			out += &format!("{} {} {}\n", gutter, style.paint(BLUE, "="), style.paint(BOLD, &format!("help: {}", help)));
+		}
+		if let Some(pass) = &self.pass {
+			out += &format!("{} {} {}\n", gutter, style.paint(BLUE, "="),
+							style.paint(BOLD, &format!("note: reported by '{}'", pass)));
+		}
+		out
+	}
+}
+
+fn json_string(text: &str) -> String {
+	let mut res: String = String::from("\"");
+	for c in text.chars() {
+		match c {
+			'"' => res.push_str("\\\""),
+			'\\' => res.push_str("\\\\"),
+			'\n' => res.push_str("\\n"),
+			'\r' => res.push_str("\\r"),
+			'\t' => res.push_str("\\t"),
+			c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+			c => res.push(c),
+		}
+	}
+	res.push('"');
+	res
+}
+
+fn json_option(text: &Option<String>) -> String {
+	match text {
+		Some(text) => json_string(text),
+		None => String::from("null"),
+	}
+}
+
+fn json_array(items: &[String]) -> String {
+	format!("[{}]", items.join(","))
+}
+
+fn json_span(loc: &Loc) -> String {
+	if loc.is_null() {
+		return String::from("null");
+	}
+	format!("{{\"file\":{},\"start\":{{\"line\":{},\"column\":{}}},\"end\":{{\"line\":{},\"column\":{}}}}}",
+			json_string(&loc.filepath), loc.row, loc.col, loc.end_row, loc.end_col)
+}
+
+fn sarif_location(loc: &Loc, message: Option<&String>) -> String {
+	let mut location: String = format!("{{\"physicalLocation\":{{\"artifactLocation\":{{\"uri\":{}}}",
+									   json_string(&loc.filepath));
+	if !loc.is_null() {
+		location += &format!(",\"region\":{{\"startLine\":{},\"startColumn\":{},\"endLine\":{},\"endColumn\":{}}}",
+							 loc.row, loc.col, loc.end_row, loc.end_col);
+	}
+	location += "}";
+	if let Some(message) = message {
+		location += &format!(",\"message\":{{\"text\":{}}}", json_string(message));
+	}
+	location + "}"
+}
+
+impl Diagnostic {
+	/// Single line JSON record of this diagnostic.
+	pub fn to_json(&self) -> String {
+		let labels: Vec<String> = self.secondary_labels.iter()
+			.map(|label| format!("{{\"span\":{},\"message\":{}}}", json_span(&label.loc), json_string(&label.message)))
+			.collect();
+		let notes: Vec<String> = self.notes.iter().map(|note| json_string(note)).collect();
+		let helps: Vec<String> = self.helps.iter().map(|help| json_string(help)).collect();
+		format!("{{\"severity\":{},\"code\":{},\"message\":{},\"pass\":{},\"file\":{},\"span\":{},\"label\":{},\"labels\":{},\"notes\":{},\"helps\":{}}}",
+				json_string(&self.severity.to_string()), json_option(&self.code), json_string(&self.message),
+				json_option(&self.pass), json_string(&self.loc.filepath), json_span(&self.loc),
+				json_option(&self.label), json_array(&labels), json_array(&notes), json_array(&helps))
+	}
+	fn to_sarif_result(&self) -> String {
+		let mut text: String = self.message.clone();
+		for note in self.notes.iter() {text += &format!("\nnote: {}", note);}
+		for help in self.helps.iter() {text += &format!("\nhelp: {}", help);}
+		let related: Vec<String> = self.secondary_labels.iter()
+			.map(|label| sarif_location(&label.loc, Some(&label.message))).collect();
+		format!("{{\"ruleId\":{},\"level\":{},\"message\":{{\"text\":{}}},\"locations\":[{}],\"relatedLocations\":{},\"properties\":{{\"pass\":{}}}}}",
+				json_option(&self.code), json_string(&self.severity.to_string()), json_string(&text),
+				sarif_location(&self.loc, self.label.as_ref()), json_array(&related), json_option(&self.pass))
+	}
+}
+
+#[derive(Default)]
+pub struct Diagnostics {
+	pub diagnostics: Vec<Diagnostic>,
+	sources: HashMap<String, Vec<String>>,
+}
+
+impl Diagnostics {
+	pub fn new() -> Self {
+		Self::default()
+	}
+	/// Registers source lines so diagnostics pointing into `filepath` can show snippets.
+	pub fn add_source(&mut self, filepath: String, lines: Vec<String>) {
+		self.sources.insert(filepath, lines);
+	}
+	pub fn push(&mut self, diagnostic: Diagnostic) {
+		self.diagnostics.push(diagnostic);
+	}
+	pub fn has_errors(&self) -> bool {
+		self.diagnostics.iter().any(|diagnostic| diagnostic.is_error())
+	}
+	pub fn set_pass_from(&mut self, ix: usize, pass: String) {
+		for diagnostic in self.diagnostics[ix..].iter_mut() {
+			if diagnostic.pass.is_none() {
+				diagnostic.pass = Some(pass.clone());
+			}
+		}
+	}
+	pub fn emit(&mut self, format: &ErrorFormat, color: bool) {
+		match format {
+			ErrorFormat::Human => for diagnostic in self.diagnostics.iter() {
+				eprintln!("{}", diagnostic.render(&self.sources, color));
+			},
+			ErrorFormat::Json => for diagnostic in self.diagnostics.iter() {
+				eprintln!("{}", diagnostic.to_json());
+			},
+			ErrorFormat::Sarif => {
+				let results: Vec<String> = self.diagnostics.iter()
+					.map(|diagnostic| diagnostic.to_sarif_result()).collect();
+				eprintln!("{{\"$schema\":\"https://json.schemastore.org/sarif-2.1.0.json\",\"version\":\"2.1.0\",\
+						   \"runs\":[{{\"tool\":{{\"driver\":{{\"name\":\"irl\",\"version\":\"{}\"}}}},\"results\":{}}}]}}",
+						  env!("CARGO_PKG_VERSION"), json_array(&results));
+			},
+		}
+		self.diagnostics.clear();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn sources(files: &[(&str, &[&str])]) -> HashMap<String, Vec<String>> {
+		files.iter().map(|(filepath, lines)| (filepath.to_string(), lines.iter().map(|line| line.to_string()).collect()))
+			.collect()
+	}
+
+	#[test]
+	fn renders_tabs_as_spaces() {
+		let diagnostic: Diagnostic = Loc::span(1, 2, 1, 3, String::from("test.irl"))
+			.error(String::from("unknown identifier 'x'"))
+			.with_code("E0202")
+			.with_label(String::from("not defined"));
+		assert_eq!(diagnostic.render(&sources(&[("test.irl", &["\tx = y + 1"])]), false), [
+			"error[E0202]: unknown identifier 'x'",
+			" --> test.irl:1:2",
+			"  |",
+			"1 |     x = y + 1",
+			"  |     ^ not defined",
+			"",
+		].join("\n"));
+	}
+
+	#[test]
+	fn renders_columns_in_characters() {
+		let diagnostic: Diagnostic = Loc::span(1, 12, 1, 13, String::from("test.irl"))
+			.error(String::from("unknown identifier 'z'"))
+			.with_label(String::from("not defined"));
+		assert_eq!(diagnostic.render(&sources(&[("test.irl", &["s = \"€€\" + z"])]), false), [
+			"error: unknown identifier 'z'",
+			" --> test.irl:1:12",
+			"  |",
+			"1 | s = \"€€\" + z",
+			"  |            ^ not defined",
+			"",
+		].join("\n"));
+	}
+
+	#[test]
+	fn renders_secondary_labels() {
+		let mut diagnostic: Diagnostic = Loc::span(12, 1, 12, 9, String::from("main.irl"))
+			.error(String::from("global already defined 'g'"))
+			.with_code("E0206")
+			.with_label(String::from("global redefined here"))
+			.with_secondary_label(Loc::span(2, 8, 2, 9, String::from("main.irl")), String::from("first defined here"))
+			.with_secondary_label(Loc::span(12, 8, 12, 9, String::from("main.irl")), String::from("named here"))
+			.with_secondary_label(Loc::span(1, 1, 1, 9, String::from("util.irl")), String::from("also here"))
+			.with_note(String::from("globals are shared"))
+			.with_help(String::from("rename one of them"));
+		diagnostic.pass = Some(String::from("asm_validation_pass"));
+		let mut main: Vec<&str> = vec![""; 12];
+		main[1] = "global g: i32 = 0";
+		main[11] = "global g: i32 = 1";
+		let sources: HashMap<String, Vec<String>> = sources(&[("main.irl", &main), ("util.irl", &["global g: i32 = 2"])]);
+		assert_eq!(diagnostic.render(&sources, false), [
+			"error[E0206]: global already defined 'g'",
+			"  --> main.irl:12:1",
+			"   |",
+			" 2 | global g: i32 = 0",
+			"   |        - first defined here",
+			"...",
+			"12 | global g: i32 = 1",
+			"   | ^^^^^^^^ global redefined here",
+			"   |        - named here",
+			"  ::: util.irl:1:1",
+			"   |",
+			" 1 | global g: i32 = 2",
+			"   | -------- also here",
+			"   |",
+			"   = note: globals are shared",
+			"   = help: rename one of them",
+			"   = note: reported by 'asm_validation_pass'",
+			"",
+		].join("\n"));
+	}
+
+	/// An error spanning two files, with all the parts the machine readable formats carry.
+	fn redefined_global() -> Diagnostic {
+		let mut diagnostic: Diagnostic = Loc::span(1, 1, 1, 18, String::from("util.irl"))
+			.error(String::from("global already defined 'g'"))
+			.with_code("E0206")
+			.with_label(String::from("global redefined here"))
+			.with_secondary_label(Loc::span(3, 1, 3, 18, String::from("main.irl")), String::from("global first defined here"))
+			.with_note(String::from("globals are \"shared\""))
+			.with_help(String::from("rename one of them"));
+		diagnostic.pass = Some(String::from("asm_validation_pass"));
+		diagnostic
+	}
+
+	#[test]
+	fn json_record() {
+		assert_eq!(redefined_global().to_json(), concat!(
+			r#"{"severity":"error","code":"E0206","message":"global already defined 'g'","pass":"asm_validation_pass","#,
+			r#""file":"util.irl","span":{"file":"util.irl","start":{"line":1,"column":1},"end":{"line":1,"column":18}},"#,
+			r#""label":"global redefined here","#,
+			r#""labels":[{"span":{"file":"main.irl","start":{"line":3,"column":1},"end":{"line":3,"column":18}},"message":"global first defined here"}],"#,
+			r#""notes":["globals are \"shared\""],"helps":["rename one of them"]}"#));
+		let diagnostic: Diagnostic = Loc::null().program_error(String::from("no\tentry"));
+		assert_eq!(diagnostic.to_json(), concat!(
+			r#"{"severity":"error","code":null,"message":"no\tentry","pass":null,"file":"","span":null,"#,
+			r#""label":null,"labels":[],"notes":[],"helps":[]}"#));
+	}
+
+	#[test]
+	fn sarif_result() {
+		assert_eq!(redefined_global().to_sarif_result(), concat!(
+			r#"{"ruleId":"E0206","level":"error","#,
+			r#""message":{"text":"global already defined 'g'\nnote: globals are \"shared\"\nhelp: rename one of them"},"#,
+			r#""locations":[{"physicalLocation":{"artifactLocation":{"uri":"util.irl"},"#,
+			r#""region":{"startLine":1,"startColumn":1,"endLine":1,"endColumn":18}},"message":{"text":"global redefined here"}}],"#,
+			r#""relatedLocations":[{"physicalLocation":{"artifactLocation":{"uri":"main.irl"},"#,
+			r#""region":{"startLine":3,"startColumn":1,"endLine":3,"endColumn":18}},"message":{"text":"global first defined here"}}],"#,
+			r#""properties":{"pass":"asm_validation_pass"}}"#));
+	}
+}
@@ -1,126 +1,413 @@
-use std::io::{prelude::*, BufReader};
-use std::collections::HashMap;
-use crate::fe::{token::{Token, TokenKind}, loc::Loc};
+use crate::fe::{token::*, loc::Loc, diagnostic::{Diagnostic, Diagnostics}};
 
 pub struct Lexer {
 	pub tokens: Vec<Token>
 }
 
 impl Lexer {
-	pub fn new(filepath: String) -> Self {
+	pub fn new(filepath: String, diagnostics: &mut Diagnostics) -> Self {
+		match std::fs::read_to_string(&filepath) {
+			Ok(source) => Self::from_source(&source, filepath, diagnostics),
+			Err(err) => {
+				diagnostics.push(Loc::new(0, 0, filepath.clone())
+								 .program_error(format!("could not read file; {}", err)).with_code("E0001"));
+				Self{tokens: vec![Token::new(TokenKind::Eof, Loc::new(0, 0, filepath))]}
+			},
+		}
+	}
+	pub fn from_source(source: &str, filepath: String, diagnostics: &mut Diagnostics) -> Self {
+		diagnostics.add_source(filepath.clone(), source.lines().map(String::from).collect());
 		let mut tokens: Vec<Token> = Vec::new();
-		let mut content: Vec<String> = BufReader
-			::new(std::fs::File::open(&filepath).expect("could not open file"))
-			.lines()
-			.map(|line| line.expect("could not parse file"))
-			.collect();
-		
-		if content.is_empty() {
-			return Self{tokens};
-		}
-
-		let mut row: usize = 0;
-		let mut col: usize = 0;
-
-		let operator_table: Vec<(String, TokenKind)> = vec![
-			(String::from("=="), TokenKind::Eq),
-			(String::from("="), TokenKind::Equal),
-			(String::from("+"), TokenKind::Plus),
-			(String::from("-"), TokenKind::Minus),
-			(String::from("*"), TokenKind::Mul),
-			(String::from("//"), TokenKind::Comment),
-			(String::from("/"), TokenKind::Div),
-			(String::from("!="), TokenKind::Neq),
-			(String::from(">="), TokenKind::Ge),
-			(String::from("<="), TokenKind::Le),
-			(String::from(">"), TokenKind::Gt),
-			(String::from("<"), TokenKind::Lt),
-			(String::from(","), TokenKind::Comma),
-			(String::from("("), TokenKind::LParen),
-			(String::from(")"), TokenKind::RParen),
-		];
-
-		let keyword_table: HashMap<String, TokenKind> = [
-			(String::from("function"), TokenKind::Function),
-		    (String::from("arg"), TokenKind::Arg),
-		    (String::from("goto"), TokenKind::Goto),
-		    (String::from("label"), TokenKind::Label),
-		    (String::from("if"), TokenKind::If),
-		    (String::from("param"), TokenKind::Param),
-		    (String::from("call"), TokenKind::Call),
-		    (String::from("ret"), TokenKind::Ret)
-		].iter().cloned().collect();
-
-		for i in 0..content.len() {
-			row = i+1;
-			col = 1;
-
-			while !content[i].is_empty() {
-
-				let mut l: usize = 0;
-				for c in content[i].chars() {
-					if c.is_whitespace() {
-						l += 1;
-					} else {
-						break;
-					}
-				}
-				content[i] = content[i][l..].to_string();
-				if content[i].is_empty() {
-					break;
-				}
-				col += l;
-				let loc: Loc = Loc::new(row, col, filepath.clone());
-
-				let mut flag: bool = false;
-				for (operator, token_kind) in &operator_table {
-					if content[i].starts_with(operator) {
-						tokens.push(Token::new(token_kind.clone(), loc.clone()));
-						content[i] = content[i][operator.len()..].to_string();
-						col += operator.len();
-						flag = true;
-						break;
-					}
-				}
-				if flag {
-					continue;
-				}
+		for token in Tokens::new(source, filepath) {
+			match token {
+				Ok(token) => tokens.push(token),
+				Err(diagnostic) => diagnostics.push(diagnostic),
+			}
+		}
+		Self{tokens}
+	}
+}
 
-				if content[i].chars().nth(0).unwrap().is_alphabetic() {
-					let mut keyword_sz: usize = 1;
-					while keyword_sz < content[i].len() &&
-						content[i].chars().nth(keyword_sz).unwrap().is_alphanumeric() {
-						keyword_sz += 1;
-					}
-					let keyword: String = content[i][0..keyword_sz].to_string();
-					match keyword_table.get(&keyword) {
-						Some(token_kind) => tokens.push(Token::new(token_kind.clone(), loc.clone())),
-						None => tokens.push(Token::new(TokenKind::Iden(keyword), loc.clone())),
-					}
-					content[i] = content[i][keyword_sz..].to_string();
-					col += keyword_sz;
-					continue;
-				}
+const OPERATOR_TABLE: &[(&str, TokenKind)] = &[
+	("==", TokenKind::Eq),
+	("=", TokenKind::Equal),
+	("+", TokenKind::Plus),
+	("->", TokenKind::Arrow),
+	("-", TokenKind::Minus),
+	("*", TokenKind::Mul),
+	("/", TokenKind::Div),
+	("%", TokenKind::Rem),
+	("&", TokenKind::And),
+	("|", TokenKind::Or),
+	("^", TokenKind::Xor),
+	("<<", TokenKind::Shl),
+	(">>>", TokenKind::LShr),
+	(">>", TokenKind::Shr),
+	("!=", TokenKind::Neq),
+	("!", TokenKind::Not),
+	("~", TokenKind::Tilde),
+	(">=", TokenKind::Ge),
+	("<=", TokenKind::Le),
+	(">", TokenKind::Gt),
+	("<", TokenKind::Lt),
+	(",", TokenKind::Comma),
+	(":", TokenKind::Colon),
+	("(", TokenKind::LParen),
+	(")", TokenKind::RParen),
+	("[", TokenKind::LBracket),
+	("]", TokenKind::RBracket),
+	("{", TokenKind::LBrace),
+	("}", TokenKind::RBrace),
+];
 
-				if content[i].chars().nth(0).unwrap().is_numeric() {
-					let mut num_sz: usize = 0;
-					let mut num: i32 = 0;
-					while num_sz < content[i].len() && content[i].chars().nth(num_sz).unwrap().is_numeric() {
-						num = num*10 + content[i].chars().nth(num_sz).unwrap() as i32 - '0' as i32;
-						num_sz += 1;
-					}
-					tokens.push(Token::new(TokenKind::Num(num), loc.clone()));
-					content[i] = content[i][num_sz..].to_string();
-					col += num_sz;
-					continue;
-				}
+/// Lazily lexes `source` with a byte offset cursor; every line ends with an
+/// `Eol` token and the stream ends with a single `Eof` token.
+pub struct Tokens<'a> {
+	source: &'a str,
+	filepath: String,
+	pos: usize,
+	row: usize,
+	col: usize,
+	/// whether the last token was an identifier, a number or a `)`, after which `%` is an operator
+	after_operand: bool,
+	done: bool,
+}
 
-				loc.error(format!("unexpected token '{}'", content[i].chars().nth(0).unwrap()));
+impl<'a> Tokens<'a> {
+	pub fn new(source: &'a str, filepath: String) -> Self {
+		Self{source, filepath, pos: 0, row: 1, col: 1, after_operand: false, done: false}
+	}
+	fn rest(&self) -> &'a str {
+		&self.source[self.pos..]
+	}
+	fn peek(&self) -> Option<char> {
+		self.rest().chars().next()
+	}
+	/// Advances the cursor over `len` bytes of the current line.
+	fn bump(&mut self, len: usize) {
+		self.col += self.source[self.pos..self.pos+len].chars().count();
+		self.pos += len;
+	}
+	fn span(&self, row: usize, col: usize) -> Loc {
+		Loc::span(row, col, self.row, self.col, self.filepath.clone())
+	}
+	fn take_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> &'a str {
+		let start: usize = self.pos;
+		let len: usize = self.rest().find(|c: char| !predicate(c)).unwrap_or(self.rest().len());
+		self.bump(len);
+		&self.source[start..start+len]
+	}
+	/// Integer literal with an optional `0x`, `0b` or `0o` prefix and `_` separators;
+	/// the sign is handled by the parser so only the magnitude is lexed here.
+	fn lex_num(&self, text: &str, row: usize, col: usize) -> Result<Token, Diagnostic> {
+		let loc: Loc = self.span(row, col);
+		let (radix, digits, base) = match text.get(..2) {
+			Some("0x") | Some("0X") => (16, &text[2..], "hexadecimal"),
+			Some("0b") | Some("0B") => (2, &text[2..], "binary"),
+			Some("0o") | Some("0O") => (8, &text[2..], "octal"),
+			_ => (10, text, "decimal"),
+		};
+		if let Some(digit) = digits.chars().find(|c| *c != '_' && !c.is_digit(radix)) {
+			return Err(loc.error(format!("invalid digit '{}' in {} literal", digit, base)).with_code("E0003")
+					   .with_label(format!("'{}' is not a base {} digit", digit, radix)));
+		}
+		let digits: String = digits.chars().filter(|c| *c != '_').collect();
+		if digits.is_empty() {
+			return Err(loc.error(format!("{} literal has no digits", base)).with_code("E0003"));
+		}
+		match u64::from_str_radix(&digits, radix) {
+			Ok(num) => Ok(Token::new(TokenKind::Num(num), loc)),
+			Err(_) => Err(loc.error(String::from("integer literal is too large")).with_code("E0003")
+						  .with_label(format!("'{}' does not fit in 64 bits", text))),
+		}
+	}
+	/// Length of the decimal float literal at the cursor, i.e. digits followed by a
+	/// fraction (`1.5`), an exponent (`1e9`, `2.5e-3`) or both; `None` for an integer.
+	fn float_len(&self) -> Option<usize> {
+		let rest: &[u8] = self.rest().as_bytes();
+		let digits = |ix: usize| ix + rest[ix..].iter().take_while(|b| b.is_ascii_digit() || **b == b'_').count();
+		let mut len: usize = digits(0);
+		let mut is_float: bool = false;
+		if rest.get(len) == Some(&b'.') && rest.get(len+1).is_some_and(|b| b.is_ascii_digit()) {
+			len = digits(len+1);
+			is_float = true;
+		}
+		if matches!(rest.get(len), Some(b'e') | Some(b'E')) {
+			let exponent: usize = if matches!(rest.get(len+1), Some(b'+') | Some(b'-')) {len+2} else {len+1};
+			if rest.get(exponent).is_some_and(|b| b.is_ascii_digit()) {
+				len = digits(exponent);
+				is_float = true;
 			}
-			tokens.push(Token::new(TokenKind::Eol, Loc::new(row, col, filepath.clone())));
 		}
-		tokens.push(Token::new(TokenKind::Eof, Loc::new(row, col, filepath.clone())));
+		is_float.then_some(len)
+	}
+	fn lex_float(&mut self, len: usize, row: usize, col: usize) -> Result<Token, Diagnostic> {
+		let start: usize = self.pos;
+		self.bump(len);
+		let text: &str = &self.source[start..self.pos];
+		let loc: Loc = self.span(row, col);
+		match text.replace('_', "").parse::<f64>() {
+			Ok(num) if num.is_finite() => Ok(Token::new(TokenKind::Float(num), loc)),
+			_ => Err(loc.error(String::from("float literal is out of range")).with_code("E0003")
+					 .with_label(format!("'{}' does not fit in f64", text))),
+		}
+	}
+	/// String literal closed on the same line; the token keeps the bytes, with the
+	/// escapes `\n`, `\t`, `\r`, `\0`, `\\`, `\"` and `\xNN` replaced by what they stand for.
+	fn lex_str(&mut self, row: usize, col: usize) -> Result<Token, Diagnostic> {
+		self.bump(1); // eat '"'
+		let mut bytes: Vec<u8> = Vec::new();
+		let mut error: Option<Diagnostic> = None;
+		loop {
+			let (escape_row, escape_col) = (self.row, self.col);
+			match self.peek() {
+				Some('"') => break,
+				None | Some('\n') => return Err(self.span(row, col).error(String::from("unterminated string literal"))
+					.with_code("E0006").with_help(String::from("close the string with '\"' on the same line"))),
+				Some('\\') => {
+					self.bump(1);
+					let hex: &str = self.rest().get(1..3)
+						.filter(|hex| hex.chars().all(|c| c.is_ascii_hexdigit())).unwrap_or("");
+					let (byte, len): (Option<u8>, usize) = match self.peek() {
+						Some('n') => (Some(b'\n'), 1),
+						Some('t') => (Some(b'\t'), 1),
+						Some('r') => (Some(b'\r'), 1),
+						Some('0') => (Some(0), 1),
+						Some('\\') => (Some(b'\\'), 1),
+						Some('"') => (Some(b'"'), 1),
+						Some('x') if hex.len() == 2 => (u8::from_str_radix(hex, 16).ok(), 3),
+						Some('\n') | None => (None, 0),
+						Some(c) => (None, c.len_utf8()),
+					};
+					self.bump(len);
+					match byte {
+						Some(byte) => bytes.push(byte),
+						// keep lexing up to the closing quote and report the first bad escape
+						None => {error.get_or_insert(self.span(escape_row, escape_col)
+							.error(String::from("unknown escape in string literal")).with_code("E0006")
+							.with_help(String::from("expected one of '\\n', '\\t', '\\r', '\\0', '\\\\', '\\\"' or '\\xNN'")));},
+					}
+				},
+				Some(c) => {
+					let mut buf: [u8; 4] = [0; 4];
+					bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+					self.bump(c.len_utf8());
+				},
+			}
+		}
+		self.bump(1); // eat '"'
+		match error {
+			Some(error) => Err(error),
+			None => Ok(Token::new(TokenKind::Str(bytes), self.span(row, col))),
+		}
+	}
+	/// `// ...` up to the end of the line or `/* ... */` possibly spanning lines;
+	/// the token keeps the comment verbatim, delimiters included.
+	fn lex_comment(&mut self, row: usize, col: usize) -> Result<Token, Diagnostic> {
+		let start: usize = self.pos;
+		if self.rest().starts_with("//") {
+			self.take_while(|c| c != '\n');
+			let text: &str = self.source[start..self.pos].trim_end();
+			return Ok(Token::new(TokenKind::Comment(text.to_string()), self.span(row, col)));
+		}
+		let len: usize = match self.rest()[2..].find("*/") {
+			Some(len) => len + 4,
+			// leave the final newline so the line still ends with an `Eol`
+			None => self.rest().trim_end().len(),
+		};
+		for c in self.source[self.pos..self.pos+len].chars() {
+			if c == '\n' {
+				self.row += 1;
+				self.col = 1;
+			} else {
+				self.col += 1;
+			}
+		}
+		self.pos += len;
+		let text: &str = &self.source[start..self.pos];
+		if len < 4 || !text.ends_with("*/") {
+			return Err(Loc::span(row, col, row, col+2, self.filepath.clone())
+					   .error(String::from("unterminated block comment")).with_code("E0005")
+					   .with_label(String::from("comment starts here; no matching '*/' was found")));
+		}
+		Ok(Token::new(TokenKind::Comment(text.to_string()), self.span(row, col)))
+	}
+	fn lex_token(&mut self) -> Result<Token, Diagnostic> {
+		let (row, col) = (self.row, self.col);
+		if self.rest().starts_with("//") || self.rest().starts_with("/*") {
+			return self.lex_comment(row, col);
+		}
+		let c: char = self.peek().unwrap();
+		// a sigil directly followed by a name is an identifier, unless it is a '%' following an
+		// operand, as in 'a%b', which like a '%' on its own is the remainder operator
+		if is_iden_sigil(c) && !(c == '%' && self.after_operand) && self.rest()[1..].starts_with(is_iden_continue) {
+			self.bump(1);
+			let text: &str = self.take_while(is_iden_continue);
+			return Ok(Token::new(TokenKind::Iden(format!("{}{}", c, text)), self.span(row, col)));
+		}
+		for (operator, token_kind) in OPERATOR_TABLE.iter() {
+			if self.rest().starts_with(operator) {
+				self.bump(operator.len());
+				return Ok(Token::new(token_kind.clone(), self.span(row, col)));
+			}
+		}
+		if is_iden_start(c) {
+			let text: &str = self.take_while(is_iden_continue);
+			let kind: TokenKind = TokenKind::from_keyword(text).unwrap_or_else(|| TokenKind::Iden(text.to_string()));
+			return Ok(Token::new(kind, self.span(row, col)));
+		}
+		if c == '`' {
+			self.bump(1);
+			let text: &str = self.take_while(|c| c != '`' && c != '\n');
+			if self.peek() != Some('`') {
+				return Err(self.span(row, col).error(String::from("unterminated quoted identifier"))
+						   .with_code("E0004").with_help(String::from("close the identifier with '`'")));
+			}
+			self.bump(1);
+			if text.is_empty() {
+				return Err(self.span(row, col).error(String::from("empty quoted identifier")).with_code("E0004"));
+			}
+			return Ok(Token::new(TokenKind::Iden(text.to_string()), self.span(row, col)));
+		}
+		if c == '"' {
+			return self.lex_str(row, col);
+		}
+		if c.is_ascii_digit() {
+			if let Some(len) = self.float_len() {
+				return self.lex_float(len, row, col);
+			}
+			let text: &str = self.take_while(|c| c.is_alphanumeric() || c == '_');
+			return self.lex_num(text, row, col);
+		}
+		self.bump(c.len_utf8());
+		Err(self.span(row, col).error(format!("unexpected token '{}'", c)).with_code("E0002")
+			.with_label(String::from("unexpected character")))
+	}
+}
+
+impl Iterator for Tokens<'_> {
+	type Item = Result<Token, Diagnostic>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done {
+			return None;
+		}
+		self.take_while(|c| c.is_whitespace() && c != '\n');
+		match self.peek() {
+			Some('\n') => {
+				let loc: Loc = Loc::new(self.row, self.col, self.filepath.clone());
+				self.pos += 1;
+				self.row += 1;
+				self.col = 1;
+				self.after_operand = false;
+				Some(Ok(Token::new(TokenKind::Eol, loc)))
+			},
+			Some(_) => {
+				let token: Result<Token, Diagnostic> = self.lex_token();
+				self.after_operand = matches!(&token, Ok(token) if matches!(token.kind, TokenKind::Iden(_) | TokenKind::Num(_)
+																			 | TokenKind::Float(_) | TokenKind::RParen));
+				Some(token)
+			},
+			None => {
+				let loc: Loc = Loc::new(self.row, self.col, self.filepath.clone());
+				// the last line is not newline terminated
+				if self.col != 1 {
+					self.col = 1;
+					return Some(Ok(Token::new(TokenKind::Eol, loc)));
+				}
+				self.done = true;
+				Some(Ok(Token::new(TokenKind::Eof, loc)))
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn lex(source: &str) -> (Vec<Token>, Diagnostics) {
+		let mut diagnostics: Diagnostics = Diagnostics::new();
+		let lexer: Lexer = Lexer::from_source(source, String::from("test.irl"), &mut diagnostics);
+		(lexer.tokens, diagnostics)
+	}
+
+	fn kinds(source: &str) -> Vec<TokenKind> {
+		let (tokens, diagnostics) = lex(source);
+		assert!(diagnostics.diagnostics.is_empty(), "unexpected diagnostics lexing {:?}", source);
+		tokens.into_iter().map(|token| token.kind).collect()
+	}
+
+	fn codes(source: &str) -> Vec<String> {
+		lex(source).1.diagnostics.iter().map(|diagnostic| diagnostic.code.clone().unwrap_or_default()).collect()
+	}
+
+	fn iden(name: &str) -> TokenKind {
+		TokenKind::Iden(String::from(name))
+	}
+
+	#[test]
+	fn columns_count_characters_not_bytes() {
+		let (tokens, diagnostics) = lex("// héllo wörld\nx = \"日本\" // ✓\ny = 1");
+		assert!(diagnostics.diagnostics.is_empty());
+		let y: &Token = tokens.iter().find(|token| token.kind == iden("y")).unwrap();
+		assert_eq!((y.loc.row, y.loc.col), (3, 1));
+		let string: &Token = tokens.iter().find(|token| matches!(token.kind, TokenKind::Str(_))).unwrap();
+		assert!(string.kind == TokenKind::Str("日本".as_bytes().to_vec()));
+		assert_eq!((string.loc.col, string.loc.end_col), (5, 9));
+	}
+
+	#[test]
+	fn multi_byte_characters_are_reported_whole() {
+		let (tokens, diagnostics) = lex("x = € + 1");
+		assert_eq!(diagnostics.diagnostics.len(), 1);
+		let diagnostic: &Diagnostic = &diagnostics.diagnostics[0];
+		assert_eq!(diagnostic.code.as_deref(), Some("E0002"));
+		assert!(diagnostic.message.contains('€'));
+		assert_eq!((diagnostic.loc.col, diagnostic.loc.end_col), (5, 6));
+		// lexing goes on after the bad character
+		assert!(tokens.iter().any(|token| token.kind == TokenKind::Num(1)));
+	}
+
+	#[test]
+	fn unicode_letters_form_identifiers() {
+		assert!(kinds("größe = ñ1") == vec![iden("größe"), TokenKind::Equal, iden("ñ1"), TokenKind::Eol, TokenKind::Eof]);
+	}
+
+	#[test]
+	fn integer_literals() {
+		assert!(kinds("0xff 0b1010 0o17 1_000_000") == vec![TokenKind::Num(255), TokenKind::Num(10), TokenKind::Num(15),
+															TokenKind::Num(1_000_000), TokenKind::Eol, TokenKind::Eof]);
+		assert!(kinds("18446744073709551615")[0] == TokenKind::Num(u64::MAX));
+	}
+
+	#[test]
+	fn literal_range_errors() {
+		assert_eq!(codes("x = 18446744073709551616"), vec!["E0003"]);
+		assert_eq!(codes("x = 0x1_0000_0000_0000_0000"), vec!["E0003"]);
+		assert_eq!(codes("x = 1e400"), vec!["E0003"]);
+		assert_eq!(codes("x = 0b102"), vec!["E0003"]);
+		assert_eq!(codes("x = 0x"), vec!["E0003"]);
+	}
+
+	#[test]
+	fn percent_after_an_operand_is_the_remainder_operator() {
+		let rem = |lhs: TokenKind, rhs: TokenKind| vec![iden("c"), TokenKind::Equal, lhs, TokenKind::Rem, rhs,
+														TokenKind::Eol, TokenKind::Eof];
+		assert!(kinds("c = a%b") == rem(iden("a"), iden("b")));
+		assert!(kinds("c = 10%3") == rem(TokenKind::Num(10), TokenKind::Num(3)));
+		assert!(kinds("c = a % b") == rem(iden("a"), iden("b")));
+		assert!(kinds("c = %a%%b") == rem(iden("%a"), iden("%b")));
+		assert!(kinds("c = 1.5%%t0") == rem(TokenKind::Float(1.5), iden("%t0")));
+		assert!(kinds("c = (a)%b")[5] == TokenKind::Rem);
+	}
 
-		return Self{tokens};
+	#[test]
+	fn percent_elsewhere_starts_an_identifier() {
+		assert!(kinds("%t3 = %0") == vec![iden("%t3"), TokenKind::Equal, iden("%0"), TokenKind::Eol, TokenKind::Eof]);
+		assert!(kinds("param %x")[1] == iden("%x"));
+		assert!(kinds("x = (%y + 1)")[3] == iden("%y"));
+		// a new line does not follow an operand
+		assert!(kinds("x = a\n%t = 1")[4] == iden("%t"));
 	}
 }
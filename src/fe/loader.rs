@@ -0,0 +1,159 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use crate::fe::{lexer::Lexer, parser::Parser, loc::Loc, ast::*, diagnostic::Diagnostics};
+
+/// Loads the file at `filepath` and every file it imports, directly or not, into a single
+/// program. The functions of an imported file that it does not export are renamed after the
+/// file, e.g. `helper` of `util.irl` becomes `util.helper`, so files cannot clash on them.
+/// Globals, constants and externs keep their names and share one program-wide namespace.
+/// The directives of the files are moved onto the returned module.
+pub fn load_program(filepath: String, diagnostics: &mut Diagnostics) -> ModuleAstNode {
+	let mut loader: Loader = Loader{stack: vec![], loaded: HashSet::new(), prefixes: HashSet::new(),
+									directives: vec![], nodes: vec![]};
+	// a file that cannot be read is reported by the lexer
+	let canonical: PathBuf = std::fs::canonicalize(&filepath).unwrap_or(PathBuf::from(&filepath));
+	loader.load(filepath, canonical, false, diagnostics);
+	let mut functions: HashMap<String, Loc> = HashMap::new();
+	for node in loader.nodes.iter() {
+		if let AstNode::Function(node) = node {
+			match functions.get(&node.name) {
+				Some(first_loc) => diagnostics.push(node.loc.error(format!("duplicate symbol '{}'", node.name))
+													.with_code("E0008")
+													.with_label(String::from("function defined again here"))
+													.with_secondary_label(first_loc.clone(), String::from("first defined here"))),
+				None => {functions.insert(node.name.clone(), node.loc.clone());},
+			}
+		}
+	}
+	ModuleAstNode{directives: loader.directives, nodes: loader.nodes}
+}
+
+struct Loader {
+	/// files being loaded, the importing one before the imported one
+	stack: Vec<(PathBuf, String)>,
+	loaded: HashSet<PathBuf>,
+	/// names the private functions of the imported files are prefixed with
+	prefixes: HashSet<String>,
+	directives: Vec<DirectiveAstNode>,
+	nodes: Vec<AstNode>,
+}
+
+impl Loader {
+	/// Loads the file at `filepath` unless it was already loaded; `imported` is false for the
+	/// file the program is compiled from.
+	fn load(&mut self, filepath: String, canonical: PathBuf, imported: bool, diagnostics: &mut Diagnostics) {
+		if !self.loaded.insert(canonical.clone()) {
+			return;
+		}
+		let lexer: Lexer = Lexer::new(filepath.clone(), diagnostics);
+		let mut imports: Vec<ImportAstNode> = vec![];
+		let mut nodes: Vec<AstNode> = vec![];
+		for node in Parser::new(lexer.tokens, diagnostics).nodes.into_iter() {
+			match node {
+				AstNode::Import(node) => imports.push(node),
+				AstNode::Directive(node) => self.directive(node, imported, diagnostics),
+				node => nodes.push(node),
+			}
+		}
+		if imported {
+			self.namespace(&filepath, &mut nodes);
+		}
+		self.nodes.append(&mut nodes);
+		self.stack.push((canonical, filepath.clone()));
+		for import in imports.into_iter() {
+			self.import(&filepath, import, diagnostics);
+		}
+		self.stack.pop();
+	}
+	fn import(&mut self, importer: &String, node: ImportAstNode, diagnostics: &mut Diagnostics) {
+		let dir: &Path = Path::new(importer).parent().unwrap_or(Path::new(""));
+		let filepath: String = dir.join(&node.path).to_string_lossy().to_string();
+		let canonical: PathBuf = match std::fs::canonicalize(&filepath) {
+			Ok(canonical) => canonical,
+			Err(err) => {
+				diagnostics.push(node.loc.error(format!("could not read file '{}'; {}", filepath, err))
+								 .with_code("E0001")
+								 .with_label(String::from("imported here"))
+								 .with_note(String::from("imports are resolved relative to the importing file")));
+				return;
+			},
+		};
+		if let Some(ix) = self.stack.iter().position(|(loading, _)| *loading == canonical) {
+			let chain: Vec<String> = self.stack[ix..].iter().map(|(_, path)| path.clone())
+				.chain([filepath.clone()]).collect();
+			diagnostics.push(node.loc.error(format!("import cycle through '{}'", filepath))
+							 .with_code("E0007")
+							 .with_label(String::from("imported here while it is being loaded"))
+							 .with_note(format!("the import chain is {}", chain.join(" -> "))));
+			return;
+		}
+		self.load(filepath, canonical, true, diagnostics);
+	}
+	/// Records a directive on the program; every file may give the target and data layout,
+	/// which must then agree, while only the `module` name of the compiled file is kept.
+	fn directive(&mut self, node: DirectiveAstNode, imported: bool, diagnostics: &mut Diagnostics) {
+		if imported && matches!(node.directive, Directive::Module(_)) {
+			return;
+		}
+		let keyword: &str = node.directive.keyword();
+		match self.directives.iter().find(|given| given.directive.keyword() == keyword) {
+			Some(given) if given.directive != node.directive => {
+				diagnostics.push(node.loc.error(format!("conflicting '{}' directive", keyword))
+								 .with_code("E0009")
+								 .with_label(String::from("differs from the one given before"))
+								 .with_secondary_label(given.loc.clone(), format!("'{}' first given here", keyword)));
+			},
+			Some(_) => {},
+			None => self.directives.push(node),
+		}
+	}
+	/// Prefixes the functions of an imported file that it does not export with a name unique
+	/// to the file, derived from its name; its globals are left shared with the other files.
+	fn namespace(&mut self, filepath: &String, nodes: &mut [AstNode]) {
+		let stem: String = Path::new(filepath).file_stem().map_or(String::from("module"), |stem| stem.to_string_lossy().to_string());
+		let mut prefix: String = stem.clone();
+		let mut n: usize = 1;
+		while !self.prefixes.insert(prefix.clone()) {
+			prefix = format!("{}.{}", stem, n);
+			n += 1;
+		}
+		let renames: HashMap<String, String> = nodes.iter().filter_map(|node| match node {
+			AstNode::Function(node) if !node.exported => Some((node.name.clone(), format!("{}.{}", prefix, node.name))),
+			_ => None,
+		}).collect();
+		rename(nodes, &renames);
+	}
+}
+
+/// Renames the functions in `renames`, and the calls and addresses of them, among `nodes`.
+fn rename(nodes: &mut [AstNode], renames: &HashMap<String, String>) {
+	for node in nodes.iter_mut() {
+		match node {
+			AstNode::Function(node) => {
+				if let Some(name) = renames.get(&node.name) {
+					node.name = name.clone();
+				}
+				rename(&mut node.body, renames);
+			},
+			AstNode::Label(node) => rename(&mut node.body, renames),
+			AstNode::While(node) => rename(&mut node.body, renames),
+			AstNode::IfBlock(node) => {
+				rename(&mut node.then_body, renames);
+				if let Some(else_body) = node.else_body.as_mut() {
+					rename(else_body, renames);
+				}
+			},
+			AstNode::Call(node) => if let Callee::Direct(callee) = &mut node.callee {
+				if let Some(name) = renames.get(callee) {
+					*callee = name.clone();
+				}
+			},
+			AstNode::Assignment(node) => if let AstNode::FuncAddr(ref mut addr_node) = *node.var {
+				if let Some(name) = renames.get(&addr_node.name) {
+					addr_node.name = name.clone();
+				}
+			},
+			_ => {},
+		}
+	}
+}
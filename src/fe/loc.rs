@@ -1,8 +1,13 @@
+use crate::fe::diagnostic::{Diagnostic, Severity};
+
+/// Span of source text; `end_col` is exclusive and columns are counted in characters.
 #[derive(Clone)]
 pub struct Loc {
 	pub row: usize,
 	pub col: usize,
-	pub filepath: String, 
+	pub end_row: usize,
+	pub end_col: usize,
+	pub filepath: String,
 }
 
 impl std::fmt::Display for Loc {
@@ -13,20 +18,28 @@ impl std::fmt::Display for Loc {
 
 impl Loc {
 	pub fn new(row: usize, col: usize, filepath: String) -> Self {
-		Self{row, col, filepath}
+		Self{row, col, end_row: row, end_col: col+1, filepath}
+	}
+	pub fn span(row: usize, col: usize, end_row: usize, end_col: usize, filepath: String) -> Self {
+		Self{row, col, end_row, end_col, filepath}
+	}
+	/// Span starting at `self` and ending where `end` ends.
+	pub fn to(&self, end: &Loc) -> Self {
+		Self::span(self.row, self.col, end.end_row, end.end_col, self.filepath.clone())
+	}
+	pub fn error(&self, message: String) -> Diagnostic {
+		Diagnostic::new(Severity::Error, self.clone(), message)
 	}
-	pub fn error(&self, message: String) {
-		eprintln!("{}: error: {}", self, message);
-		std::process::exit(1);
+	pub fn warning(&self, message: String) -> Diagnostic {
+		Diagnostic::new(Severity::Warning, self.clone(), message)
 	}
-	pub fn program_error(&self, message: String) {
-		eprintln!("{}: error: {}", self.filepath, message);
-		std::process::exit(1);
+	pub fn program_error(&self, message: String) -> Diagnostic {
+		Diagnostic::new(Severity::Error, Loc::new(0, 0, self.filepath.clone()), message)
 	}
-	pub fn message(&self, message: String) {
-		eprintln!("{}: {}", self, message);
+	pub fn is_null(&self) -> bool {
+		self.row == 0
 	}
 	pub fn null() -> Self {
-		Self{row: 0, col: 0, filepath: String::from("")}
+		Self::new(0, 0, String::from(""))
 	}
 }
@@ -1,5 +1,10 @@
 pub mod loc;
+pub mod diagnostic;
+pub mod types;
+pub mod target;
 pub mod token;
 pub mod lexer;
 pub mod ast;
 pub mod parser;
+pub mod prelude;
+pub mod loader;
\ No newline at end of file
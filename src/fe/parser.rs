@@ -1,9 +1,10 @@
 use crate::fe::token::{Token, TokenKind};
-use crate::fe::{loc::Loc, ast::*};
+use crate::fe::{loc::Loc, ast::*, types::Type, target::{Target, DataLayout}, diagnostic::{Diagnostic, Diagnostics}};
 
 #[derive(PartialEq, PartialOrd, Eq, Ord)]
 enum Precedence {
     Default = 0,
+    Block = 5,
     Label = 10,
     Function = 20,
     Start = 100,
@@ -15,220 +16,761 @@ pub struct Parser {
 
 fn token_prec(token: &Token) -> Precedence {
 	match token.kind {
-		TokenKind::Function => Precedence::Function,
+		TokenKind::Function | TokenKind::Global | TokenKind::Const | TokenKind::Extern
+			| TokenKind::Import | TokenKind::Export | TokenKind::Module | TokenKind::Target
+			| TokenKind::Datalayout => Precedence::Function,
 		TokenKind::Label => Precedence::Label,
+		TokenKind::RBrace => Precedence::Block,
 		_ => Precedence::Default, 
 	}
 }
 
-fn eat_iden(tokens: &Vec<Token>, ix: &mut usize) -> String {
-	tokens[*ix].assert_token_kind(TokenKind::Iden(String::from("")));
+fn eat_iden(tokens: &[Token], ix: &mut usize) -> Result<String, Diagnostic> {
 	let name: String = match &tokens[*ix].kind {
 		TokenKind::Iden(id) => id.to_string(),
-		_ => unreachable!(),
+		_ => return Err(tokens[*ix].error_token_kind_mismatch(vec![TokenKind::Iden(String::from(""))])),
 	};
 	*ix += 1;
-	name
+	Ok(name)
 }
 
-fn eat_num(tokens: &Vec<Token>, ix: &mut usize) -> i32 {
-	tokens[*ix].assert_token_kind(TokenKind::Num(0));
-	let num: i32 = match tokens[*ix].kind {
+fn eat_num(tokens: &[Token], ix: &mut usize) -> Result<u64, Diagnostic> {
+	let num: u64 = match tokens[*ix].kind {
 		TokenKind::Num(num_) => num_,
-		_ => unreachable!(),
+		_ => return Err(tokens[*ix].error_token_kind_mismatch(vec![TokenKind::Num(0)])),
+	};
+	*ix += 1;
+	Ok(num)
+}
+
+fn eat_count(tokens: &[Token], ix: &mut usize) -> Result<usize, Diagnostic> {
+	let loc: Loc = tokens[*ix].loc.clone();
+	let count: u64 = eat_num(tokens, ix)?;
+	usize::try_from(count).map_err(|_| loc.error(format!("count '{}' is too large", count)).with_code("E0102"))
+}
+
+fn is_signed_literal(tokens: &[Token], ix: usize) -> bool {
+	tokens[ix].kind == TokenKind::Minus && matches!(tokens[ix+1].kind, TokenKind::Num(_) | TokenKind::Float(_))
+}
+
+/// Integer or float literal with an optional leading '-'; its type, and so the range it is
+/// checked against, is settled by type_check_pass.
+fn eat_literal(tokens: &[Token], ix: &mut usize) -> Result<AstNode, Diagnostic> {
+	let loc: Loc = tokens[*ix].loc.clone();
+	let negative: bool = is_signed_literal(tokens, *ix);
+	if negative {
+		*ix += 1; // eat '-'
+	}
+	if let TokenKind::Float(num) = tokens[*ix].kind {
+		*ix += 1;
+		let num: f64 = if negative {-num} else {num};
+		return Ok(AstNode::Float(FloatAstNode{num, ty: Type::F64, loc: loc.to(&tokens[*ix-1].loc)}));
+	}
+	let magnitude: u64 = eat_num(tokens, ix)?;
+	let loc: Loc = loc.to(&tokens[*ix-1].loc);
+	let num: Option<i64> = match negative {
+		true => 0i64.checked_sub_unsigned(magnitude),
+		false => i64::try_from(magnitude).ok(),
 	};
+	match num {
+		Some(num) => Ok(AstNode::Num(NumAstNode{num, ty: Type::I32, loc})),
+		None => Err(loc.error(format!("literal out of range for {}", Type::I64)).with_code("E0102")
+					.with_label(format!("this literal does not fit in {} whose range is '{}..={}'",
+										Type::I64, Type::I64.min(), Type::I64.max()))),
+	}
+}
+
+fn eat_type(tokens: &[Token], ix: &mut usize) -> Result<Type, Diagnostic> {
+	let loc: Loc = tokens[*ix].loc.clone();
+	let name: String = eat_iden(tokens, ix)?;
+	Type::from_name(&name).ok_or_else(|| loc.error(format!("unknown type '{}'", name)).with_code("E0103")
+									   .with_help(String::from("expected one of 'i1', 'i8', 'i16', 'i32', 'i64', 'f32', 'f64' or 'ptr'")))
+}
+
+/// Optional type annotation introduced by `prefix`, i.e. `: ty` or `-> ty`.
+fn eat_annotation(tokens: &[Token], ix: &mut usize, prefix: TokenKind) -> Result<Option<Type>, Diagnostic> {
+	if tokens[*ix].kind != prefix {
+		return Ok(None);
+	}
 	*ix += 1;
-	num
+	Ok(Some(eat_type(tokens, ix)?))
+}
+
+/// Return type of a signature; `-> void` declares that it returns nothing, written as `None`,
+/// and a missing annotation means `i32`.
+fn eat_ret_ty(tokens: &[Token], ix: &mut usize) -> Result<Option<Type>, Diagnostic> {
+	if tokens[*ix].kind == TokenKind::Arrow && tokens[*ix+1].kind == TokenKind::Iden(String::from("void")) {
+		*ix += 2; // eat '-> void'
+		return Ok(None);
+	}
+	Ok(Some(eat_annotation(tokens, ix, TokenKind::Arrow)?.unwrap_or(Type::I32)))
 }
 
-fn eat_operand(tokens: &Vec<Token>, ix: &mut usize) -> AstNode {
+fn eat_operand(tokens: &[Token], ix: &mut usize) -> Result<AstNode, Diagnostic> {
 	let loc: Loc = tokens[*ix].loc.clone();
 	match tokens[*ix].kind {
-		TokenKind::Iden(_) => AstNode::Iden(IdenAstNode{name: eat_iden(tokens, ix), loc: loc}),
-		TokenKind::Num(_) => AstNode::Num(NumAstNode{num: eat_num(tokens, ix), loc: loc}),
-		_ => {
-			tokens[*ix].error_token_kind_mismatch(vec![
-				TokenKind::Iden(String::from("")), TokenKind::Num(0)]);
-			unreachable!()  
+		TokenKind::Iden(_) => Ok(AstNode::Iden(IdenAstNode{name: eat_iden(tokens, ix)?, ty: Type::I32, loc})),
+		TokenKind::Num(_) | TokenKind::Float(_) => eat_literal(tokens, ix),
+		TokenKind::Str(ref bytes) => {
+			*ix += 1;
+			Ok(AstNode::Str(StrAstNode{bytes: bytes.clone(), loc}))
 		},
+		_ if is_signed_literal(tokens, *ix) => eat_literal(tokens, ix),
+		_ => Err(tokens[*ix].error_token_kind_mismatch(vec![
+			TokenKind::Iden(String::from("")), TokenKind::Num(0), TokenKind::Float(0.0), TokenKind::Str(vec![])])),
+	}
+}
+
+/// Name of the called function, or `*f` to call through the function pointer `f`.
+fn eat_callee(tokens: &[Token], ix: &mut usize) -> Result<Callee, Diagnostic> {
+	if tokens[*ix].kind != TokenKind::Mul {
+		return Ok(Callee::Direct(eat_iden(tokens, ix)?));
+	}
+	*ix += 1; // eat '*'
+	let loc: Loc = tokens[*ix].loc.clone();
+	Ok(Callee::Indirect(Box::new(AstNode::Iden(IdenAstNode{name: eat_iden(tokens, ix)?, ty: Type::Ptr, loc}))))
+}
+
+/// Binding power of a binary operator, from comparisons binding the loosest to
+/// multiplicative operators binding the tightest as in C.
+fn binary_prec(token: &Token) -> Option<u8> {
+	match token.kind {
+		TokenKind::Eq | TokenKind::Neq | TokenKind::Gt | TokenKind::Lt | TokenKind::Ge | TokenKind::Le => Some(1),
+		TokenKind::Or => Some(2),
+		TokenKind::Xor => Some(3),
+		TokenKind::And => Some(4),
+		TokenKind::Shl | TokenKind::Shr | TokenKind::LShr => Some(5),
+		TokenKind::Plus | TokenKind::Minus => Some(6),
+		TokenKind::Mul | TokenKind::Div | TokenKind::Rem => Some(7),
+		_ => None,
+	}
+}
+
+/// Operand, parenthesised expression or unary operator applied to either of them.
+fn eat_primary(tokens: &Vec<Token>, ix: &mut usize) -> Result<AstNode, Diagnostic> {
+	let loc: Loc = tokens[*ix].loc.clone();
+	if tokens[*ix].kind == TokenKind::LParen {
+		*ix += 1; // eat '('
+		let expr: AstNode = eat_expr(tokens, ix, 0)?;
+		assert_n_eat(tokens, TokenKind::RParen, ix)?;
+		return Ok(expr);
+	}
+	if tokens[*ix].is_unary() && !is_signed_literal(tokens, *ix) {
+		let op: UnaryOp = UnaryOp::new(&tokens[*ix])?;
+		*ix += 1; // eat 'unary'
+		let var: AstNode = eat_primary(tokens, ix)?;
+		let loc: Loc = loc.to(&tokens[*ix-1].loc);
+		return Ok(AstNode::Unary(UnaryAstNode{op, var: Box::new(var), loc}));
 	}
+	eat_operand(tokens, ix)
 }
 
-fn assert_n_eat(tokens: &Vec<Token>, expected_kind: TokenKind, ix: &mut usize) {
-	tokens[*ix].assert_token_kind(expected_kind);
+/// Expression of binary operators binding at least as tight as `min_prec`, parsed by
+/// precedence climbing into nested nodes that flatten_expr_pass lowers to three-address code.
+fn eat_expr(tokens: &Vec<Token>, ix: &mut usize, min_prec: u8) -> Result<AstNode, Diagnostic> {
+	let loc: Loc = tokens[*ix].loc.clone();
+	let mut lhs: AstNode = eat_primary(tokens, ix)?;
+	while let Some(prec) = binary_prec(&tokens[*ix]).filter(|prec| *prec >= min_prec) {
+		let op_ix: usize = *ix;
+		*ix += 1; // eat 'arith' or 'relop'
+		let rhs: AstNode = eat_expr(tokens, ix, prec+1)?;
+		let loc: Loc = loc.to(&tokens[*ix-1].loc);
+		lhs = match tokens[op_ix].is_relop() {
+			true => AstNode::Relop(RelopAstNode{op: RelOp::new(&tokens[op_ix])?, lhs: Box::new(lhs), rhs: Box::new(rhs), loc}),
+			false => AstNode::Arith(ArithAstNode{op: ArithOp::new(&tokens[op_ix])?, lhs: Box::new(lhs), rhs: Box::new(rhs), loc}),
+		};
+	}
+	Ok(lhs)
+}
+
+fn assert_n_eat(tokens: &[Token], expected_kind: TokenKind, ix: &mut usize) -> Result<(), Diagnostic> {
+	tokens[*ix].assert_token_kind(expected_kind)?;
 	*ix += 1;
+	Ok(())
+}
+
+/// Statements end at an `Eol` which may be preceded by trailing comments, or at the `}`
+/// closing the block they are in; all are left for `build_ast_prec` to consume.
+fn assert_end_of_statement(tokens: &[Token], ix: usize) -> Result<(), Diagnostic> {
+	match tokens[ix].kind {
+		TokenKind::Comment(_) | TokenKind::RBrace => Ok(()),
+		_ => tokens[ix].assert_token_kind(TokenKind::Eol),
+	}
+}
+
+/// Eats comments and empty lines, keeping the comments as standalone nodes.
+fn eat_comments(tokens: &[Token], ix: &mut usize) -> Vec<AstNode> {
+	let mut comments: Vec<AstNode> = vec![];
+	loop {
+		match &tokens[*ix].kind {
+			TokenKind::Comment(text) => comments.push(AstNode::Comment(CommentAstNode{
+				text: text.clone(), trailing: false, loc: tokens[*ix].loc.clone()})),
+			TokenKind::Eol => {},
+			_ => return comments,
+		}
+		*ix += 1;
+	}
+}
+
+struct Signature {
+	name: String,
+	args: Vec<AstNode>,
+	ret_ty: Option<Type>,
+	loc: Loc,
+	/// comments written among the header lines
+	comments: Vec<AstNode>,
 }
 
-fn parse_function(tokens: &Vec<Token>, ix: &mut usize) -> AstNode {
+/// `keyword L, n -> ty` followed by `n` lines of `arg id: ty`, shared by
+/// function definitions and extern declarations.
+fn parse_signature(tokens: &[Token], ix: &mut usize, keyword: TokenKind) -> Result<Signature, Diagnostic> {
 	let loc: Loc = tokens[*ix].loc.clone();
-	assert_n_eat(tokens, TokenKind::Function, ix);
-	let name: String = eat_iden(tokens, ix);
-	assert_n_eat(tokens, TokenKind::Comma, ix);
-	let arg_count: i32 = eat_num(tokens, ix);
-	assert_n_eat(tokens, TokenKind::Eol, ix);
+	assert_n_eat(tokens, keyword, ix)?;
+	let name: String = eat_iden(tokens, ix)?;
+	assert_n_eat(tokens, TokenKind::Comma, ix)?;
+	let arg_count: usize = eat_count(tokens, ix)?;
+	let ret_ty: Option<Type> = eat_ret_ty(tokens, ix)?;
+	let loc: Loc = loc.to(&tokens[*ix-1].loc);
+	assert_end_of_statement(tokens, *ix)?;
 	let mut args: Vec<AstNode> = vec![];
+	let mut comments: Vec<AstNode> = vec![];
 	for _ in 0..arg_count {
-		assert_n_eat(tokens, TokenKind::Arg, ix);
+		comments.append(&mut eat_comments(tokens, ix));
+		assert_n_eat(tokens, TokenKind::Arg, ix)?;
 		let arg_loc: Loc = tokens[*ix].loc.clone();
-		args.push(AstNode::Iden(IdenAstNode{name: eat_iden(tokens, ix), loc: arg_loc}));
-		assert_n_eat(tokens, TokenKind::Eol, ix);
+		let name: String = eat_iden(tokens, ix)?;
+		let ty: Type = eat_annotation(tokens, ix, TokenKind::Colon)?.unwrap_or(Type::I32);
+		args.push(AstNode::Iden(IdenAstNode{name, ty, loc: arg_loc}));
+		assert_end_of_statement(tokens, *ix)?;
 	}
-	let body = build_ast_prec(tokens, ix, Precedence::Function);
-	AstNode::Function(FunctionAstNode{name: name, args: args, body: body, loc: loc})
+	Ok(Signature{name, args, ret_ty, loc, comments})
+}
+
+fn parse_function(tokens: &Vec<Token>, ix: &mut usize, diagnostics: &mut Diagnostics) -> Result<AstNode, Diagnostic> {
+	let exported: bool = tokens[*ix].kind == TokenKind::Export;
+	if exported {
+		assert_n_eat(tokens, TokenKind::Export, ix)?;
+	}
+	let mut signature: Signature = parse_signature(tokens, ix, TokenKind::Function)?;
+	// comments among the header lines are kept at the start of the body;
+	// only the ones trailing the last header line stay trailing
+	let mut body: Vec<AstNode> = vec![];
+	while let TokenKind::Comment(_) = tokens[*ix].kind {
+		body.push(parse_comment(tokens, ix)?);
+	}
+	body.append(&mut signature.comments);
+	body.append(&mut build_ast_prec(tokens, ix, Precedence::Function, diagnostics));
+	Ok(AstNode::Function(FunctionAstNode{name: signature.name, exported, args: signature.args, ret_ty: signature.ret_ty,
+										 body, loc: signature.loc}))
+}
+
+/// Returns the declaration followed by the comments written among its header lines.
+fn parse_extern(tokens: &[Token], ix: &mut usize) -> Result<Vec<AstNode>, Diagnostic> {
+	let mut signature: Signature = parse_signature(tokens, ix, TokenKind::Extern)?;
+	let mut nodes: Vec<AstNode> = vec![AstNode::Extern(ExternAstNode{
+		name: signature.name, args: signature.args, ret_ty: signature.ret_ty, loc: signature.loc})];
+	nodes.append(&mut signature.comments);
+	Ok(nodes)
 }
 
-fn parse_label(tokens: &Vec<Token>, ix: &mut usize) -> AstNode {
+fn parse_label(tokens: &Vec<Token>, ix: &mut usize, diagnostics: &mut Diagnostics) -> Result<AstNode, Diagnostic> {
 	let loc: Loc = tokens[*ix].loc.clone();
-	assert_n_eat(tokens, TokenKind::Label, ix);
-	let name: String = eat_iden(tokens, ix);
-	assert_n_eat(tokens, TokenKind::Eol, ix);
-	let body: Vec<AstNode> = build_ast_prec(tokens, ix, Precedence::Label);
-	AstNode::Label(LabelAstNode{name: name, body: body, loc: loc})
+	assert_n_eat(tokens, TokenKind::Label, ix)?;
+	let name: String = eat_iden(tokens, ix)?;
+	let loc: Loc = loc.to(&tokens[*ix-1].loc);
+	assert_end_of_statement(tokens, *ix)?;
+	let body: Vec<AstNode> = build_ast_prec(tokens, ix, Precedence::Label, diagnostics);
+	Ok(AstNode::Label(LabelAstNode{name, body, lowered: false, loc}))
 }
 
-fn parse_goto(tokens: &Vec<Token>, ix: &mut usize) -> AstNode {
+fn parse_goto(tokens: &[Token], ix: &mut usize) -> Result<AstNode, Diagnostic> {
 	let loc: Loc = tokens[*ix].loc.clone();
-	assert_n_eat(tokens, TokenKind::Goto, ix);
-	let name: String = eat_iden(tokens, ix);
-	assert_n_eat(tokens, TokenKind::Eol, ix);
-	AstNode::Goto(GotoAstNode{name: name, loc: loc})
+	assert_n_eat(tokens, TokenKind::Goto, ix)?;
+	let name: String = eat_iden(tokens, ix)?;
+	let loc: Loc = loc.to(&tokens[*ix-1].loc);
+	assert_end_of_statement(tokens, *ix)?;
+	Ok(AstNode::Goto(GotoAstNode{name, loc}))
 }
 
-fn parse_comment(tokens: &Vec<Token>, ix: &mut usize) {
-	assert_n_eat(tokens, TokenKind::Comment, ix);
-	while tokens[*ix].kind != TokenKind::Eol && tokens[*ix].kind != TokenKind::Eof {
-		*ix += 1;
-	}
-	if tokens[*ix].kind == TokenKind::Eol {
-		*ix += 1
-	}
+fn parse_comment(tokens: &[Token], ix: &mut usize) -> Result<AstNode, Diagnostic> {
+	let loc: Loc = tokens[*ix].loc.clone();
+	let text: String = match &tokens[*ix].kind {
+		TokenKind::Comment(text) => text.clone(),
+		_ => return Err(tokens[*ix].error_token_kind_mismatch(vec![TokenKind::Comment(String::from(""))])),
+	};
+	let trailing: bool = *ix > 0 && tokens[*ix-1].kind != TokenKind::Eol;
+	*ix += 1;
+	Ok(AstNode::Comment(CommentAstNode{text, trailing, loc}))
 }
 
-fn parse_assignment(tokens: &Vec<Token>, ix: &mut usize) -> AstNode {
+fn parse_assignment(tokens: &Vec<Token>, ix: &mut usize) -> Result<AstNode, Diagnostic> {
 	let loc: Loc = tokens[*ix].loc.clone();
-	let id: String = eat_iden(tokens, ix);
-	assert_n_eat(tokens, TokenKind::Equal, ix);
+	let id: String = eat_iden(tokens, ix)?;
+	let ty: Option<Type> = eat_annotation(tokens, ix, TokenKind::Colon)?;
+	assert_n_eat(tokens, TokenKind::Equal, ix)?;
 	let var_loc: Loc = tokens[*ix].loc.clone();
-	if tokens[*ix].is_unary() {
-		let op: UnaryOp = UnaryOp::new(&tokens[*ix]);
-		*ix += 1; // eat 'unary'
-		let var: AstNode = eat_operand(tokens, ix);
-		assert_n_eat(tokens, TokenKind::Eol, ix);
-		return AstNode::Assignment(AssignmentAstNode{name: id, var: Box::new(
-			AstNode::Unary(UnaryAstNode{op: op, var: Box::new(var), loc: var_loc})), loc});
+	if tokens[*ix].kind == TokenKind::Alloca {
+		assert_n_eat(tokens, TokenKind::Alloca, ix)?;
+		let size: usize = eat_count(tokens, ix)?;
+		let loc: Loc = loc.to(&tokens[*ix-1].loc);
+		let var_loc: Loc = var_loc.to(&tokens[*ix-1].loc);
+		assert_end_of_statement(tokens, *ix)?;
+		return Ok(AstNode::Assignment(AssignmentAstNode{name: id, ty, var: Box::new(
+			AstNode::Alloca(AllocaAstNode{size, loc: var_loc})), loc}));
+	}
+	if tokens[*ix].kind == TokenKind::And {
+		*ix += 1; // eat '&'
+		let name: String = eat_iden(tokens, ix)?;
+		let loc: Loc = loc.to(&tokens[*ix-1].loc);
+		let var_loc: Loc = var_loc.to(&tokens[*ix-1].loc);
+		assert_end_of_statement(tokens, *ix)?;
+		return Ok(AstNode::Assignment(AssignmentAstNode{name: id, ty, var: Box::new(
+			AstNode::FuncAddr(FuncAddrAstNode{name, loc: var_loc})), loc}));
+	}
+	if tokens[*ix].kind == TokenKind::Load {
+		assert_n_eat(tokens, TokenKind::Load, ix)?;
+		let ptr: AstNode = eat_operand(tokens, ix)?;
+		let loc: Loc = loc.to(&tokens[*ix-1].loc);
+		let var_loc: Loc = var_loc.to(&tokens[*ix-1].loc);
+		assert_end_of_statement(tokens, *ix)?;
+		return Ok(AstNode::Assignment(AssignmentAstNode{name: id, ty, var: Box::new(
+			AstNode::Load(LoadAstNode{ptr: Box::new(ptr), ty: ty.unwrap_or(Type::I32), loc: var_loc})), loc}));
 	}
 	if tokens[*ix].kind == TokenKind::Call {
-		assert_n_eat(tokens, TokenKind::Call, ix);
-		let name: String = eat_iden(tokens, ix);
-		assert_n_eat(tokens, TokenKind::Comma, ix);
+		assert_n_eat(tokens, TokenKind::Call, ix)?;
+		let callee: Callee = eat_callee(tokens, ix)?;
+		assert_n_eat(tokens, TokenKind::Comma, ix)?;
 		let param_count_loc: Loc = tokens[*ix].loc.clone();
-		let param_count: i32 = eat_num(tokens, ix);
+		let param_count: usize = eat_count(tokens, ix)?;
 		if param_count != 0 {
-			param_count_loc.error(format!("expected param count to be '0'; but found '{}'",
-										  param_count));
-			unreachable!()
+			return Err(param_count_loc.error(format!("expected param count to be '0'; but found '{}'",
+													 param_count))
+					   .with_code("E0101")
+					   .with_label(String::from("no 'param' instruction precedes this call")));
 		}
-		assert_n_eat(tokens, TokenKind::Eol, ix);
-		return AstNode::Call(CallAstNode{id: id, name: name, params: vec![], loc: loc});
-	}
-	let lhs: AstNode = eat_operand(tokens, ix);
-	if tokens[*ix].is_arith() {
-		let op: ArithOp = ArithOp::new(&tokens[*ix]);
-		*ix += 1; // eat 'arith'
-		let rhs: AstNode = eat_operand(tokens, ix);
-		assert_n_eat(tokens, TokenKind::Eol, ix);
-		return AstNode::Assignment(AssignmentAstNode{name: id, var: Box::new(
-			AstNode::Arith(ArithAstNode{op: op, lhs: Box::new(lhs), rhs: Box::new(rhs),
-										loc: var_loc})), loc});
-	}
-	if tokens[*ix].is_relop() {
-		let op: RelOp = RelOp::new(&tokens[*ix]);
-		*ix += 1; // eat 'relop'
-		let rhs: AstNode = eat_operand(tokens, ix);
-		assert_n_eat(tokens, TokenKind::Eol, ix);
-		return AstNode::Assignment(AssignmentAstNode{name: id, var: Box::new(
-			AstNode::Relop(RelopAstNode{op: op, lhs: Box::new(lhs), rhs: Box::new(rhs),
-										loc: var_loc})), loc});
+		let loc: Loc = loc.to(&tokens[*ix-1].loc);
+		assert_end_of_statement(tokens, *ix)?;
+		return Ok(AstNode::Call(CallAstNode{id: Some(id), callee, params: vec![], ty, loc}));
 	}
-	assert_n_eat(tokens, TokenKind::Eol, ix);
-	AstNode::Assignment(AssignmentAstNode{name: id, var: Box::new(lhs), loc})
+	let var: AstNode = eat_expr(tokens, ix, 0)?;
+	let loc: Loc = loc.to(&tokens[*ix-1].loc);
+	assert_end_of_statement(tokens, *ix)?;
+	Ok(AstNode::Assignment(AssignmentAstNode{name: id, ty, var: Box::new(var), loc}))
+}
+
+fn parse_store(tokens: &[Token], ix: &mut usize) -> Result<AstNode, Diagnostic> {
+	let loc: Loc = tokens[*ix].loc.clone();
+	assert_n_eat(tokens, TokenKind::Store, ix)?;
+	let ptr: AstNode = eat_operand(tokens, ix)?;
+	assert_n_eat(tokens, TokenKind::Comma, ix)?;
+	let var: AstNode = eat_operand(tokens, ix)?;
+	let loc: Loc = loc.to(&tokens[*ix-1].loc);
+	assert_end_of_statement(tokens, *ix)?;
+	Ok(AstNode::Store(StoreAstNode{ptr: Box::new(ptr), var: Box::new(var), loc}))
+}
+
+/// Globals are initialized when the program is loaded so only literals are allowed.
+fn parse_global(tokens: &[Token], ix: &mut usize) -> Result<AstNode, Diagnostic> {
+	let loc: Loc = tokens[*ix].loc.clone();
+	assert_n_eat(tokens, TokenKind::Global, ix)?;
+	let name: String = eat_iden(tokens, ix)?;
+	let ty: Option<Type> = eat_annotation(tokens, ix, TokenKind::Colon)?;
+	assert_n_eat(tokens, TokenKind::Equal, ix)?;
+	let init: AstNode = match tokens[*ix].kind {
+		TokenKind::Num(_) | TokenKind::Float(_) | TokenKind::Iden(_) => eat_operand(tokens, ix)?,
+		_ if is_signed_literal(tokens, *ix) => eat_literal(tokens, ix)?,
+		_ => return Err(tokens[*ix].error_token_kind_mismatch(vec![TokenKind::Num(0), TokenKind::Float(0.0), TokenKind::Iden(String::from(""))])
+						.with_note(String::from("globals can only be initialized with a literal or a constant"))),
+	};
+	let loc: Loc = loc.to(&tokens[*ix-1].loc);
+	assert_end_of_statement(tokens, *ix)?;
+	Ok(AstNode::Global(GlobalAstNode{name, ty, init: Box::new(init), loc}))
+}
+
+fn eat_str(tokens: &[Token], ix: &mut usize) -> Result<String, Diagnostic> {
+	let text: String = match &tokens[*ix].kind {
+		TokenKind::Str(bytes) => String::from_utf8_lossy(bytes).to_string(),
+		_ => return Err(tokens[*ix].error_token_kind_mismatch(vec![TokenKind::Str(vec![])])),
+	};
+	*ix += 1;
+	Ok(text)
+}
+
+/// `import "path"` of another file of the program.
+fn parse_import(tokens: &[Token], ix: &mut usize) -> Result<AstNode, Diagnostic> {
+	let loc: Loc = tokens[*ix].loc.clone();
+	assert_n_eat(tokens, TokenKind::Import, ix)?;
+	let path: String = eat_str(tokens, ix)?;
+	let loc: Loc = loc.to(&tokens[*ix-1].loc);
+	assert_end_of_statement(tokens, *ix)?;
+	Ok(AstNode::Import(ImportAstNode{path, loc}))
+}
+
+/// `module "name"`, `target "triple"` or `datalayout "layout"`; the target and data layout
+/// are checked to be known and well formed here, whether a backend can honor them is up to it.
+fn parse_directive(tokens: &[Token], ix: &mut usize) -> Result<AstNode, Diagnostic> {
+	let loc: Loc = tokens[*ix].loc.clone();
+	let kind: TokenKind = tokens[*ix].kind.clone();
+	*ix += 1;
+	let value_loc: Loc = tokens[*ix].loc.clone();
+	let value: String = eat_str(tokens, ix)?;
+	let directive: Directive = match kind {
+		TokenKind::Module => Directive::Module(value),
+		TokenKind::Target => match Target::from_triple(&value) {
+			Some(target) => Directive::Target(target),
+			None => return Err(value_loc.error(format!("unknown target '{}'", value))
+							   .with_code("E0105")
+							   .with_label(String::from("no backend for this target"))
+							   .with_help(format!("expected one of '{}' or '{}'", Target::Wasm32, Target::X86_64Linux))),
+		},
+		_ => match DataLayout::parse(&value) {
+			Ok(layout) => Directive::Datalayout(layout),
+			Err(spec) => return Err(value_loc.error(format!("malformed data layout specification '{}'", spec))
+									.with_code("E0105")
+									.with_label(String::from("in this data layout"))
+									.with_note(String::from("specifications are separated by '-', e.g. 'e-p:32:32'"))),
+		},
+	};
+	let loc: Loc = loc.to(&tokens[*ix-1].loc);
+	assert_end_of_statement(tokens, *ix)?;
+	Ok(AstNode::Directive(DirectiveAstNode{directive, loc}))
+}
+
+/// `const id = expr` over literals and other constants, which substitute_const_pass evaluates.
+fn parse_const(tokens: &Vec<Token>, ix: &mut usize) -> Result<AstNode, Diagnostic> {
+	let loc: Loc = tokens[*ix].loc.clone();
+	assert_n_eat(tokens, TokenKind::Const, ix)?;
+	let name: String = eat_iden(tokens, ix)?;
+	let loc: Loc = loc.to(&tokens[*ix-1].loc);
+	assert_n_eat(tokens, TokenKind::Equal, ix)?;
+	let var: AstNode = eat_expr(tokens, ix, 0)?;
+	assert_end_of_statement(tokens, *ix)?;
+	Ok(AstNode::Const(ConstAstNode{name, var: Box::new(var), loc}))
 }
 
-fn parse_ret(tokens: &Vec<Token>, ix: &mut usize) -> AstNode {
+fn parse_ret(tokens: &[Token], ix: &mut usize) -> Result<AstNode, Diagnostic> {
 	let loc: Loc = tokens[*ix].loc.clone();
-	assert_n_eat(tokens, TokenKind::Ret, ix);
-	let var: AstNode = eat_operand(tokens, ix);
-	assert_n_eat(tokens, TokenKind::Eol, ix);
-	AstNode::Ret(RetAstNode{var: Box::new(var), loc: loc})
+	assert_n_eat(tokens, TokenKind::Ret, ix)?;
+	// a plain 'ret' returns from a function returning nothing
+	let var: Option<Box<AstNode>> = match tokens[*ix].kind {
+		TokenKind::Eol | TokenKind::Comment(_) => None,
+		_ => Some(Box::new(eat_operand(tokens, ix)?)),
+	};
+	let loc: Loc = loc.to(&tokens[*ix-1].loc);
+	assert_end_of_statement(tokens, *ix)?;
+	Ok(AstNode::Ret(RetAstNode{var, loc}))
 }
 
-fn parse_param(tokens: &Vec<Token>, ix: &mut usize) -> AstNode {
+/// Returns the comments written among the 'param' lines followed by the call, which either
+/// binds its result with `id = call L, n` or discards it with a bare `call L, n`.
+fn parse_param(tokens: &[Token], ix: &mut usize) -> Result<Vec<AstNode>, Diagnostic> {
 	let mut params: Vec<AstNode> = vec![];
+	let mut nodes: Vec<AstNode> = vec![];
 	while tokens[*ix].kind == TokenKind::Param {
-		assert_n_eat(tokens, TokenKind::Param, ix);
-		params.push(eat_operand(tokens, ix));
-		assert_n_eat(tokens, TokenKind::Eol, ix);
+		assert_n_eat(tokens, TokenKind::Param, ix)?;
+		params.push(eat_operand(tokens, ix)?);
+		assert_end_of_statement(tokens, *ix)?;
+		nodes.append(&mut eat_comments(tokens, ix));
 	}
 	let loc: Loc = tokens[*ix].loc.clone();
-	let id: String = eat_iden(tokens, ix);
-	assert_n_eat(tokens, TokenKind::Equal, ix);
-	assert_n_eat(tokens, TokenKind::Call, ix);
-	let name: String = eat_iden(tokens, ix);
-	assert_n_eat(tokens, TokenKind::Comma, ix);
+	let (id, ty): (Option<String>, Option<Type>) = match tokens[*ix].kind {
+		TokenKind::Call => (None, None),
+		_ => {
+			let id: String = eat_iden(tokens, ix)?;
+			let ty: Option<Type> = eat_annotation(tokens, ix, TokenKind::Colon)?;
+			assert_n_eat(tokens, TokenKind::Equal, ix)?;
+			(Some(id), ty)
+		},
+	};
+	assert_n_eat(tokens, TokenKind::Call, ix)?;
+	let callee: Callee = eat_callee(tokens, ix)?;
+	assert_n_eat(tokens, TokenKind::Comma, ix)?;
 	let param_loc: Loc = tokens[*ix].loc.clone();
-	let param_count: i32 = eat_num(tokens, ix);
-	if params.len() as i32 != param_count {
-		param_loc.error(format!("expected param count to be '{}'; but found '{}'",
-								params.len(), param_count));
+	let param_count: usize = eat_count(tokens, ix)?;
+	if params.len() != param_count {
+		return Err(param_loc.error(format!("expected param count to be '{}'; but found '{}'",
+										   params.len(), param_count))
+				   .with_code("E0101")
+				   .with_label(format!("{} 'param' instruction(s) precede this call", params.len())));
 	}
-	assert_n_eat(tokens, TokenKind::Eol, ix);
-	AstNode::Call(CallAstNode{id: id, name: name, params: params, loc: loc})
+	let loc: Loc = loc.to(&tokens[*ix-1].loc);
+	assert_end_of_statement(tokens, *ix)?;
+	nodes.push(AstNode::Call(CallAstNode{id, callee, params, ty, loc}));
+	Ok(nodes)
 }
 
-fn parse_if(tokens: &Vec<Token>, ix: &mut usize) -> AstNode {
-	let loc: Loc = tokens[*ix].loc.clone();
-	assert_n_eat(tokens, TokenKind::If, ix);
-	assert_n_eat(tokens, TokenKind::LParen, ix);
+/// `(cond)` of a branch or loop; the condition is either a comparison or a single operand
+/// tested against zero.
+fn eat_condition(tokens: &[Token], ix: &mut usize) -> Result<AstNode, Diagnostic> {
+	assert_n_eat(tokens, TokenKind::LParen, ix)?;
 	let relop_loc: Loc = tokens[*ix].loc.clone();
-	let lhs: AstNode = eat_operand(tokens, ix);
-	let op: RelOp = RelOp::new(&tokens[*ix]);
-	*ix += 1; // eat 'relop'
-	let rhs: AstNode = eat_operand(tokens, ix);
-	let condition: AstNode = AstNode::Relop(RelopAstNode{op: op, lhs: Box::new(lhs),
-														 rhs: Box::new(rhs), loc: relop_loc});
-	assert_n_eat(tokens, TokenKind::RParen, ix);
-	assert_n_eat(tokens, TokenKind::Goto, ix);
-	let name: String = eat_iden(tokens, ix);
-	assert_n_eat(tokens, TokenKind::Eol, ix);
-	return AstNode::If(IfAstNode{condition: Box::new(condition), label: name, loc: loc})
+	let lhs: AstNode = eat_operand(tokens, ix)?;
+	let condition: AstNode = if tokens[*ix].is_relop() {
+		let op: RelOp = RelOp::new(&tokens[*ix])?;
+		*ix += 1; // eat 'relop'
+		let rhs: AstNode = eat_operand(tokens, ix)?;
+		let relop_loc: Loc = relop_loc.to(&tokens[*ix-1].loc);
+		AstNode::Relop(RelopAstNode{op, lhs: Box::new(lhs), rhs: Box::new(rhs), loc: relop_loc})
+	} else {lhs};
+	assert_n_eat(tokens, TokenKind::RParen, ix)?;
+	Ok(condition)
+}
+
+/// `{` followed by statements up to the matching `}`, which may be on the same line.
+fn parse_block(tokens: &Vec<Token>, ix: &mut usize, diagnostics: &mut Diagnostics) -> Result<Vec<AstNode>, Diagnostic> {
+	assert_n_eat(tokens, TokenKind::LBrace, ix)?;
+	let body: Vec<AstNode> = build_ast_prec(tokens, ix, Precedence::Block, diagnostics);
+	if tokens[*ix].kind == TokenKind::Label {
+		return Err(tokens[*ix].loc.error(String::from("label inside a block"))
+				   .with_code("E0104")
+				   .with_label(String::from("labels cannot be placed between '{' and '}'"))
+				   .with_help(String::from("jump out of a loop with 'break' or 'continue' instead")));
+	}
+	assert_n_eat(tokens, TokenKind::RBrace, ix)?;
+	Ok(body)
+}
+
+/// Whether the `if` at `ix` is followed by a block rather than by a `goto`.
+fn is_if_block(tokens: &[Token], ix: usize) -> bool {
+	tokens[ix..].iter().find(|token| matches!(token.kind, TokenKind::LBrace | TokenKind::Goto | TokenKind::Eol | TokenKind::Eof))
+		.is_some_and(|token| token.kind == TokenKind::LBrace)
+}
+
+/// `if (cond) { ... }` optionally followed by `else { ... }` or `else if`.
+fn parse_if_block(tokens: &Vec<Token>, ix: &mut usize, diagnostics: &mut Diagnostics) -> Result<AstNode, Diagnostic> {
+	let loc: Loc = tokens[*ix].loc.clone();
+	assert_n_eat(tokens, TokenKind::If, ix)?;
+	let condition: AstNode = eat_condition(tokens, ix)?;
+	let loc: Loc = loc.to(&tokens[*ix-1].loc);
+	let then_body: Vec<AstNode> = parse_block(tokens, ix, diagnostics)?;
+	let else_body: Option<Vec<AstNode>> = match (&tokens[*ix].kind, &tokens[*ix+1].kind) {
+		(TokenKind::Else, TokenKind::If) => {
+			*ix += 1; // eat 'else'
+			Some(vec![parse_if_block(tokens, ix, diagnostics)?])
+		},
+		(TokenKind::Else, _) => {
+			*ix += 1; // eat 'else'
+			Some(parse_block(tokens, ix, diagnostics)?)
+		},
+		_ => None,
+	};
+	assert_end_of_statement(tokens, *ix)?;
+	Ok(AstNode::IfBlock(IfBlockAstNode{condition: Box::new(condition), then_body,
+									   else_body, loc}))
+}
+
+fn parse_while(tokens: &Vec<Token>, ix: &mut usize, diagnostics: &mut Diagnostics) -> Result<AstNode, Diagnostic> {
+	let loc: Loc = tokens[*ix].loc.clone();
+	assert_n_eat(tokens, TokenKind::While, ix)?;
+	let condition: AstNode = eat_condition(tokens, ix)?;
+	let loc: Loc = loc.to(&tokens[*ix-1].loc);
+	let body: Vec<AstNode> = parse_block(tokens, ix, diagnostics)?;
+	assert_end_of_statement(tokens, *ix)?;
+	Ok(AstNode::While(WhileAstNode{condition: Box::new(condition), body, loc}))
+}
+
+fn parse_loop_jump(tokens: &[Token], ix: &mut usize) -> Result<AstNode, Diagnostic> {
+	let loc: Loc = tokens[*ix].loc.clone();
+	let exit: bool = tokens[*ix].kind == TokenKind::Break;
+	if exit {
+		assert_n_eat(tokens, TokenKind::Break, ix)?;
+	} else {
+		assert_n_eat(tokens, TokenKind::Continue, ix)?;
+	}
+	assert_end_of_statement(tokens, *ix)?;
+	Ok(AstNode::LoopJump(LoopJumpAstNode{exit, loc}))
+}
+
+/// `if (cond) goto L` or `ifnot (cond) goto L`, optionally followed by `else L2`; an `if`
+/// followed by a block instead of a `goto` is parsed by `parse_if_block`.
+fn parse_if(tokens: &[Token], ix: &mut usize) -> Result<AstNode, Diagnostic> {
+	let loc: Loc = tokens[*ix].loc.clone();
+	let negated: bool = tokens[*ix].kind == TokenKind::IfNot;
+	if negated {
+		assert_n_eat(tokens, TokenKind::IfNot, ix)?;
+	} else {
+		assert_n_eat(tokens, TokenKind::If, ix)?;
+	}
+	let condition: AstNode = eat_condition(tokens, ix)?;
+	assert_n_eat(tokens, TokenKind::Goto, ix)?;
+	let name: String = eat_iden(tokens, ix)?;
+	let otherwise: Option<String> = if tokens[*ix].kind == TokenKind::Else {
+		assert_n_eat(tokens, TokenKind::Else, ix)?;
+		Some(eat_iden(tokens, ix)?)
+	} else {None};
+	let loc: Loc = loc.to(&tokens[*ix-1].loc);
+	assert_end_of_statement(tokens, *ix)?;
+	Ok(AstNode::If(IfAstNode{condition: Box::new(condition), label: name, otherwise,
+							 negated, loc}))
+}
+
+/// Integer case value of a switch, i.e. a literal with an optional leading '-'.
+fn eat_case_value(tokens: &[Token], ix: &mut usize) -> Result<NumAstNode, Diagnostic> {
+	let num_ix: usize = if is_signed_literal(tokens, *ix) {*ix+1} else {*ix};
+	if !matches!(tokens[num_ix].kind, TokenKind::Num(_)) {
+		return Err(tokens[num_ix].error_token_kind_mismatch(vec![TokenKind::Num(0)]));
+	}
+	match eat_literal(tokens, ix)? {
+		AstNode::Num(num_node) => Ok(num_node),
+		_ => unreachable!(),
+	}
+}
+
+/// `switch x [0: L0, 1: L1, ...] default Ld`; the case list may be empty.
+fn parse_switch(tokens: &[Token], ix: &mut usize) -> Result<AstNode, Diagnostic> {
+	let loc: Loc = tokens[*ix].loc.clone();
+	assert_n_eat(tokens, TokenKind::Switch, ix)?;
+	let scrutinee: AstNode = eat_operand(tokens, ix)?;
+	assert_n_eat(tokens, TokenKind::LBracket, ix)?;
+	let mut cases: Vec<SwitchCase> = Vec::new();
+	while tokens[*ix].kind != TokenKind::RBracket {
+		if !cases.is_empty() {
+			assert_n_eat(tokens, TokenKind::Comma, ix)?;
+		}
+		let value: NumAstNode = eat_case_value(tokens, ix)?;
+		assert_n_eat(tokens, TokenKind::Colon, ix)?;
+		let label: String = eat_iden(tokens, ix)?;
+		cases.push(SwitchCase{value, label});
+	}
+	assert_n_eat(tokens, TokenKind::RBracket, ix)?;
+	assert_n_eat(tokens, TokenKind::Default, ix)?;
+	let default: String = eat_iden(tokens, ix)?;
+	let loc: Loc = loc.to(&tokens[*ix-1].loc);
+	assert_end_of_statement(tokens, *ix)?;
+	Ok(AstNode::Switch(SwitchAstNode{scrutinee: Box::new(scrutinee), cases, default, loc}))
+}
 
+/// Skips the rest of a malformed statement up to the next `Eol` (consumed) or `label`, `}`
+/// or a top level keyword or `Eof` (left for the caller) and returns the error node covering
+/// the skipped tokens. The blocks the statement opens are skipped up to their closing `}`.
+fn recover(tokens: &[Token], ix: &mut usize, start: usize) -> AstNode {
+	if *ix == start {
+		*ix += 1; // always make progress past the offending token
+	}
+	let mut depth: usize = tokens[start..*ix].iter().fold(0, |depth, token| match token.kind {
+		TokenKind::LBrace => depth + 1,
+		TokenKind::RBrace => depth.saturating_sub(1),
+		_ => depth,
+	});
+	loop {
+		match tokens[*ix].kind {
+			TokenKind::Function | TokenKind::Global | TokenKind::Const | TokenKind::Extern | TokenKind::Import
+				| TokenKind::Export | TokenKind::Module | TokenKind::Target | TokenKind::Datalayout
+				| TokenKind::Eof => break,
+			TokenKind::Eol | TokenKind::Label | TokenKind::RBrace if depth == 0 => break,
+			TokenKind::LBrace => depth += 1,
+			TokenKind::RBrace => depth -= 1,
+			_ => {},
+		}
+		*ix += 1;
+	}
+	let loc: Loc = tokens[start].loc.to(&tokens[*ix-1].loc);
+	if tokens[*ix].kind == TokenKind::Eol {
+		*ix += 1;
+	}
+	AstNode::Error(ErrorAstNode{loc})
 }
 
-fn build_ast_prec(tokens: &Vec<Token>, ix: &mut usize, prec: Precedence) -> Vec<AstNode> {
+fn build_ast_prec(tokens: &Vec<Token>, ix: &mut usize, prec: Precedence,
+				  diagnostics: &mut Diagnostics) -> Vec<AstNode> {
 	let mut nodes: Vec<AstNode> = vec![];
 	while tokens[*ix].kind != TokenKind::Eof && token_prec(&tokens[*ix]) < prec {
-		match tokens[*ix].kind {
-			TokenKind::Function => nodes.push(parse_function(&tokens, ix)),
-			TokenKind::Label => nodes.push(parse_label(&tokens, ix)),
-			TokenKind::Goto => nodes.push(parse_goto(&tokens, ix)),
-			TokenKind::Iden(_) => nodes.push(parse_assignment(&tokens, ix)),
-			TokenKind::Comment => parse_comment(&tokens, ix),
-			TokenKind::Ret => nodes.push(parse_ret(&tokens, ix)),
-			TokenKind::Param => nodes.push(parse_param(&tokens, ix)),
-			TokenKind::If => nodes.push(parse_if(&tokens, ix)),
-			TokenKind::Eol => {*ix += 1;},
-			_ => tokens[*ix].error_token_kind_mismatch(
+		let start: usize = *ix;
+		let result: Result<(), Diagnostic> = match tokens[*ix].kind {
+			TokenKind::Function | TokenKind::Export => parse_function(tokens, ix, diagnostics).map(|node| nodes.push(node)),
+			TokenKind::Label => parse_label(tokens, ix, diagnostics).map(|node| nodes.push(node)),
+			TokenKind::Goto => parse_goto(tokens, ix).map(|node| nodes.push(node)),
+			TokenKind::Iden(_) => parse_assignment(tokens, ix).map(|node| nodes.push(node)),
+			TokenKind::Comment(_) => parse_comment(tokens, ix).map(|node| nodes.push(node)),
+			TokenKind::Ret => parse_ret(tokens, ix).map(|node| nodes.push(node)),
+			TokenKind::Param | TokenKind::Call => parse_param(tokens, ix).map(|mut params| nodes.append(&mut params)),
+			TokenKind::If if is_if_block(tokens, *ix) => parse_if_block(tokens, ix, diagnostics).map(|node| nodes.push(node)),
+			TokenKind::If | TokenKind::IfNot => parse_if(tokens, ix).map(|node| nodes.push(node)),
+			TokenKind::While => parse_while(tokens, ix, diagnostics).map(|node| nodes.push(node)),
+			TokenKind::Break | TokenKind::Continue => parse_loop_jump(tokens, ix).map(|node| nodes.push(node)),
+			TokenKind::Switch => parse_switch(tokens, ix).map(|node| nodes.push(node)),
+			TokenKind::Store => parse_store(tokens, ix).map(|node| nodes.push(node)),
+			TokenKind::Global => parse_global(tokens, ix).map(|node| nodes.push(node)),
+			TokenKind::Const => parse_const(tokens, ix).map(|node| nodes.push(node)),
+			TokenKind::Import => parse_import(tokens, ix).map(|node| nodes.push(node)),
+			TokenKind::Module | TokenKind::Target | TokenKind::Datalayout => parse_directive(tokens, ix).map(|node| nodes.push(node)),
+			TokenKind::Extern => parse_extern(tokens, ix).map(|mut externs| nodes.append(&mut externs)),
+			TokenKind::Eol => {*ix += 1; Ok(())},
+			_ => Err(tokens[*ix].error_token_kind_mismatch(
 				vec![TokenKind::Function, TokenKind::Label, TokenKind::Goto,
 					 TokenKind::Iden(String::from("")), TokenKind::Ret,
-					 TokenKind::Param, TokenKind::If, TokenKind::Eol])
+					 TokenKind::Param, TokenKind::Call, TokenKind::If, TokenKind::IfNot, TokenKind::Switch, TokenKind::While,
+					 TokenKind::Break, TokenKind::Continue, TokenKind::Store, TokenKind::Global, TokenKind::Const, TokenKind::Extern,
+					 TokenKind::Import, TokenKind::Export, TokenKind::Module, TokenKind::Target, TokenKind::Datalayout,
+					 TokenKind::Eol])),
 		};
+		if let Err(diagnostic) = result {
+			diagnostics.push(diagnostic);
+			nodes.push(recover(tokens, ix, start));
+		}
 	}
 	nodes
 }
 
+/// Moves comments written in the middle of a line to its end, just before the
+/// `Eol`, so statements only ever see comments trailing them.
+fn hoist_comments(tokens: Vec<Token>) -> Vec<Token> {
+	let mut hoisted: Vec<Token> = Vec::with_capacity(tokens.len());
+	let mut comments: Vec<Token> = vec![];
+	for token in tokens.into_iter() {
+		match token.kind {
+			TokenKind::Comment(_) => comments.push(token),
+			TokenKind::Eol | TokenKind::Eof => {
+				hoisted.append(&mut comments);
+				hoisted.push(token);
+			},
+			_ => hoisted.push(token),
+		}
+	}
+	hoisted
+}
+
 impl Parser {
-	pub fn new(tokens: Vec<Token>) -> Self {
+	pub fn new(tokens: Vec<Token>, diagnostics: &mut Diagnostics) -> Self {
+		let tokens: Vec<Token> = hoist_comments(tokens);
 		let mut ix: usize = 0;
-		Self{nodes: build_ast_prec(&tokens, &mut ix, Precedence::Start)}
+		Self{nodes: build_ast_prec(&tokens, &mut ix, Precedence::Start, diagnostics)}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::fe::lexer::Lexer;
+
+	/// Parses `source`, given one statement per line, and returns the codes of the
+	/// diagnostics reported.
+	fn parse(source: &[&str]) -> Vec<String> {
+		let mut diagnostics: Diagnostics = Diagnostics::new();
+		let lexer: Lexer = Lexer::from_source(&source.join("\n"), String::from("test.irl"), &mut diagnostics);
+		Parser::new(lexer.tokens, &mut diagnostics);
+		diagnostics.diagnostics.iter().map(|diagnostic| diagnostic.code.clone().unwrap_or_default()).collect()
+	}
+
+	#[test]
+	fn directives() {
+		assert!(parse(&["module \"m\"", "target \"wasm32\"", "datalayout \"e-p:32:32-i64:64\""]).is_empty());
+		assert!(parse(&["target \"x86_64-unknown-linux-gnu\"", "datalayout \"E\""]).is_empty());
+		assert!(parse(&["target \"wasm32-unknown-unknown\""]).is_empty());
+	}
+
+	#[test]
+	fn unknown_target() {
+		assert_eq!(parse(&["target \"aarch64-linux\""]), vec!["E0105"]);
+		assert_eq!(parse(&["target \"\""]), vec!["E0105"]);
+	}
+
+	#[test]
+	fn malformed_datalayout() {
+		assert_eq!(parse(&["datalayout \"e--p:32\""]), vec!["E0105"]);
+		assert_eq!(parse(&["datalayout \"p:12\""]), vec!["E0105"]);
+		assert_eq!(parse(&["datalayout \"e:1\""]), vec!["E0105"]);
+		assert_eq!(parse(&["datalayout \"p:32:\""]), vec!["E0105"]);
+		assert_eq!(parse(&["datalayout \"8-e\""]), vec!["E0105"]);
 	}
 }
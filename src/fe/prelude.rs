@@ -0,0 +1,9 @@
+use crate::fe::{lexer::Lexer, parser::Parser, ast::AstNode, diagnostic::Diagnostics};
+
+/// Declarations of the runtime functions from `prelude.irl`, added to every program;
+/// the file is embedded in the binary so it does not depend on the source checkout.
+pub fn prelude(diagnostics: &mut Diagnostics) -> Vec<AstNode> {
+	let lexer: Lexer = Lexer::from_source(include_str!("prelude.irl"), String::from("prelude.irl"), diagnostics);
+	Parser::new(lexer.tokens, diagnostics).nodes.into_iter()
+		.filter(|node| matches!(node, AstNode::Extern(_))).collect()
+}
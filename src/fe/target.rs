@@ -0,0 +1,79 @@
+/// Machines a program can be compiled for, named in a `target` directive by an LLVM-style
+/// triple such as `wasm32` or `x86_64-linux`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+	Wasm32,
+	X86_64Linux,
+}
+
+impl Target {
+	/// Triples are matched on their architecture, and for `x86_64` on their operating system,
+	/// so `wasm32-unknown-unknown` and `x86_64-unknown-linux-gnu` are accepted as well.
+	pub fn from_triple(triple: &str) -> Option<Self> {
+		let parts: Vec<&str> = triple.split('-').collect();
+		match parts[0] {
+			"wasm32" => Some(Target::Wasm32),
+			"x86_64" if parts[1..].contains(&"linux") => Some(Target::X86_64Linux),
+			_ => None,
+		}
+	}
+	pub fn pointer_bits(&self) -> u32 {
+		match self {
+			Target::Wasm32 => 32,
+			Target::X86_64Linux => 64,
+		}
+	}
+	/// Command line flag selecting the backend of the target.
+	pub fn flag(&self) -> &'static str {
+		match self {
+			Target::Wasm32 => "--wat",
+			Target::X86_64Linux => "--fasm-linux-x86_64",
+		}
+	}
+}
+
+impl std::fmt::Display for Target {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Target::Wasm32 => write!(f, "wasm32"),
+			Target::X86_64Linux => write!(f, "x86_64-linux"),
+		}
+	}
+}
+
+/// Layout of data in memory given by a `datalayout` directive; a `-` separated list of
+/// LLVM-style specifications of which the endianness (`e` or `E`) and the pointer size
+/// (`p:32`) are used, the others are only checked to be well formed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataLayout {
+	pub text: String,
+	pub big_endian: bool,
+	pub pointer_bits: Option<u32>,
+}
+
+impl DataLayout {
+	/// Parses `text`, or returns the specification that is malformed.
+	pub fn parse(text: &str) -> Result<Self, String> {
+		let mut layout: DataLayout = Self{text: String::from(text), big_endian: false, pointer_bits: None};
+		for spec in text.split('-') {
+			let (name, fields): (&str, Vec<&str>) = match spec.split_once(':') {
+				Some((name, fields)) => (name, fields.split(':').collect()),
+				None => (spec, vec![]),
+			};
+			if name.is_empty() || fields.iter().any(|field| field.is_empty()) {
+				return Err(String::from(spec));
+			}
+			match name {
+				"e" | "E" if fields.is_empty() => layout.big_endian = name == "E",
+				"p" | "p0" => match fields.first().map(|field| field.parse::<u32>()) {
+					Some(Ok(bits)) if bits > 0 && bits % 8 == 0 => layout.pointer_bits = Some(bits),
+					_ => return Err(String::from(spec)),
+				},
+				"e" | "E" => return Err(String::from(spec)),
+				_ if name.starts_with(|c: char| c.is_ascii_alphabetic()) => {},
+				_ => return Err(String::from(spec)),
+			}
+		}
+		Ok(layout)
+	}
+}
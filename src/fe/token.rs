@@ -1,23 +1,47 @@
-use crate::fe::{loc::Loc};
+use crate::fe::{loc::Loc, diagnostic::Diagnostic};
 
 #[derive(PartialEq, Clone)]
 pub enum TokenKind {
 	Function,
 	Iden(String),
-	Num(i32),
+	Num(u64),
+	Float(f64),
+	Str(Vec<u8>),
 	Comma, 
+	Colon,
+	Arrow,
 	Arg,
 	Equal,
 	Plus,
 	Minus,
 	Mul,
-	Comment,
+	Rem,
+	And,
+	Or,
+	Xor,
+	Shl,
+	Shr,
+	LShr,
+	Not,
+	Tilde,
+	Comment(String),
 	Div,
 	Goto,
 	Label,
 	If,
+	IfNot,
+	Else,
+	Switch,
+	Default,
+	While,
+	Break,
+	Continue,
 	LParen,
 	RParen,
+	LBracket,
+	RBracket,
+	LBrace,
+	RBrace,
 	Eq,
 	Neq,
 	Gt,
@@ -27,6 +51,17 @@ pub enum TokenKind {
 	Param,
 	Call,
 	Ret,
+	Alloca,
+	Load,
+	Store,
+	Global,
+	Const,
+	Extern,
+	Import,
+	Export,
+	Module,
+	Target,
+	Datalayout,
 	Eol,
 	Eof,
 }
@@ -37,19 +72,43 @@ impl std::fmt::Display for TokenKind {
 			TokenKind::Function => "function",
 			TokenKind::Iden(_) => "iden",
 			TokenKind::Num(_) => "num",
+			TokenKind::Float(_) => "float",
+			TokenKind::Str(_) => "string",
 			TokenKind::Comma => ",",
+			TokenKind::Colon => ":",
+			TokenKind::Arrow => "->",
 			TokenKind::Arg => "arg",
 			TokenKind::Equal => "=",
 			TokenKind::Plus => "+",
 			TokenKind::Minus => "-",
 			TokenKind::Mul => "*",
+			TokenKind::Rem => "%",
+			TokenKind::And => "&",
+			TokenKind::Or => "|",
+			TokenKind::Xor => "^",
+			TokenKind::Shl => "<<",
+			TokenKind::Shr => ">>",
+			TokenKind::LShr => ">>>",
+			TokenKind::Not => "!",
+			TokenKind::Tilde => "~",
 			TokenKind::Div => "/",
-			TokenKind::Comment => "//",
+			TokenKind::Comment(_) => "comment",
 			TokenKind::Goto => "goto",
 			TokenKind::Label => "label",
 			TokenKind::If => "if",
+			TokenKind::IfNot => "ifnot",
+			TokenKind::Else => "else",
+			TokenKind::Switch => "switch",
+			TokenKind::Default => "default",
+			TokenKind::While => "while",
+			TokenKind::Break => "break",
+			TokenKind::Continue => "continue",
 			TokenKind::LParen => "(",
 			TokenKind::RParen => ")",
+			TokenKind::LBracket => "[",
+			TokenKind::RBracket => "]",
+			TokenKind::LBrace => "{",
+			TokenKind::RBrace => "}",
 			TokenKind::Eq => "==",
 			TokenKind::Neq => "!=",
 			TokenKind::Gt => ">",
@@ -59,12 +118,103 @@ impl std::fmt::Display for TokenKind {
 			TokenKind::Param => "param",
 			TokenKind::Call => "call",
 			TokenKind::Ret => "ret",
+			TokenKind::Alloca => "alloca",
+			TokenKind::Load => "load",
+			TokenKind::Store => "store",
+			TokenKind::Global => "global",
+			TokenKind::Const => "const",
+			TokenKind::Extern => "extern",
+			TokenKind::Import => "import",
+			TokenKind::Export => "export",
+			TokenKind::Module => "module",
+			TokenKind::Target => "target",
+			TokenKind::Datalayout => "datalayout",
 			TokenKind::Eol => "EOL",
 			TokenKind::Eof => "EOF",
         })
     }
 }
 
+impl TokenKind {
+	pub fn from_keyword(text: &str) -> Option<Self> {
+		match text {
+			"function" => Some(TokenKind::Function),
+			"arg" => Some(TokenKind::Arg),
+			"goto" => Some(TokenKind::Goto),
+			"label" => Some(TokenKind::Label),
+			"if" => Some(TokenKind::If),
+			"ifnot" => Some(TokenKind::IfNot),
+			"else" => Some(TokenKind::Else),
+			"switch" => Some(TokenKind::Switch),
+			"default" => Some(TokenKind::Default),
+			"while" => Some(TokenKind::While),
+			"break" => Some(TokenKind::Break),
+			"continue" => Some(TokenKind::Continue),
+			"param" => Some(TokenKind::Param),
+			"call" => Some(TokenKind::Call),
+			"ret" => Some(TokenKind::Ret),
+			"alloca" => Some(TokenKind::Alloca),
+			"load" => Some(TokenKind::Load),
+			"store" => Some(TokenKind::Store),
+			"global" => Some(TokenKind::Global),
+			"const" => Some(TokenKind::Const),
+			"extern" => Some(TokenKind::Extern),
+			"import" => Some(TokenKind::Import),
+			"export" => Some(TokenKind::Export),
+			"module" => Some(TokenKind::Module),
+			"target" => Some(TokenKind::Target),
+			"datalayout" => Some(TokenKind::Datalayout),
+			_ => None,
+		}
+	}
+}
+
+pub fn is_iden_start(c: char) -> bool {
+	c.is_alphabetic() || c == '_'
+}
+
+pub fn is_iden_continue(c: char) -> bool {
+	c.is_alphanumeric() || c == '_' || c == '.'
+}
+
+pub fn is_iden_sigil(c: char) -> bool {
+	c == '%' || c == '@'
+}
+
+/// Whether `name` can be written without quotes, i.e. `[_a-z][_.a-z0-9]*`
+/// optionally preceded by a sigil, or a sigil followed by `[_.a-z0-9]+`.
+pub fn is_bare_iden(name: &str) -> bool {
+	let mut chars = name.chars();
+	match chars.next() {
+		Some(c) if is_iden_sigil(c) => !chars.as_str().is_empty() && chars.all(is_iden_continue),
+		Some(c) if is_iden_start(c) => chars.all(is_iden_continue) && TokenKind::from_keyword(name).is_none(),
+		_ => false,
+	}
+}
+
+/// Source form of an identifier; keywords and unusual names are quoted with backticks.
+pub fn format_iden(name: &str) -> String {
+	if is_bare_iden(name) {name.to_string()} else {format!("`{}`", name)}
+}
+
+/// Source form of a string literal; bytes without a printable form are written as escapes.
+pub fn format_str(bytes: &[u8]) -> String {
+	let mut text: String = String::from("\"");
+	for b in bytes.iter() {
+		match b {
+			b'\n' => text.push_str("\\n"),
+			b'\t' => text.push_str("\\t"),
+			b'\r' => text.push_str("\\r"),
+			0 => text.push_str("\\0"),
+			b'"' | b'\\' => text.push_str(&format!("\\{}", *b as char)),
+			0x20..=0x7e => text.push(*b as char),
+			_ => text.push_str(&format!("\\x{:02x}", b)),
+		}
+	}
+	text.push('"');
+	text
+}
+
 pub struct Token {
 	pub kind: TokenKind,
 	pub loc: Loc,
@@ -81,27 +231,19 @@ impl Token {
         Self{kind, loc}
     }
 	pub fn is_arith(&self) -> bool {
-		match self.kind {
-			TokenKind::Plus | TokenKind::Minus
-				| TokenKind::Mul | TokenKind::Div => true,
-			_ => false
-		}
+		matches!(self.kind, TokenKind::Plus | TokenKind::Minus | TokenKind::Mul | TokenKind::Div
+				| TokenKind::Rem | TokenKind::And | TokenKind::Or | TokenKind::Xor
+				| TokenKind::Shl | TokenKind::Shr | TokenKind::LShr)
 	}
 	pub fn is_relop(&self) -> bool {
-		match self.kind {
-			TokenKind::Eq | TokenKind::Neq | TokenKind::Gt
-				| TokenKind::Lt | TokenKind::Ge | TokenKind::Le => true,
-			_ => false
-		}
+		matches!(self.kind, TokenKind::Eq | TokenKind::Neq | TokenKind::Gt
+				| TokenKind::Lt | TokenKind::Ge | TokenKind::Le)
 	}
 	pub fn is_unary(&self) -> bool {
-		match self.kind {
-			TokenKind::Minus => true,
-			_ => false
-		}
+		matches!(self.kind, TokenKind::Minus | TokenKind::Not | TokenKind::Tilde)
 	}
-	pub fn error_token_kind_mismatch(&self, expected_kinds: Vec<TokenKind>) {
-		match expected_kinds.len() {
+	pub fn error_token_kind_mismatch(&self, expected_kinds: Vec<TokenKind>) -> Diagnostic {
+		let diagnostic: Diagnostic = match expected_kinds.len() {
 			0 => self.loc.error(format!("expected no token; but got token of kind '{}'", self.kind)),
 			1 => self.loc.error(format!("expected token of kind '{}'; but got '{}'",
 										expected_kinds[0], self.kind)),
@@ -113,16 +255,21 @@ impl Token {
 				self.loc.error(format!("expected token of kinds '{}'; but got '{}'",
 										expected_kinds_string, self.kind))
 			}
-		}
+		};
+		diagnostic.with_code("E0100")
 	}
-	pub fn assert_token_kind(&self, expected_kind: TokenKind) {
+	pub fn assert_token_kind(&self, expected_kind: TokenKind) -> Result<(), Diagnostic> {
 		let kind_matches: bool = match (&self.kind, expected_kind.clone()) {
             (TokenKind::Iden(_), TokenKind::Iden(_)) => true,
             (TokenKind::Num(_), TokenKind::Num(_)) => true,
+            (TokenKind::Float(_), TokenKind::Float(_)) => true,
+            (TokenKind::Str(_), TokenKind::Str(_)) => true,
+            (TokenKind::Comment(_), TokenKind::Comment(_)) => true,
             _ => self.kind == expected_kind,
         };
 		if !kind_matches {
-			self.error_token_kind_mismatch(vec![expected_kind]);
+			return Err(self.error_token_kind_mismatch(vec![expected_kind]));
 		}
+		Ok(())
 	}
 }
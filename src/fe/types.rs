@@ -0,0 +1,87 @@
+/// Types of IRL values; `i1` is a boolean holding either 0 or 1, `f32`/`f64`
+/// are IEEE 754 floats and `ptr` is the address of memory reserved by `alloca`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Type {
+	I1, I8, I16, I32, I64, F32, F64, Ptr,
+}
+
+impl Type {
+	pub fn from_name(name: &str) -> Option<Self> {
+		match name {
+			"i1" => Some(Type::I1),
+			"i8" => Some(Type::I8),
+			"i16" => Some(Type::I16),
+			"i32" => Some(Type::I32),
+			"i64" => Some(Type::I64),
+			"f32" => Some(Type::F32),
+			"f64" => Some(Type::F64),
+			"ptr" => Some(Type::Ptr),
+			_ => None,
+		}
+	}
+	pub fn bits(&self) -> u32 {
+		match self {
+			Type::I1 => 1,
+			Type::I8 => 8,
+			Type::I16 => 16,
+			Type::I32 => 32,
+			Type::I64 | Type::F64 | Type::Ptr => 64,
+			Type::F32 => 32,
+		}
+	}
+	/// Storage size in bytes.
+	pub fn size(&self) -> usize {
+		match self {
+			Type::I1 | Type::I8 => 1,
+			Type::I16 => 2,
+			Type::I32 | Type::F32 => 4,
+			Type::I64 | Type::F64 | Type::Ptr => 8,
+		}
+	}
+	pub fn is_float(&self) -> bool {
+		matches!(self, Type::F32 | Type::F64)
+	}
+	pub fn min(&self) -> i64 {
+		match self {
+			Type::I1 => 0,
+			_ => i64::MIN >> (64 - self.bits()),
+		}
+	}
+	pub fn max(&self) -> i64 {
+		match self {
+			Type::I1 => 1,
+			_ => i64::MAX >> (64 - self.bits()),
+		}
+	}
+	pub fn contains(&self, value: i64) -> bool {
+		self.min() <= value && value <= self.max()
+	}
+	/// Mask selecting the bits of a value of this type, used to zero extend it.
+	pub fn mask(&self) -> u64 {
+		u64::MAX >> (64 - self.bits())
+	}
+	/// Truncates `value` to the width of the type, i.e. two's complement wrap around.
+	pub fn wrap(&self, value: i64) -> i64 {
+		match self {
+			Type::I1 => value & 1,
+			_ => (value << (64 - self.bits())) >> (64 - self.bits()),
+		}
+	}
+	/// Rounds `value` to the precision of the float type.
+	pub fn round(&self, value: f64) -> f64 {
+		match self {
+			Type::F32 => value as f32 as f64,
+			_ => value,
+		}
+	}
+}
+
+impl std::fmt::Display for Type {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Type::Ptr => write!(f, "ptr"),
+			_ if self.is_float() => write!(f, "f{}", self.bits()),
+			_ => write!(f, "i{}", self.bits()),
+		}
+	}
+}
@@ -1,4 +1,4 @@
-use crate::fe::{lexer::Lexer, parser::Parser, ast::AstNode};
+use crate::fe::{loader::load_program, prelude::prelude, ast::{AstNode, ModuleAstNode}, diagnostic::Diagnostics};
 use crate::mw::default_ast_pass_manager::*;
 use crate::opt::{default_compiler_pass_manager::*, cfg::*};
 use crate::trn::transpiler::*;
@@ -10,61 +10,70 @@ pub mod opt;
 pub mod trn;
 pub mod cli;
 
+fn exit_on_errors(diagnostics: &mut Diagnostics, options: &CliOptions) {
+	if diagnostics.has_errors() {
+		diagnostics.emit(&options.error_format, options.color);
+		std::process::exit(1);
+	}
+}
+
 fn main() {
 	let options: CliOptions = CliOptions::new();
+	let mut diagnostics: Diagnostics = Diagnostics::new();
 
 	// module: fe
-	let lexer = Lexer::new(options.filepath.clone());
+	let mut module: ModuleAstNode = load_program(options.filepath.clone(), &mut diagnostics);
 	options.verbose_message(String::from("lexing complete"));
-	let parser = Parser::new(lexer.tokens);
-	options.verbose_message(String::from("parsing complete"));
-	let mut ast: Vec<AstNode> = parser.nodes;
+	// printed before bailing out so a partially parsed file can still be inspected
 	if options.debug {
 		println!("Initial AST");
 		println!("===========");
-		for node in ast.iter() {
-			println!("{}", node);
-		}
+		print!("{}", module);
 	}
+	module.nodes.append(&mut prelude(&mut diagnostics));
+	exit_on_errors(&mut diagnostics, &options);
+	options.verbose_message(String::from("parsing complete"));
 	options.verbose_message(String::from("FE over"));
 
 	// module: mw
-	run_default_ast_pass_manager(&mut ast, &options);
+	run_default_ast_pass_manager(&mut module.nodes, &options, &mut diagnostics);
+	exit_on_errors(&mut diagnostics, &options);
 	options.verbose_message(String::from("MW over"));
 	if options.debug {
 		println!("MW Optimized AST");
 		println!("===========");
-		for node in ast.iter() {
-			println!("{}", node);
-		}
+		print!("{}", module);
 	}
 
 	// module: opt
-	let mut cfg_table: Vec<ControlFlowGraph> = cfg_table_from_program(&ast);
-	run_default_compiler_pass_manager(&mut cfg_table);
+	let mut cfg_table: Vec<ControlFlowGraph> = cfg_table_from_program(&module.nodes);
+	run_default_compiler_pass_manager(&mut cfg_table, &mut diagnostics);
+	exit_on_errors(&mut diagnostics, &options);
 	if options.cfg {
 		let dot_filepath: String = replace_extension(options.filepath.clone(), "irl", "dot");
 		dump_cfg_table_to_svg(&cfg_table, dot_filepath.to_string());
 		options.run_command(&["dot", "-Tsvg", "-O", dot_filepath.as_str()]);
 		options.verbose_error(format!("created control flow graph svg '{}.svg'", dot_filepath));
 	}
-	options.verbose_message(format!("OPT over"));
+	options.verbose_message(String::from("OPT over"));
 	
-	ast.clear();
-	for cfg in cfg_table.iter() {
-		ast.push(cfg.generate_ast());
+	let mut cfgs = cfg_table.iter();
+	for node in module.nodes.iter_mut() {
+		if let AstNode::Function(_) = node {
+			*node = cfgs.next().unwrap().generate_ast();
+		}
 	}
 	if options.debug {
 		println!("OPT Optimized AST");
 		println!("===========");
-		for node in ast.iter() {
-			println!("{}", node);
-		}
+		print!("{}", module);
 	}
 
 	if options.run {
 		options.run_command(&[&remove_extension(options.filepath.clone(), "irl")]);
 	}
 
-	transpile(&options, &ast, options.filepath.clone());
+	transpile(&options, &module, options.filepath.clone(), &mut diagnostics);
+	exit_on_errors(&mut diagnostics, &options);
+	diagnostics.emit(&options.error_format, options.color);
 }
@@ -1,9 +1,9 @@
-use crate::{fe::ast::*, mw::pass::AstPass};
+use crate::{fe::{ast::*, diagnostic::Diagnostics}, mw::pass::AstPass};
 
 pub struct AddGotoPass;
 
 impl AstPass for AddGotoPass {
-	fn apply(&self, nodes: &mut Vec<AstNode>) {
+	fn apply(&self, nodes: &mut Vec<AstNode>, _diagnostics: &mut Diagnostics) {
 		let _ = helper(nodes, 0);
 	}
 	fn name(&self) -> String {String::from("add_goto_pass")}
@@ -13,18 +13,25 @@ fn helper(nodes: &mut Vec<AstNode>, ix: usize) -> bool {
 	if ix >= nodes.len() {
 		return false;
 	} 
+	// comments do not fall through, so look past them for the next label
+	let next: usize = (ix+1..nodes.len()).find(|&jx| !nodes[jx].is_comment()).unwrap_or(nodes.len());
 	let add: bool = match &mut nodes[ix] {
 		AstNode::Function(node) => helper(&mut node.body, 0),
-		AstNode::Label(node) => match node.body[node.body.len()-1] {
-			AstNode::Goto(_) => false,
-			AstNode::Ret(_) => false,
+		AstNode::Label(node) => match node.body.iter().rev().find(|node| !node.is_comment()) {
+			Some(AstNode::Goto(_)) => false,
+			Some(AstNode::Switch(_)) => false,
+			Some(AstNode::Ret(_)) => false,
+			Some(AstNode::If(if_node)) if if_node.otherwise.is_some() => false,
 			_ => true,
 		},
 		AstNode::Goto(_) => false,
-		_ => if ix+1 < nodes.len() {matches!(nodes[ix+1], AstNode::Label(_))} else {false},
+		AstNode::Switch(_) => false,
+		AstNode::If(node) if node.otherwise.is_some() => false,
+		AstNode::Comment(_) => false,
+		_ => if next < nodes.len() {matches!(nodes[next], AstNode::Label(_))} else {false},
 	};
 	if add {
-		if let Some(AstNode::Label(next_label_node)) = nodes.get(ix + 1) {
+		if let Some(AstNode::Label(next_label_node)) = nodes.get(next) {
 			let goto_node = AstNode::Goto(GotoAstNode {
 				name: next_label_node.name.clone(),
 				loc: next_label_node.loc.clone(),
@@ -32,7 +39,7 @@ fn helper(nodes: &mut Vec<AstNode>, ix: usize) -> bool {
 			if let AstNode::Label(current_label_node) = &mut nodes[ix] {
 				current_label_node.body.push(goto_node);
 			} else {
-				nodes.insert(ix + 1, goto_node);
+				nodes.insert(next, goto_node);
 			}
 		}
 	}
@@ -1,21 +1,80 @@
-use std::collections::HashSet;
-use crate::{fe::ast::*, mw::pass::AstPass};
+use std::collections::{HashMap, HashSet};
+use crate::{fe::{ast::*, loc::Loc, types::Type, diagnostic::Diagnostics}, mw::pass::AstPass};
 
 pub struct AsmValidationPass;
 
+/// Argument and return types of a function definition or extern declaration.
+fn signature(node: &AstNode) -> Option<(String, Vec<Type>, Option<Type>, Loc)> {
+	match node {
+		AstNode::Function(node) => Some((node.name.clone(), node.args.iter().map(|arg| arg.ty()).collect(),
+										 node.ret_ty, node.loc.clone())),
+		AstNode::Extern(node) => Some((node.name.clone(), node.args.iter().map(|arg| arg.ty()).collect(),
+									   node.ret_ty, node.loc.clone())),
+		_ => None,
+	}
+}
+
+fn format_signature(args: &[Type], ret_ty: Option<Type>) -> String {
+	format!("({}) -> {}", args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>().join(", "),
+			format_ret_ty(ret_ty))
+}
+
 impl AstPass for AsmValidationPass {
-	fn apply(&self, nodes: &mut Vec<AstNode>) {
-		if nodes.len() == 0 {return;}
+	fn apply(&self, nodes: &mut Vec<AstNode>, diagnostics: &mut Diagnostics) {
+		if nodes.is_empty() {return;}
 		let mut function_names: HashSet<String> = HashSet::new();
+		let mut globals: HashMap<String, Loc> = HashMap::new();
+		let mut signatures: HashMap<String, (Vec<Type>, Option<Type>, Loc)> = HashMap::new();
+		for node in nodes.iter() {
+			if let Some((name, args, ret_ty, loc)) = signature(node) {
+				match signatures.get(&name) {
+					Some((first_args, first_ret_ty, first_loc)) if *first_args != args || *first_ret_ty != ret_ty => {
+						diagnostics.push(loc.error(format!("conflicting declarations of '{}'", name))
+										 .with_code("E0209")
+										 .with_label(format!("declared here as '{}'", format_signature(&args, ret_ty)))
+										 .with_secondary_label(first_loc.clone(), format!(
+											 "previously declared as '{}'", format_signature(first_args, *first_ret_ty))));
+					},
+					Some(_) => {},
+					None => {signatures.insert(name, (args, ret_ty, loc));},
+				}
+			}
+			if let AstNode::Global(global_node) = node {
+				if let Some(first_loc) = globals.get(&global_node.name) {
+					diagnostics.push(global_node.loc.error(format!("global already defined '{}'", global_node.name))
+									 .with_code("E0206")
+									 .with_label(String::from("global redefined here"))
+									 .with_secondary_label(first_loc.clone(), String::from("global first defined here")));
+					continue;
+				}
+				globals.insert(global_node.name.clone(), global_node.loc.clone());
+			}
+		}
 		for node in nodes.iter() {
 			if let AstNode::Function(function_node) = node {
 				function_names.insert(function_node.name.clone());
-			} else {
-				node.loc().error(String::from("expected top level function instruction"));
+				for arg in function_node.args.iter() {
+					if let AstNode::Iden(iden_node) = arg {
+						if let Some(global_loc) = globals.get(&iden_node.name) {
+							diagnostics.push(iden_node.loc.error(format!("argument shadows global '{}'", iden_node.name))
+											 .with_code("E0206")
+											 .with_label(String::from("argument declared here"))
+											 .with_secondary_label(global_loc.clone(), String::from("global defined here"))
+											 .with_help(String::from("rename the argument")));
+						}
+					}
+				}
+			} else if !node.is_comment() && !matches!(node, AstNode::Global(_) | AstNode::Const(_) | AstNode::Extern(_)) {
+				diagnostics.push(node.loc().error(String::from("expected top level function or global instruction"))
+								 .with_code("E0200")
+								 .with_help(String::from("move this instruction into a function body")));
 			}
 		}
 		if !function_names.contains(&String::from("main")) {
-			nodes[0].loc().program_error(String::from("program entry point aka function 'main' not found"));
+			diagnostics.push(nodes[0].loc()
+							 .program_error(String::from("program entry point aka function 'main' not found"))
+							 .with_code("E0201")
+							 .with_help(String::from("define the entry point as 'function main, 0'")));
 		}
 	}
 	fn name(&self) -> String {String::from("asm_validation_pass")}
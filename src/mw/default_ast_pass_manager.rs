@@ -1,20 +1,29 @@
-use crate::{fe::ast::AstNode, mw::pass::*};
+use crate::{fe::{ast::AstNode, diagnostic::Diagnostics}, mw::pass::*};
 use crate::cli::CliOptions;
 use crate::mw::validate_iden_pass::ValidateIdenPass;
 use crate::mw::add_goto_pass::AddGotoPass;
+use crate::mw::type_check_pass::TypeCheckPass;
 use crate::mw::asm_validation_pass::AsmValidationPass;
+use crate::mw::flatten_expr_pass::FlattenExprPass;
+use crate::mw::lower_control_flow_pass::LowerControlFlowPass;
+use crate::mw::substitute_const_pass::SubstituteConstPass;
 
 const PASS_MAX_APPLICATION_LIMIT: usize = 1;
 
-pub fn run_default_ast_pass_manager(nodes: &mut Vec<AstNode>, options: &CliOptions) {
+pub fn run_default_ast_pass_manager(nodes: &mut Vec<AstNode>, options: &CliOptions,
+									 diagnostics: &mut Diagnostics) {
 	let mut ast_pass_manager: AstPassManager = AstPassManager::new();
+	ast_pass_manager.add(LowerControlFlowPass{});
+	ast_pass_manager.add(SubstituteConstPass{});
 	ast_pass_manager.add(AsmValidationPass{});
 	ast_pass_manager.add(ValidateIdenPass{});
+	ast_pass_manager.add(TypeCheckPass{});
+	ast_pass_manager.add(FlattenExprPass{});
 	ast_pass_manager.add(AddGotoPass{});
 
 	for _ in 0..PASS_MAX_APPLICATION_LIMIT {
 		let prev_nodes: Vec<AstNode> = nodes.clone();
-		ast_pass_manager.run(nodes, options);
-		if prev_nodes == *nodes {break;}
+		ast_pass_manager.run(nodes, options, diagnostics);
+		if diagnostics.has_errors() || prev_nodes == *nodes {break;}
 	}
 }
@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+use crate::{fe::{ast::*, loc::Loc, types::Type, diagnostic::Diagnostics}, mw::pass::AstPass};
+
+/// Lowers nested expressions to three-address code: every operand of an arithmetic,
+/// comparison or unary expression that is an expression itself is first computed into
+/// a fresh temporary, innermost and leftmost first. Runs after type_check_pass so that
+/// each temporary is annotated with the type its expression was checked at.
+pub struct FlattenExprPass;
+
+struct Context {
+	/// names of the values of the function, which temporaries must not reuse
+	taken: HashSet<String>,
+	next: usize,
+}
+
+impl Context {
+	fn fresh(&mut self) -> String {
+		loop {
+			let name: String = format!("%t{}", self.next);
+			self.next += 1;
+			if self.taken.insert(name.clone()) {
+				return name;
+			}
+		}
+	}
+}
+
+impl AstPass for FlattenExprPass {
+	fn apply(&self, nodes: &mut Vec<AstNode>, _diagnostics: &mut Diagnostics) {
+		let globals: HashSet<String> = nodes.iter().filter_map(|node| match node {
+			AstNode::Global(node) => Some(node.name.clone()),
+			_ => None,
+		}).collect();
+		for node in nodes.iter_mut() {
+			let taken: HashSet<String> = node.value_operands().union(&globals).cloned().collect();
+			if let AstNode::Function(node) = node {
+				flatten_body(&mut node.body, &mut Context{taken, next: 0});
+			}
+		}
+	}
+	fn name(&self) -> String {String::from("flatten_expr_pass")}
+}
+
+fn flatten_body(nodes: &mut Vec<AstNode>, context: &mut Context) {
+	let mut flattened: Vec<AstNode> = Vec::with_capacity(nodes.len());
+	for mut node in nodes.drain(..) {
+		match &mut node {
+			AstNode::Label(node) => flatten_body(&mut node.body, context),
+			AstNode::Assignment(node) => {
+				let ty: Type = node.ty.unwrap_or(Type::I32);
+				flatten_operands(&mut node.var, ty, &mut flattened, context);
+			},
+			_ => {},
+		}
+		flattened.push(node);
+	}
+	*nodes = flattened;
+}
+
+/// Type an operand or expression was checked at; `None` for comparisons and '!' whose
+/// outcome takes the type of their context.
+fn expr_ty(node: &AstNode) -> Option<Type> {
+	match node {
+		AstNode::Iden(_) | AstNode::Num(_) | AstNode::Float(_) | AstNode::Str(_) => Some(node.ty()),
+		AstNode::Arith(node) => expr_ty(&node.lhs).or(expr_ty(&node.rhs)),
+		AstNode::Unary(node) if node.op != UnaryOp::Not => expr_ty(&node.var),
+		_ => None,
+	}
+}
+
+/// Replaces the operands of the expression `node`, checked at `ty`, that are expressions
+/// by temporaries whose assignments are appended to `insts`.
+fn flatten_operands(node: &mut AstNode, ty: Type, insts: &mut Vec<AstNode>, context: &mut Context) {
+	match node {
+		AstNode::Arith(node) => {
+			// a pointer is offset by an 'i32'
+			let rhs_ty: Type = if ty == Type::Ptr {Type::I32} else {ty};
+			flatten_operand(&mut node.lhs, ty, insts, context);
+			flatten_operand(&mut node.rhs, rhs_ty, insts, context);
+		},
+		AstNode::Relop(node) => {
+			let ty: Type = expr_ty(&node.lhs).or(expr_ty(&node.rhs)).unwrap_or(Type::I32);
+			flatten_operand(&mut node.lhs, ty, insts, context);
+			flatten_operand(&mut node.rhs, ty, insts, context);
+		},
+		AstNode::Unary(node) if node.op == UnaryOp::Not => {
+			let ty: Type = expr_ty(&node.var).unwrap_or(Type::I32);
+			flatten_operand(&mut node.var, ty, insts, context);
+		},
+		AstNode::Unary(node) => flatten_operand(&mut node.var, ty, insts, context),
+		_ => {},
+	}
+}
+
+fn flatten_operand(node: &mut Box<AstNode>, ty: Type, insts: &mut Vec<AstNode>, context: &mut Context) {
+	if !matches!(**node, AstNode::Arith(_) | AstNode::Relop(_) | AstNode::Unary(_)) {
+		return;
+	}
+	flatten_operands(node, ty, insts, context);
+	let name: String = context.fresh();
+	let loc: Loc = node.loc();
+	let var: AstNode = std::mem::replace(&mut **node, AstNode::Iden(IdenAstNode{name: name.clone(), ty, loc: loc.clone()}));
+	insts.push(AstNode::Assignment(AssignmentAstNode{name, ty: Some(ty), var: Box::new(var), loc}));
+}
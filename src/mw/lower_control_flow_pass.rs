@@ -0,0 +1,228 @@
+use std::collections::HashSet;
+use crate::{fe::{ast::*, loc::Loc, diagnostic::Diagnostics}, mw::pass::AstPass};
+
+/// Lowers `while` loops, `if` blocks, `break` and `continue` to labels, branches and gotos.
+/// The labels it places are numbered per function and named after the statement they come
+/// from, e.g. `while.0.cond` and `while.0.end`, skipping the names the function already uses.
+pub struct LowerControlFlowPass;
+
+/// Labels the `continue` and `break` of a loop jump to.
+struct Loop {
+	cond: String,
+	end: String,
+}
+
+struct Context {
+	/// labels of the function, which generated labels must not reuse
+	taken: HashSet<String>,
+	next: usize,
+	loops: Vec<Loop>,
+	/// lowered body; once a label is placed, statements are added to it
+	nodes: Vec<AstNode>,
+}
+
+impl Context {
+	/// Labels `{kind}.{n}.{part}` for every part, with the first `n` for which all are free.
+	fn fresh(&mut self, kind: &str, parts: &[&str]) -> Vec<String> {
+		loop {
+			let names: Vec<String> = parts.iter().map(|part| format!("{}.{}.{}", kind, self.next, part)).collect();
+			self.next += 1;
+			if names.iter().all(|name| !self.taken.contains(name)) {
+				self.taken.extend(names.iter().cloned());
+				return names;
+			}
+		}
+	}
+	fn push(&mut self, node: AstNode) {
+		match self.nodes.last_mut() {
+			Some(AstNode::Label(label_node)) => label_node.body.push(node),
+			_ => self.nodes.push(node),
+		}
+	}
+	fn place_label(&mut self, name: String, loc: Loc) {
+		self.nodes.push(AstNode::Label(LabelAstNode{name, body: vec![], lowered: true, loc}));
+	}
+	fn goto(&mut self, name: String, loc: Loc) {
+		self.push(AstNode::Goto(GotoAstNode{name, loc}));
+	}
+	/// Whether control can reach past the statements lowered so far.
+	fn falls_through(&self) -> bool {
+		let body: &Vec<AstNode> = match self.nodes.last() {
+			Some(AstNode::Label(label_node)) => &label_node.body,
+			_ => &self.nodes,
+		};
+		match body.iter().rev().find(|node| !node.is_comment()) {
+			Some(AstNode::Goto(_)) | Some(AstNode::Ret(_)) | Some(AstNode::Switch(_)) => false,
+			Some(AstNode::If(if_node)) => if_node.otherwise.is_none(),
+			_ => true,
+		}
+	}
+}
+
+impl AstPass for LowerControlFlowPass {
+	fn apply(&self, nodes: &mut Vec<AstNode>, diagnostics: &mut Diagnostics) {
+		for node in nodes.iter_mut() {
+			if let AstNode::Function(node) = node {
+				let mut taken: HashSet<String> = HashSet::new();
+				for body_node in node.body.iter() {
+					if let AstNode::Label(label_node) = body_node {
+						taken.insert(label_node.name.clone());
+					}
+				}
+				let mut context: Context = Context{taken, next: 0, loops: vec![], nodes: vec![]};
+				for body_node in node.body.drain(..) {
+					lower_node(body_node, &mut context, diagnostics);
+				}
+				node.body = context.nodes;
+			}
+		}
+	}
+	fn name(&self) -> String {String::from("lower_control_flow_pass")}
+}
+
+fn lower_body(nodes: Vec<AstNode>, context: &mut Context, diagnostics: &mut Diagnostics) {
+	for node in nodes.into_iter() {
+		lower_node(node, context, diagnostics);
+	}
+}
+
+fn lower_node(node: AstNode, context: &mut Context, diagnostics: &mut Diagnostics) {
+	match node {
+		AstNode::Label(mut node) => {
+			let body: Vec<AstNode> = std::mem::take(&mut node.body);
+			context.nodes.push(AstNode::Label(node));
+			lower_body(body, context, diagnostics);
+		},
+		AstNode::While(node) => {
+			let labels: Vec<String> = context.fresh("while", &["cond", "end"]);
+			let (cond, end): (String, String) = (labels[0].clone(), labels[1].clone());
+			context.place_label(cond.clone(), node.loc.clone());
+			context.push(AstNode::If(IfAstNode{condition: node.condition, label: end.clone(), otherwise: None,
+											   negated: true, loc: node.loc.clone()}));
+			context.loops.push(Loop{cond: cond.clone(), end: end.clone()});
+			lower_body(node.body, context, diagnostics);
+			context.loops.pop();
+			if context.falls_through() {
+				context.goto(cond, node.loc.clone());
+			}
+			context.place_label(end, node.loc);
+		},
+		AstNode::IfBlock(node) => {
+			let else_body: Vec<AstNode> = match node.else_body {
+				Some(else_body) => else_body,
+				None => {
+					let end: String = context.fresh("if", &["end"]).remove(0);
+					context.push(AstNode::If(IfAstNode{condition: node.condition, label: end.clone(), otherwise: None,
+													   negated: true, loc: node.loc.clone()}));
+					lower_body(node.then_body, context, diagnostics);
+					context.place_label(end, node.loc);
+					return;
+				},
+			};
+			let labels: Vec<String> = context.fresh("if", &["else", "end"]);
+			let (otherwise, end): (String, String) = (labels[0].clone(), labels[1].clone());
+			context.push(AstNode::If(IfAstNode{condition: node.condition, label: otherwise.clone(), otherwise: None,
+											   negated: true, loc: node.loc.clone()}));
+			lower_body(node.then_body, context, diagnostics);
+			let then_falls_through: bool = context.falls_through();
+			if then_falls_through {
+				context.goto(end.clone(), node.loc.clone());
+			}
+			context.place_label(otherwise, node.loc.clone());
+			lower_body(else_body, context, diagnostics);
+			// an end label nothing jumps or falls to would start an unreachable block
+			if then_falls_through || context.falls_through() {
+				context.place_label(end, node.loc);
+			}
+		},
+		AstNode::LoopJump(node) => {
+			let target: Option<String> = context.loops.last()
+				.map(|inner| if node.exit {inner.end.clone()} else {inner.cond.clone()});
+			match target {
+				Some(target) => context.goto(target, node.loc),
+				None => diagnostics.push(node.loc.error(format!("'{}' outside of a loop",
+																 if node.exit {"break"} else {"continue"}))
+										 .with_code("E0211")
+										 .with_label(String::from("not inside a 'while' loop"))),
+			}
+		},
+		node => context.push(node),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::fe::{lexer::Lexer, parser::Parser};
+
+	/// Lowers the functions in `source`, given one statement per line, and returns the lines of
+	/// the lowered program without their indentation.
+	fn lower(source: &[&str]) -> (Vec<String>, Diagnostics) {
+		let mut diagnostics: Diagnostics = Diagnostics::new();
+		let lexer: Lexer = Lexer::from_source(&source.join("\n"), String::from("test.irl"), &mut diagnostics);
+		let mut nodes: Vec<AstNode> = Parser::new(lexer.tokens, &mut diagnostics).nodes;
+		assert!(diagnostics.diagnostics.is_empty(), "unexpected diagnostics parsing {:?}", source);
+		LowerControlFlowPass{}.apply(&mut nodes, &mut diagnostics);
+		let lines: Vec<String> = nodes.iter()
+			.flat_map(|node| node.to_string().lines().map(|line| line.trim().to_string()).collect::<Vec<String>>())
+			.collect();
+		(lines, diagnostics)
+	}
+
+	fn lowers_to(source: &[&str], expected: &[&str]) {
+		let (lowered, diagnostics) = lower(source);
+		assert!(diagnostics.diagnostics.is_empty());
+		assert_eq!(lowered, expected);
+	}
+
+	#[test]
+	fn while_loop() {
+		lowers_to(&["function main, 0", "i = 0", "while (i < 5) {", "i = i + 1", "}", "ret i"],
+				  &["function main, 0", "i = 0", "label while.0.cond", "ifnot (i < 5) goto while.0.end", "i = i + 1",
+					"goto while.0.cond", "label while.0.end", "ret i"]);
+	}
+
+	#[test]
+	fn break_and_continue_jump_to_the_innermost_loop() {
+		lowers_to(&["function main, 0", "while (i < 5) {", "while (j < 5) {", "break", "}", "continue", "}", "ret 0"],
+				  &["function main, 0", "label while.0.cond", "ifnot (i < 5) goto while.0.end",
+					"label while.1.cond", "ifnot (j < 5) goto while.1.end", "goto while.1.end", "label while.1.end",
+					"goto while.0.cond", "label while.0.end", "ret 0"]);
+	}
+
+	#[test]
+	fn if_without_else() {
+		lowers_to(&["function main, 0", "if (x == 1) {", "x = 2", "}", "ret x"],
+				  &["function main, 0", "ifnot (x == 1) goto if.0.end", "x = 2", "label if.0.end", "ret x"]);
+	}
+
+	#[test]
+	fn if_else() {
+		lowers_to(&["function main, 0", "if (x == 1) {", "x = 2", "} else {", "x = 3", "}", "ret x"],
+				  &["function main, 0", "ifnot (x == 1) goto if.0.else", "x = 2", "goto if.0.end", "label if.0.else",
+					"x = 3", "label if.0.end", "ret x"]);
+	}
+
+	#[test]
+	fn if_else_with_both_arms_jumping_has_no_end_label() {
+		lowers_to(&["function main, 0", "while (i < 5) {", "if (i == 3) {", "break", "} else {", "i = i + 1",
+					"continue", "}", "}", "ret i"],
+				  &["function main, 0", "label while.0.cond", "ifnot (i < 5) goto while.0.end",
+					"ifnot (i == 3) goto if.1.else", "goto while.0.end", "label if.1.else", "i = i + 1",
+					"goto while.0.cond", "label while.0.end", "ret i"]);
+	}
+
+	#[test]
+	fn generated_labels_skip_the_functions_own() {
+		lowers_to(&["function main, 0", "if (x == 1) {", "x = 2", "}", "label if.0.end", "ret x"],
+				  &["function main, 0", "ifnot (x == 1) goto if.1.end", "x = 2", "label if.1.end", "label if.0.end",
+					"ret x"]);
+	}
+
+	#[test]
+	fn loop_jumps_outside_of_a_loop() {
+		let (_, diagnostics) = lower(&["function main, 0", "break", "if (x == 1) {", "continue", "}", "ret 0"]);
+		let codes: Vec<Option<String>> = diagnostics.diagnostics.iter().map(|diagnostic| diagnostic.code.clone()).collect();
+		assert_eq!(codes, vec![Some(String::from("E0211")), Some(String::from("E0211"))]);
+	}
+}
@@ -3,3 +3,7 @@ pub mod validate_iden_pass;
 pub mod add_goto_pass;
 pub mod default_ast_pass_manager;
 pub mod asm_validation_pass;
+pub mod type_check_pass;
+pub mod flatten_expr_pass;
+pub mod lower_control_flow_pass;
+pub mod substitute_const_pass;
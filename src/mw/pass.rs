@@ -1,26 +1,29 @@
-use crate::fe::ast::AstNode;
+use crate::fe::{ast::AstNode, diagnostic::Diagnostics};
 use crate::cli::CliOptions;
 
 pub trait AstPass {
-	fn apply(&self, nodes: &mut Vec<AstNode>);
+	fn apply(&self, nodes: &mut Vec<AstNode>, diagnostics: &mut Diagnostics);
 	fn name(&self) -> String;
 }
 
+#[derive(Default)]
 pub struct AstPassManager {
 	passes: Vec<Box<dyn AstPass>>,
 }
 
 impl AstPassManager {
 	pub fn new() -> Self {
-		Self{passes: vec![]}
+		Self::default()
 	}
 	pub fn add<T: AstPass + 'static>(&mut self, pass: T) {
 		self.passes.push(Box::new(pass));
 	}
-	pub fn run(&self, nodes: &mut Vec<AstNode>, options: &CliOptions) {
+	pub fn run(&self, nodes: &mut Vec<AstNode>, options: &CliOptions, diagnostics: &mut Diagnostics) {
 		for pass in self.passes.iter() {
 			options.verbose_message(format!("running '{}'", pass.name()));
-			pass.apply(nodes);
+			let diagnostics_len: usize = diagnostics.diagnostics.len();
+			pass.apply(nodes, diagnostics);
+			diagnostics.set_pass_from(diagnostics_len, pass.name());
 		}
 	}
 }
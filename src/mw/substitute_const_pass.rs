@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use crate::{fe::{ast::*, loc::Loc, types::Type, diagnostic::Diagnostics}, mw::pass::AstPass};
+
+/// Evaluates every `const` and replaces its uses in functions and global initializers by
+/// the resulting literal, whose type is then settled by its context as for any literal.
+/// Constant expressions are evaluated as `i64` or `f64`.
+pub struct SubstituteConstPass;
+
+struct Context<'a> {
+	defs: HashMap<String, &'a ConstAstNode>,
+	/// value of every constant evaluated so far, `None` if it could not be evaluated
+	values: HashMap<String, Option<AstNode>>,
+	/// constants being evaluated, innermost last
+	stack: Vec<String>,
+}
+
+impl Context<'_> {
+	fn evaluate(&mut self, name: &String, diagnostics: &mut Diagnostics) -> Option<AstNode> {
+		if let Some(value) = self.values.get(name) {
+			return value.clone();
+		}
+		let def: &ConstAstNode = self.defs[name];
+		self.stack.push(name.clone());
+		let mut var: AstNode = (*def.var).clone();
+		let value: Option<AstNode> = match self.substitute_operands(&mut var, diagnostics) {
+			true => {
+				widen(&mut var);
+				let loc: Loc = var.loc();
+				match var.evaluate() {
+					Value::Constant(c) => Some(AstNode::Num(NumAstNode{num: c, ty: Type::I32, loc})),
+					Value::Float(c) => Some(AstNode::Float(FloatAstNode{num: c, ty: Type::F64, loc})),
+					_ => {
+						diagnostics.push(loc.error(format!("cannot evaluate constant '{}'", name))
+										 .with_code("E0215")
+										 .with_label(String::from("not a constant integer or float expression"))
+										 .with_note(String::from("integer and float operands cannot be mixed and division by zero is undefined")));
+						None
+					},
+				}
+			},
+			false => None,
+		};
+		self.stack.pop();
+		self.values.insert(name.clone(), value.clone());
+		value
+	}
+	/// Replaces the constants among the operands of the constant expression `node` by
+	/// their values; false if any of them is not a constant or could not be evaluated.
+	fn substitute_operands(&mut self, node: &mut AstNode, diagnostics: &mut Diagnostics) -> bool {
+		match node {
+			AstNode::Iden(iden_node) => {
+				if !self.defs.contains_key(&iden_node.name) {
+					diagnostics.push(iden_node.loc.error(format!("'{}' is not a constant", iden_node.name))
+									 .with_code("E0215")
+									 .with_label(String::from("constant expressions can only use literals and other constants")));
+					return false;
+				}
+				if self.stack.contains(&iden_node.name) {
+					diagnostics.push(iden_node.loc.error(format!("cycle in the definition of constant '{}'", iden_node.name))
+									 .with_code("E0214")
+									 .with_label(String::from("used here while it is being evaluated"))
+									 .with_secondary_label(self.defs[&iden_node.name].loc.clone(),
+														   String::from("constant defined here")));
+					return false;
+				}
+				let name: String = iden_node.name.clone();
+				let loc: Loc = iden_node.loc.clone();
+				match self.evaluate(&name, diagnostics) {
+					Some(value) => {
+						*node = with_loc(value, loc);
+						true
+					},
+					None => false,
+				}
+			},
+			AstNode::Arith(node) => self.substitute_operands(&mut node.lhs, diagnostics)
+				& self.substitute_operands(&mut node.rhs, diagnostics),
+			AstNode::Relop(node) => self.substitute_operands(&mut node.lhs, diagnostics)
+				& self.substitute_operands(&mut node.rhs, diagnostics),
+			AstNode::Unary(node) => self.substitute_operands(&mut node.var, diagnostics),
+			_ => true,
+		}
+	}
+}
+
+/// Literal `value` placed at `loc`, i.e. at the use of the constant it is the value of.
+fn with_loc(value: AstNode, loc: Loc) -> AstNode {
+	match value {
+		AstNode::Num(node) => AstNode::Num(NumAstNode{loc, ..node}),
+		AstNode::Float(node) => AstNode::Float(FloatAstNode{loc, ..node}),
+		value => value,
+	}
+}
+
+/// Types the literals of a constant expression at the widest integer and float types.
+fn widen(node: &mut AstNode) {
+	match node {
+		AstNode::Num(node) => node.ty = Type::I64,
+		AstNode::Float(node) => node.ty = Type::F64,
+		AstNode::Arith(node) => {
+			widen(&mut node.lhs);
+			widen(&mut node.rhs);
+		},
+		AstNode::Relop(node) => {
+			widen(&mut node.lhs);
+			widen(&mut node.rhs);
+		},
+		AstNode::Unary(node) => widen(&mut node.var),
+		_ => {},
+	}
+}
+
+/// Replaces the constants among the operands of `node`, or of the instructions in its body,
+/// by their values.
+fn substitute(node: &mut AstNode, values: &HashMap<String, AstNode>) {
+	match node {
+		AstNode::Iden(iden_node) => if let Some(value) = values.get(&iden_node.name) {
+			*node = with_loc(value.clone(), iden_node.loc.clone());
+		},
+		AstNode::Arith(node) => {
+			substitute(&mut node.lhs, values);
+			substitute(&mut node.rhs, values);
+		},
+		AstNode::Relop(node) => {
+			substitute(&mut node.lhs, values);
+			substitute(&mut node.rhs, values);
+		},
+		AstNode::Unary(node) => substitute(&mut node.var, values),
+		AstNode::Call(node) => {
+			for param in node.params.iter_mut() {
+				substitute(param, values);
+			}
+		},
+		AstNode::Assignment(node) => substitute(&mut node.var, values),
+		AstNode::Label(node) => {
+			for body_node in node.body.iter_mut() {
+				substitute(body_node, values);
+			}
+		},
+		AstNode::If(node) => substitute(&mut node.condition, values),
+		AstNode::Switch(node) => substitute(&mut node.scrutinee, values),
+		AstNode::Ret(node) => if let Some(var) = node.var.as_mut() {
+			substitute(var, values);
+		},
+		AstNode::Load(node) => substitute(&mut node.ptr, values),
+		AstNode::Store(node) => {
+			substitute(&mut node.ptr, values);
+			substitute(&mut node.var, values);
+		},
+		_ => {},
+	}
+}
+
+/// Reports the values of a function, and its arguments, named after a constant.
+fn check_assignments(nodes: &[AstNode], consts: &HashMap<String, Loc>, diagnostics: &mut Diagnostics) {
+	for node in nodes.iter() {
+		let assigned: Option<(&String, &Loc, &str)> = match node {
+			AstNode::Iden(node) => Some((&node.name, &node.loc, "argument declared here")),
+			AstNode::Assignment(node) => Some((&node.name, &node.loc, "assigned here")),
+			AstNode::Call(node) => node.id.as_ref().map(|id| (id, &node.loc, "assigned here")),
+			AstNode::Label(node) => {
+				check_assignments(&node.body, consts, diagnostics);
+				None
+			},
+			_ => None,
+		};
+		if let Some((name, loc, label)) = assigned {
+			if let Some(const_loc) = consts.get(name) {
+				diagnostics.push(loc.error(format!("cannot assign to constant '{}'", name))
+								 .with_code("E0213")
+								 .with_label(String::from(label))
+								 .with_secondary_label(const_loc.clone(), String::from("constant defined here"))
+								 .with_help(String::from("use a variable of another name")));
+			}
+		}
+	}
+}
+
+impl AstPass for SubstituteConstPass {
+	fn apply(&self, nodes: &mut Vec<AstNode>, diagnostics: &mut Diagnostics) {
+		let mut globals: HashMap<String, Loc> = HashMap::new();
+		for node in nodes.iter() {
+			if let AstNode::Global(node) = node {
+				globals.entry(node.name.clone()).or_insert(node.loc.clone());
+			}
+		}
+		let mut defs: HashMap<String, &ConstAstNode> = HashMap::new();
+		let mut names: Vec<String> = vec![];
+		for node in nodes.iter() {
+			if let AstNode::Const(node) = node {
+				if let Some(first_loc) = defs.get(&node.name).map(|def| &def.loc).or(globals.get(&node.name)) {
+					diagnostics.push(node.loc.error(format!("constant already defined '{}'", node.name))
+									 .with_code("E0212")
+									 .with_label(String::from("constant redefined here"))
+									 .with_secondary_label(first_loc.clone(), format!("'{}' first defined here", node.name)));
+					continue;
+				}
+				defs.insert(node.name.clone(), node);
+				names.push(node.name.clone());
+			}
+		}
+		let mut context: Context = Context{defs, values: HashMap::new(), stack: vec![]};
+		for name in names.iter() {
+			let _ = context.evaluate(name, diagnostics);
+		}
+		let consts: HashMap<String, Loc> = context.defs.iter().map(|(name, def)| (name.clone(), def.loc.clone())).collect();
+		let values: HashMap<String, AstNode> = context.values.into_iter()
+			.filter_map(|(name, value)| value.map(|value| (name, value))).collect();
+		for node in nodes.iter_mut() {
+			match node {
+				AstNode::Const(node) => if let Some(value) = values.get(&node.name) {
+					*node.var = with_loc(value.clone(), node.var.loc());
+				},
+				AstNode::Global(node) => substitute(&mut node.init, &values),
+				AstNode::Function(node) => {
+					check_assignments(&node.args, &consts, diagnostics);
+					check_assignments(&node.body, &consts, diagnostics);
+					for body_node in node.body.iter_mut() {
+						substitute(body_node, &values);
+					}
+				},
+				_ => {},
+			}
+		}
+	}
+	fn name(&self) -> String {String::from("substitute_const_pass")}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::fe::{lexer::Lexer, parser::Parser};
+
+	/// Substitutes the constants of `source`, given one statement per line, and returns the
+	/// lines of the resulting program without their indentation and the codes of the
+	/// diagnostics reported.
+	fn substitute_consts(source: &[&str]) -> (Vec<String>, Vec<String>) {
+		let mut diagnostics: Diagnostics = Diagnostics::new();
+		let lexer: Lexer = Lexer::from_source(&source.join("\n"), String::from("test.irl"), &mut diagnostics);
+		let mut nodes: Vec<AstNode> = Parser::new(lexer.tokens, &mut diagnostics).nodes;
+		assert!(diagnostics.diagnostics.is_empty(), "unexpected diagnostics parsing {:?}", source);
+		SubstituteConstPass{}.apply(&mut nodes, &mut diagnostics);
+		let lines: Vec<String> = nodes.iter()
+			.flat_map(|node| node.to_string().lines().map(|line| line.trim().to_string()).collect::<Vec<String>>())
+			.collect();
+		(lines, diagnostics.diagnostics.iter().map(|diagnostic| diagnostic.code.clone().unwrap_or_default()).collect())
+	}
+
+	fn codes(source: &[&str]) -> Vec<String> {
+		substitute_consts(source).1
+	}
+
+	#[test]
+	fn constants_are_substituted() {
+		let (lines, codes) = substitute_consts(&["const N = 2 * 3", "const M = N + 1", "const F = 1.5 * 2.0",
+												 "global g = M", "function main, 0", "x = M * N", "y: f64 = F",
+												 "ret N"]);
+		assert!(codes.is_empty());
+		for line in ["const M = 7", "x = 7 * 6", "ret 6"] {
+			assert!(lines.iter().any(|printed| printed == line), "no '{}' in {:?}", line, lines);
+		}
+		assert!(lines.iter().any(|line| line.starts_with("global g") && line.ends_with("= 7")), "{:?}", lines);
+		assert!(lines.iter().any(|line| line.starts_with("y") && line.ends_with("= 3.0")), "{:?}", lines);
+	}
+
+	#[test]
+	fn constants_defined_twice() {
+		assert_eq!(codes(&["const N = 1", "const N = 2", "function main, 0", "ret N"]), vec!["E0212"]);
+		assert_eq!(codes(&["global N = 1", "const N = 2", "function main, 0", "ret 0"]), vec!["E0212"]);
+	}
+
+	#[test]
+	fn constants_assigned() {
+		assert_eq!(codes(&["const N = 1", "function main, 0", "N = 2", "ret 0"]), vec!["E0213"]);
+		assert_eq!(codes(&["const N = 1", "function f, 1", "arg N", "ret 0"]), vec!["E0213"]);
+		assert_eq!(codes(&["const N = 1", "function main, 0", "label L", "N = call main, 0", "ret 0"]),
+				   vec!["E0213"]);
+	}
+
+	#[test]
+	fn constants_defined_in_terms_of_themselves() {
+		assert_eq!(codes(&["const A = A + 1", "function main, 0", "ret 0"]), vec!["E0214"]);
+		assert_eq!(codes(&["const A = B + 1", "const B = 2 * A", "function main, 0", "ret 0"]), vec!["E0214"]);
+	}
+
+	#[test]
+	fn constants_that_cannot_be_evaluated() {
+		assert_eq!(codes(&["const A = 1 / 0", "function main, 0", "ret 0"]), vec!["E0215"]);
+		assert_eq!(codes(&["const A = 1 + 2.0", "function main, 0", "ret 0"]), vec!["E0215"]);
+		assert_eq!(codes(&["const A = x + 1", "function main, 0", "ret 0"]), vec!["E0215"]);
+	}
+}
@@ -0,0 +1,399 @@
+use crate::{fe::{ast::*, loc::Loc, types::Type, diagnostic::{Diagnostic, Diagnostics}}, mw::pass::AstPass};
+use std::collections::HashMap;
+
+/// Infers the type of every value from the annotations on `arg`, assignments,
+/// globals, calls and function returns, checks that operands agree and records the
+/// result on the AST so later stages can lower values with the right width.
+pub struct TypeCheckPass;
+
+#[derive(Clone)]
+struct Signature {
+	args: Vec<Type>,
+	ret_ty: Option<Type>,
+}
+
+struct Context<'a> {
+	signatures: &'a HashMap<String, Signature>,
+	vars: HashMap<String, Type>,
+	ret_ty: Option<Type>,
+}
+
+fn mismatch(loc: Loc, expected: Type, found: Type) -> Diagnostic {
+	loc.error(String::from("mismatched types")).with_code("E0205")
+		.with_label(format!("expected '{}', found '{}'", expected, found))
+}
+
+impl AstPass for TypeCheckPass {
+	fn apply(&self, nodes: &mut Vec<AstNode>, diagnostics: &mut Diagnostics) {
+		let signatures: HashMap<String, Signature> = nodes.iter().filter_map(|node| match node {
+			AstNode::Function(node) => Some((node.name.clone(), Signature{
+				args: node.args.iter().map(|arg| arg.ty()).collect(),
+				ret_ty: node.ret_ty,
+			})),
+			AstNode::Extern(node) => Some((node.name.clone(), Signature{
+				args: node.args.iter().map(|arg| arg.ty()).collect(),
+				ret_ty: node.ret_ty,
+			})),
+			_ => None,
+		}).collect();
+		let mut globals: HashMap<String, Type> = HashMap::new();
+		for node in nodes.iter_mut() {
+			if let AstNode::Global(node) = node {
+				let context: Context = Context{signatures: &signatures, vars: HashMap::new(), ret_ty: None};
+				let ty: Type = check_expr(&mut node.init, node.ty, &context, diagnostics);
+				let ty: Type = node.ty.unwrap_or(ty);
+				node.ty = Some(ty);
+				globals.entry(node.name.clone()).or_insert(ty);
+			}
+		}
+		for node in nodes.iter_mut() {
+			if let AstNode::Function(node) = node {
+				let mut context: Context = Context{signatures: &signatures, vars: globals.clone(), ret_ty: node.ret_ty};
+				for arg in node.args.iter() {
+					if let AstNode::Iden(iden_node) = arg {
+						context.vars.insert(iden_node.name.clone(), iden_node.ty);
+					}
+				}
+				check_body(&mut node.body, &mut context, diagnostics);
+			}
+		}
+	}
+	fn name(&self) -> String {String::from("type_check_pass")}
+}
+
+fn check_body(nodes: &mut [AstNode], context: &mut Context, diagnostics: &mut Diagnostics) {
+	for node in nodes.iter_mut() {
+		check_node(node, context, diagnostics);
+	}
+}
+
+fn check_node(node: &mut AstNode, context: &mut Context, diagnostics: &mut Diagnostics) {
+	match node {
+		AstNode::Assignment(node) => {
+			let prev_ty: Option<Type> = context.vars.get(&node.name).copied();
+			if let (Some(ty), Some(prev_ty)) = (node.ty, prev_ty) {
+				if ty != prev_ty {
+					diagnostics.push(mismatch(node.loc.clone(), prev_ty, ty)
+									 .with_note(format!("'{}' was previously assigned a value of type '{}'",
+														node.name, prev_ty)));
+				}
+			}
+			let ty: Type = check_expr(&mut node.var, node.ty.or(prev_ty), context, diagnostics);
+			let ty: Type = node.ty.or(prev_ty).unwrap_or(ty);
+			node.ty = Some(ty);
+			context.vars.insert(node.name.clone(), ty);
+		},
+		AstNode::Call(node) => {
+			// nothing is known about the function behind a pointer, its signature is the
+			// one the call implies, i.e. a bare call returns nothing
+			let signature: Option<Signature> = match &mut node.callee {
+				Callee::Direct(name) => context.signatures.get(name)
+					.filter(|signature| signature.args.len() == node.params.len()).cloned(),
+				Callee::Indirect(ptr) => {
+					check_expr(ptr, Some(Type::Ptr), context, diagnostics);
+					None
+				},
+			};
+			for (ix, param) in node.params.iter_mut().enumerate() {
+				check_expr(param, signature.as_ref().map(|signature| signature.args[ix]), context, diagnostics);
+			}
+			let id: String = match &node.id {
+				Some(id) => id.clone(),
+				None => {
+					// the result of a bare call is dropped, which takes knowing its type
+					node.ty = signature.and_then(|signature| signature.ret_ty);
+					return;
+				},
+			};
+			let ty: Type = match (signature.map(|signature| signature.ret_ty), node.ty) {
+				(Some(None), ty) => {
+					diagnostics.push(node.loc.error(format!("'{}' does not return a value", node.callee)).with_code("E0205")
+									 .with_label(String::from("its result is bound here"))
+									 .with_help(format!("call it without binding a result, i.e. 'call {}, {}'",
+														node.callee, node.params.len())));
+					ty.unwrap_or(Type::I32)
+				},
+				(Some(Some(ret_ty)), Some(ty)) if ret_ty != ty => {
+					diagnostics.push(mismatch(node.loc.clone(), ty, ret_ty)
+									 .with_note(format!("'{}' returns '{}'", node.callee, ret_ty)));
+					ty
+				},
+				(Some(Some(ret_ty)), _) => ret_ty,
+				(None, Some(ty)) => ty,
+				(None, None) => context.vars.get(&id).copied().unwrap_or(Type::I32),
+			};
+			node.ty = Some(ty);
+			context.vars.insert(id, ty);
+		},
+		AstNode::Label(node) => check_body(&mut node.body, context, diagnostics),
+		AstNode::If(node) => {
+			let ty: Type = check_expr(&mut node.condition, None, context, diagnostics);
+			if ty.is_float() {
+				diagnostics.push(node.condition.loc().error(String::from("mismatched types")).with_code("E0205")
+								 .with_label(format!("expected an integer condition, found '{}'", ty))
+								 .with_help(String::from("compare it with '0.0' instead, e.g. 'if (x != 0.0)'")));
+			}
+		},
+		AstNode::Switch(node) => {
+			let ty: Type = check_expr(&mut node.scrutinee, None, context, diagnostics);
+			if ty.is_float() || ty == Type::Ptr {
+				diagnostics.push(node.scrutinee.loc().error(String::from("mismatched types")).with_code("E0205")
+								 .with_label(format!("expected an integer scrutinee, found '{}'", ty)));
+			}
+			let mut seen: HashMap<i64, Loc> = HashMap::new();
+			for case in node.cases.iter_mut() {
+				case.value.ty = ty;
+				if !ty.contains(case.value.num) {
+					diagnostics.push(case.value.loc.error(format!("literal out of range for {}", ty)).with_code("E0102")
+									 .with_label(format!("this literal does not fit in {} whose range is '{}..={}'",
+														 ty, ty.min(), ty.max())));
+				}
+				if let Some(first_loc) = seen.get(&case.value.num) {
+					diagnostics.push(case.value.loc.error(format!("duplicate switch case '{}'", case.value.num))
+									 .with_code("E0210")
+									 .with_label(String::from("this value is already handled"))
+									 .with_secondary_label(first_loc.clone(), String::from("first handled here")));
+				} else {
+					seen.insert(case.value.num, case.value.loc.clone());
+				}
+			}
+		},
+		AstNode::Ret(node) => match (context.ret_ty, node.var.as_mut()) {
+			(Some(ret_ty), Some(var)) => {check_expr(var, Some(ret_ty), context, diagnostics);},
+			(Some(ret_ty), None) => diagnostics.push(node.loc.error(String::from("missing return value")).with_code("E0205")
+													 .with_label(format!("expected a value of type '{}'", ret_ty))),
+			(None, Some(var)) => {
+				check_expr(var, None, context, diagnostics);
+				diagnostics.push(var.loc().error(String::from("cannot return a value from a function returning nothing"))
+								 .with_code("E0205")
+								 .with_label(String::from("the function is declared '-> void'"))
+								 .with_help(String::from("use a plain 'ret'")));
+			},
+			(None, None) => {},
+		},
+		AstNode::Store(node) => {
+			check_expr(&mut node.ptr, Some(Type::Ptr), context, diagnostics);
+			check_expr(&mut node.var, None, context, diagnostics);
+		},
+		_ => {},
+	}
+}
+
+/// Type of the operand if it is an identifier or a string, or of a nested expression
+/// when one of its operands settles it; other literals, comparisons and '!' take the
+/// type of their context.
+fn operand_ty(node: &AstNode, context: &Context) -> Option<Type> {
+	match node {
+		AstNode::Iden(node) => context.vars.get(&node.name).copied(),
+		AstNode::Str(_) => Some(Type::Ptr),
+		AstNode::Arith(node) => operand_ty(&node.lhs, context).or(operand_ty(&node.rhs, context)),
+		AstNode::Unary(node) if node.op != UnaryOp::Not => operand_ty(&node.var, context),
+		_ => None,
+	}
+}
+
+/// Type of a literal operand, or of a nested expression of them, when nothing else settles it.
+fn literal_ty(node: &AstNode) -> Option<Type> {
+	match node {
+		AstNode::Float(_) => Some(Type::F64),
+		AstNode::Arith(node) => literal_ty(&node.lhs).or(literal_ty(&node.rhs)),
+		AstNode::Unary(node) if node.op != UnaryOp::Not => literal_ty(&node.var),
+		_ => None,
+	}
+}
+
+fn check_expr(node: &mut AstNode, expected: Option<Type>, context: &Context, diagnostics: &mut Diagnostics) -> Type {
+	match node {
+		AstNode::Iden(node) => {
+			node.ty = match context.vars.get(&node.name) {
+				Some(ty) => *ty,
+				// unknown identifiers are reported by validate_iden_pass
+				None => return expected.unwrap_or(Type::I32),
+			};
+			if let Some(expected) = expected {
+				if node.ty != expected {
+					diagnostics.push(mismatch(node.loc.clone(), expected, node.ty)
+									 .with_note(format!("'{}' has type '{}'", node.name, node.ty)));
+				}
+			}
+			node.ty
+		},
+		AstNode::Num(node) => {
+			if let Some(expected) = expected.filter(|ty| ty.is_float() || *ty == Type::Ptr) {
+				let diagnostic: Diagnostic = node.loc.error(String::from("mismatched types")).with_code("E0205")
+					.with_label(format!("expected '{}', found integer literal", expected));
+				diagnostics.push(match expected.is_float() {
+					true => diagnostic.with_help(format!("use a float literal instead, e.g. '{}.0'", node.num)),
+					false => diagnostic,
+				});
+				return expected;
+			}
+			node.ty = expected.unwrap_or(Type::I32);
+			if !node.ty.contains(node.num) {
+				diagnostics.push(node.loc.error(format!("literal out of range for {}", node.ty)).with_code("E0102")
+								 .with_label(format!("this literal does not fit in {} whose range is '{}..={}'",
+													 node.ty, node.ty.min(), node.ty.max())));
+			}
+			node.ty
+		},
+		AstNode::Float(node) => {
+			if let Some(expected) = expected.filter(|ty| !ty.is_float()) {
+				diagnostics.push(node.loc.error(String::from("mismatched types")).with_code("E0205")
+								 .with_label(format!("expected '{}', found float literal", expected)));
+				return expected;
+			}
+			node.ty = expected.unwrap_or(Type::F64);
+			if node.ty.round(node.num).is_infinite() {
+				diagnostics.push(node.loc.error(format!("literal out of range for {}", node.ty)).with_code("E0102")
+								 .with_label(format!("this literal does not fit in {} whose largest value is '{:e}'",
+													 node.ty, f32::MAX)));
+			}
+			node.ty
+		},
+		AstNode::Str(node) => {
+			if let Some(expected) = expected.filter(|ty| *ty != Type::Ptr) {
+				diagnostics.push(node.loc.error(String::from("mismatched types")).with_code("E0205")
+								 .with_label(format!("expected '{}', found string literal", expected)));
+			}
+			Type::Ptr
+		},
+		AstNode::Arith(node) if operand_ty(&node.lhs, context) == Some(Type::Ptr) => {
+			check_expr(&mut node.lhs, Some(Type::Ptr), context, diagnostics);
+			check_expr(&mut node.rhs, Some(Type::I32), context, diagnostics);
+			if !matches!(node.op, ArithOp::Sum | ArithOp::Sub) {
+				diagnostics.push(node.loc.error(format!("cannot apply '{}' to a pointer", node.op)).with_code("E0205")
+								 .with_help(String::from("pointers can only be offset with '+' and '-'")));
+			}
+			if let Some(expected) = expected.filter(|ty| *ty != Type::Ptr) {
+				diagnostics.push(mismatch(node.loc.clone(), expected, Type::Ptr));
+			}
+			Type::Ptr
+		},
+		AstNode::Arith(node) => {
+			let ty: Type = operand_ty(&node.lhs, context).or(operand_ty(&node.rhs, context))
+				.or(expected).or(literal_ty(&node.lhs)).or(literal_ty(&node.rhs)).unwrap_or(Type::I32);
+			check_expr(&mut node.lhs, Some(ty), context, diagnostics);
+			check_expr(&mut node.rhs, Some(ty), context, diagnostics);
+			if ty.is_float() && !node.op.is_float_op() {
+				diagnostics.push(node.loc.error(format!("cannot apply '{}' to a float", node.op)).with_code("E0205")
+								 .with_help(String::from("'%', bitwise and shift operators only apply to integers")));
+			}
+			if let Some(expected) = expected {
+				if ty != expected {
+					diagnostics.push(mismatch(node.loc.clone(), expected, ty));
+				}
+			}
+			ty
+		},
+		AstNode::Relop(node) => {
+			let ty: Type = operand_ty(&node.lhs, context).or(operand_ty(&node.rhs, context))
+				.or(literal_ty(&node.lhs)).or(literal_ty(&node.rhs)).unwrap_or(Type::I32);
+			check_expr(&mut node.lhs, Some(ty), context, diagnostics);
+			check_expr(&mut node.rhs, Some(ty), context, diagnostics);
+			// the outcome of a comparison is stored as 0 or 1 in whatever width is asked for
+			match expected {
+				Some(expected) if expected.is_float() => {
+					diagnostics.push(mismatch(node.loc.clone(), expected, Type::I1));
+					expected
+				},
+				_ => expected.unwrap_or(Type::I1),
+			}
+		},
+		AstNode::Unary(node) if node.op == UnaryOp::Not => {
+			let ty: Type = operand_ty(&node.var, context).or(literal_ty(&node.var)).unwrap_or(Type::I32);
+			check_expr(&mut node.var, Some(ty), context, diagnostics);
+			if ty.is_float() {
+				diagnostics.push(node.loc.error(String::from("cannot apply '!' to a float")).with_code("E0205")
+								 .with_help(String::from("compare it with '0.0' instead, e.g. 'x == 0.0'")));
+			}
+			// like a comparison the outcome is 0 or 1 in whatever width is asked for
+			match expected {
+				Some(expected) if expected.is_float() => {
+					diagnostics.push(mismatch(node.loc.clone(), expected, Type::I1));
+					expected
+				},
+				_ => expected.unwrap_or(Type::I1),
+			}
+		},
+		AstNode::Unary(node) => {
+			let ty: Type = check_expr(&mut node.var, expected, context, diagnostics);
+			if ty.is_float() && node.op == UnaryOp::BitNot {
+				diagnostics.push(node.loc.error(String::from("cannot apply '~' to a float")).with_code("E0205")
+								 .with_help(String::from("'%', bitwise and shift operators only apply to integers")));
+			}
+			ty
+		},
+		AstNode::Alloca(_) | AstNode::FuncAddr(_) => {
+			if let Some(expected) = expected.filter(|ty| *ty != Type::Ptr) {
+				diagnostics.push(mismatch(node.loc(), expected, Type::Ptr));
+			}
+			Type::Ptr
+		},
+		AstNode::Load(node) => {
+			check_expr(&mut node.ptr, Some(Type::Ptr), context, diagnostics);
+			node.ty = expected.unwrap_or(Type::I32);
+			node.ty
+		},
+		_ => expected.unwrap_or(Type::I32),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::fe::{lexer::Lexer, parser::Parser};
+
+	/// Type checks `source`, given one statement per line, and returns the codes of the
+	/// diagnostics reported.
+	fn check(source: &[&str]) -> Vec<String> {
+		let mut diagnostics: Diagnostics = Diagnostics::new();
+		let lexer: Lexer = Lexer::from_source(&source.join("\n"), String::from("test.irl"), &mut diagnostics);
+		let mut nodes: Vec<AstNode> = Parser::new(lexer.tokens, &mut diagnostics).nodes;
+		assert!(diagnostics.diagnostics.is_empty(), "unexpected diagnostics parsing {:?}", source);
+		TypeCheckPass{}.apply(&mut nodes, &mut diagnostics);
+		diagnostics.diagnostics.iter().map(|diagnostic| diagnostic.code.clone().unwrap_or_default()).collect()
+	}
+
+	#[test]
+	fn agreeing_types() {
+		assert!(check(&["function f, 1 -> i64", "arg x: i8", "y: i64 = 1", "c = x < 2", "ret y",
+						"function main, 0", "a: i8 = 3", "param a", "b: i64 = call f, 1", "z: i8 = -128", "ret 0"])
+				.is_empty());
+	}
+
+	#[test]
+	fn mismatched_operands() {
+		assert_eq!(check(&["function main, 0", "a: i8 = 1", "b: i16 = a + 1", "ret 0"]), vec!["E0205"]);
+		assert_eq!(check(&["function main, 0", "a: i64 = 1", "c = a < 2", "ret c"]), vec!["E0205"]);
+		assert_eq!(check(&["function main, 0", "f: f64 = 2", "ret 0"]), vec!["E0205"]);
+	}
+
+	#[test]
+	fn mismatched_call_arguments_and_results() {
+		let callee: [&str; 3] = ["function f, 1 -> i64", "arg x: i8", "ret 0"];
+		assert_eq!(check(&[&callee[..], &["function main, 0", "a: i32 = 1", "param a", "b: i64 = call f, 1",
+										  "ret 0"]].concat()), vec!["E0205"]);
+		assert_eq!(check(&[&callee[..], &["function main, 0", "param 1", "b: i32 = call f, 1", "ret 0"]].concat()),
+				   vec!["E0205"]);
+	}
+
+	#[test]
+	fn literals_out_of_range() {
+		assert_eq!(check(&["function main, 0", "a: i8 = 128", "b: i8 = -129", "c: i8 = 127", "ret 0"]),
+				   vec!["E0102", "E0102"]);
+		assert_eq!(check(&["function main, 0", "a: i64 = 1", "b: i64 = a + 9223372036854775807", "ret 0"]),
+				   Vec::<String>::new());
+		assert_eq!(check(&["function main, 0", "a: f32 = 1e39", "b: f64 = 1e39", "ret 0"]), vec!["E0102"]);
+	}
+
+	#[test]
+	fn switch_cases() {
+		let switch = |cases: &str| check(&["function main, 0", "x: i8 = 1",
+										   &format!("switch x [{}] default A", cases), "label A", "ret 0"]);
+		assert!(switch("-128: A, 127: A").is_empty());
+		assert_eq!(switch("1: A, 2: A, 1: A"), vec!["E0210"]);
+		assert_eq!(switch("128: A, -129: A"), vec!["E0102", "E0102"]);
+		assert_eq!(check(&["function main, 0", "f: f64 = 1.0", "switch f [1: A] default A", "label A", "ret 0"]),
+				   vec!["E0205"]);
+	}
+}
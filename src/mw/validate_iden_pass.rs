@@ -1,129 +1,222 @@
-use crate::{fe::{ast::*, loc::Loc}, mw::pass::AstPass};
-use std::collections::HashSet;
+use crate::{fe::{ast::*, loc::Loc, diagnostic::Diagnostics}, mw::pass::AstPass};
+use std::collections::{HashMap, HashSet};
 
 pub struct ValidateIdenPass;
 
 #[derive(Clone)]
 struct Context {
 	idens: HashSet<String>,
-	labels: HashSet<String>,
+	labels: HashMap<String, Loc>,
+	/// argument count of every defined or declared function
+	functions: HashMap<String, usize>,
 }
 
 impl Context {
 	fn new() -> Self {
-		Self{idens: HashSet::new(), labels: HashSet::new()}
+		Self{idens: HashSet::new(), labels: HashMap::new(), functions: HashMap::new()}
 	}
-	fn validate_iden(&self, node: &IdenAstNode) {
+	/// Calls through a function pointer can only be checked to name a value, their
+	/// argument count is only known at run time.
+	fn validate_call(&self, node: &CallAstNode, diagnostics: &mut Diagnostics) {
+		let name: &String = match &node.callee {
+			Callee::Direct(name) => name,
+			Callee::Indirect(ptr) => {
+				if let AstNode::Iden(ref iden_node) = **ptr {
+					self.validate_iden(iden_node, diagnostics);
+				}
+				return;
+			},
+		};
+		match self.functions.get(name) {
+			None => diagnostics.push(node.loc.error(format!("unknown function '{}'", name))
+									 .with_code("E0207")
+									 .with_label(String::from("no function or extern of this name"))
+									 .with_help(format!("declare it with 'extern {}, {}'", name, node.params.len()))),
+			Some(arg_count) if *arg_count != node.params.len() => {
+				diagnostics.push(node.loc.error(format!("expected argument count of '{}' to be '{}'; but found '{}'",
+														name, arg_count, node.params.len()))
+								 .with_code("E0208")
+								 .with_label(format!("'{}' is declared with {} arguments", name, arg_count)));
+			},
+			Some(_) => {},
+		}
+	}
+	fn validate_func_addr(&self, node: &FuncAddrAstNode, diagnostics: &mut Diagnostics) {
+		if !self.functions.contains_key(&node.name) {
+			diagnostics.push(node.loc.error(format!("unknown function '{}'", node.name))
+							 .with_code("E0207")
+							 .with_label(String::from("no function or extern of this name")));
+		}
+	}
+	fn validate_iden(&self, node: &IdenAstNode, diagnostics: &mut Diagnostics) {
 		if !self.idens.contains(&node.name) {
-			node.loc.message(String::from("validate_iden_pass: failed"));
-			node.loc.error(format!("unknown identifier '{}'", node.name));
+			diagnostics.push(node.loc.error(format!("unknown identifier '{}'", node.name))
+							 .with_code("E0202")
+							 .with_label(String::from("not defined before this use")));
 		}
 	}
 	fn insert_iden(&mut self, iden: String) {
 		let _ = self.idens.insert(iden);
 	}
-	fn validate_label(&self, label: &String, loc: Loc) {
-		if !self.labels.contains(label) {
-			loc.message(String::from("validate_iden_pass: failed"));
-			loc.error(format!("unknown label identifier '{}'", label));
+	fn validate_label(&self, label: &String, loc: Loc, diagnostics: &mut Diagnostics) {
+		if !self.labels.contains_key(label) {
+			diagnostics.push(loc.error(format!("unknown label identifier '{}'", label))
+							 .with_code("E0203")
+							 .with_label(String::from("no such label in this function")));
 		}
 	}
-	fn insert_label(&mut self, label: &String, loc: Loc) {
-		if self.labels.contains(label) {
-			loc.message(String::from("validate_iden_pass: failed"));
-			loc.error(format!("label identifier already exists '{}'", label));
+	fn insert_label(&mut self, label: &String, loc: Loc, diagnostics: &mut Diagnostics) {
+		if let Some(first_loc) = self.labels.get(label) {
+			diagnostics.push(loc.error(format!("label identifier already exists '{}'", label))
+							 .with_code("E0204")
+							 .with_label(String::from("label redefined here"))
+							 .with_secondary_label(first_loc.clone(), String::from("label first defined here")));
+			return;
 		}
-		let _ = self.labels.insert(label.clone());
+		let _ = self.labels.insert(label.clone(), loc);
 	}
 }
 
 impl AstPass for ValidateIdenPass {
-	fn apply(&self, nodes: &mut Vec<AstNode>) {
+	fn apply(&self, nodes: &mut Vec<AstNode>, diagnostics: &mut Diagnostics) {
+		// constants are substituted by substitute_const_pass, their names are known like globals
+		let globals: Vec<String> = nodes.iter().filter_map(|node| match node {
+			AstNode::Global(node) => Some(node.name.clone()),
+			AstNode::Const(node) => Some(node.name.clone()),
+			_ => None,
+		}).collect();
+		let functions: HashMap<String, usize> = nodes.iter().filter_map(|node| match node {
+			AstNode::Function(node) => Some((node.name.clone(), node.args.len())),
+			AstNode::Extern(node) => Some((node.name.clone(), node.args.len())),
+			_ => None,
+		}).collect();
 		for node in nodes.iter() {
 			let mut context: Context = Context::new();
-			validate_node(node, &mut context);
-			validate_label(node, &context);
+			context.functions = functions.clone();
+			for global in globals.iter() {
+				context.insert_iden(global.clone());
+			}
+			validate_node(node, &mut context, diagnostics);
+			validate_label(node, &context, diagnostics);
 		}
 	}
 	fn name(&self) -> String {String::from("validate_iden_pass")}
 }
 
-fn validate_node(node: &AstNode, context: &mut Context) {
+fn validate_node(node: &AstNode, context: &mut Context, diagnostics: &mut Diagnostics) {
 	match node {
 		AstNode::Call(node) => {
+			context.validate_call(node, diagnostics);
 			for param in node.params.iter() {
 				if let AstNode::Iden(iden_node) = param {
-					context.validate_iden(iden_node);
+					context.validate_iden(iden_node, diagnostics);
 				}
 			}
-			context.insert_iden(node.id.clone());
+			if let Some(id) = &node.id {
+				context.insert_iden(id.clone());
+			}
 		},
 		AstNode::Arith(node) => {
-			if let AstNode::Iden(ref iden_lhs) = *node.lhs {
-				context.validate_iden(iden_lhs);
-			}
-			if let AstNode::Iden(ref iden_rhs) = *node.rhs {
-				context.validate_iden(iden_rhs);
-			}
+			validate_operand(&node.lhs, context, diagnostics);
+			validate_operand(&node.rhs, context, diagnostics);
 		},
 		AstNode::Relop(node) => {
-			if let AstNode::Iden(ref iden_lhs) = *node.lhs {
-				context.validate_iden(iden_lhs);
-			}
-			if let AstNode::Iden(ref iden_rhs) = *node.rhs {
-				context.validate_iden(iden_rhs);
-			}
-		},
-		AstNode::Unary(node) => {
-			if let AstNode::Iden(ref iden_var) = *node.var {
-				context.validate_iden(iden_var);
-			}
+			validate_operand(&node.lhs, context, diagnostics);
+			validate_operand(&node.rhs, context, diagnostics);
 		},
+		AstNode::Unary(node) => validate_operand(&node.var, context, diagnostics),
+		AstNode::FuncAddr(node) => context.validate_func_addr(node, diagnostics),
 		AstNode::Function(node) => {
 			for arg in node.args.iter() {
 				if let AstNode::Iden(iden_node) = arg {
 					context.insert_iden(iden_node.name.clone());
 				}
 			}
+			// values defined under a label are only visible to it, except to the labels placed
+			// by lower_control_flow_pass which continue the scope of the label before them
+			let mut scope: Option<Context> = None;
 			for body_node in node.body.iter() {
-				validate_node(body_node, context);
+				match body_node {
+					AstNode::Label(label_node) => {
+						context.insert_label(&label_node.name, label_node.loc.clone(), diagnostics);
+						let mut inner_context: Context = match scope.take() {
+							Some(scope) if label_node.lowered => scope,
+							_ => context.clone(),
+						};
+						for label_body_node in label_node.body.iter() {
+							validate_node(label_body_node, &mut inner_context, diagnostics);
+						}
+						scope = Some(inner_context);
+					},
+					body_node => validate_node(body_node, context, diagnostics),
+				}
 			}
 		},
 		AstNode::Assignment(node) => {
-			validate_node(&*node.var, context);
+			validate_operand(&node.var, context, diagnostics);
 			context.insert_iden(node.name.clone());
 		},
-		AstNode::Label(node) => {
-			context.insert_label(&node.name, node.loc.clone());
-			let mut inner_context: Context = context.clone(); 
-			for body_node in node.body.iter() {
-				validate_node(body_node, &mut inner_context);
-			} 
+		AstNode::If(node) => validate_operand(&node.condition, context, diagnostics),
+		AstNode::Switch(node) => {
+			if let AstNode::Iden(ref iden_node) = *node.scrutinee {
+				context.validate_iden(iden_node, diagnostics);
+			}
 		},
-		AstNode::If(node) => validate_node(&*node.condition, context),
-		AstNode::Ret(node) => {
+		AstNode::Load(node) => {
+			if let AstNode::Iden(ref iden_ptr) = *node.ptr {
+				context.validate_iden(iden_ptr, diagnostics);
+			}
+		},
+		AstNode::Store(node) => {
+			if let AstNode::Iden(ref iden_ptr) = *node.ptr {
+				context.validate_iden(iden_ptr, diagnostics);
+			}
 			if let AstNode::Iden(ref iden_var) = *node.var {
-				context.validate_iden(iden_var);
+				context.validate_iden(iden_var, diagnostics);
+			}
+		},
+		AstNode::Ret(node) => {
+			if let Some(AstNode::Iden(iden_var)) = node.var.as_deref() {
+				context.validate_iden(iden_var, diagnostics);
 			}
 		},
 		_ => {},
 	}
 }
 
-fn validate_label(node: &AstNode, context: &Context) {
+/// Operands of an expression are either identifiers or, when nested, expressions themselves.
+fn validate_operand(node: &AstNode, context: &mut Context, diagnostics: &mut Diagnostics) {
+	match node {
+		AstNode::Iden(iden_node) => context.validate_iden(iden_node, diagnostics),
+		node => validate_node(node, context, diagnostics),
+	}
+}
+
+fn validate_label(node: &AstNode, context: &Context, diagnostics: &mut Diagnostics) {
 	match node {
 		AstNode::Function(node) => {
 			for body_node in node.body.iter() {
-				validate_label(body_node, context);
+				validate_label(body_node, context, diagnostics);
 			}
 		},
 		AstNode::Label(node) => {
 			for body_node in node.body.iter() {
-				validate_label(body_node, context);
+				validate_label(body_node, context, diagnostics);
 			} 
 		},
-		AstNode::If(node) => context.validate_label(&node.label, node.loc.clone()),
-		AstNode::Goto(node) => context.validate_label(&node.name, node.loc.clone()),
+		AstNode::If(node) => {
+			context.validate_label(&node.label, node.loc.clone(), diagnostics);
+			if let Some(otherwise) = &node.otherwise {
+				context.validate_label(otherwise, node.loc.clone(), diagnostics);
+			}
+		},
+		AstNode::Switch(node) => {
+			for case in node.cases.iter() {
+				context.validate_label(&case.label, node.loc.clone(), diagnostics);
+			}
+			context.validate_label(&node.default, node.loc.clone(), diagnostics);
+		},
+		AstNode::Goto(node) => context.validate_label(&node.name, node.loc.clone(), diagnostics),
 		_ => {},
 	}
 }
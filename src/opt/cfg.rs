@@ -10,9 +10,17 @@ struct ConditionalJump {
 	otherwise: Weak<RefCell<BasicBlock>>,
 }
 
+struct SwitchJump {
+	scrutinee: AstNode,
+	cases: Vec<(NumAstNode, Weak<RefCell<BasicBlock>>)>,
+	default: Weak<RefCell<BasicBlock>>,
+	loc: Loc,
+}
+
 enum Jump {
 	Unconditional(Weak<RefCell<BasicBlock>>),
 	Conditional(ConditionalJump),
+	Switch(SwitchJump),
 }
 
 pub struct BasicBlock {
@@ -37,6 +45,8 @@ impl Hash for BasicBlock {
     }
 }
 
+/// Block compared and hashed by its id, which is only changed by `reindex_basic_blocks`;
+/// sets and maps keyed by it must not outlive a reindexing, despite the `RefCell` inside.
 #[derive(Clone)]
 pub struct BasicBlockRef(pub Rc<RefCell<BasicBlock>>);
 
@@ -55,14 +65,14 @@ impl Hash for BasicBlockRef {
 }
 
 impl BasicBlockRef {
-	pub fn borrow(&self) -> Ref<BasicBlock> {
+	pub fn borrow(&self) -> Ref<'_, BasicBlock> {
         self.0.borrow()
     }
 }
 
 impl BasicBlock {
 	fn new(id: usize) -> Self {
-		Self{id: id, label: None, insts: Vec::new(), prevs: Vec::new(), next: None}
+		Self{id, label: None, insts: Vec::new(), prevs: Vec::new(), next: None}
 	}
 	fn add_inst(&mut self, node: AstNode) {
 		self.insts.push(Rc::new(RefCell::new(node)));
@@ -76,12 +86,25 @@ impl BasicBlock {
 	fn set_conditional_jump(&mut self, jump: ConditionalJump) {
 		self.next = Some(Jump::Conditional(jump));
 	}
+	fn set_switch_jump(&mut self, jump: SwitchJump) {
+		self.next = Some(Jump::Switch(jump));
+	}
 	pub fn successors(&self) -> Vec<Weak<RefCell<BasicBlock>>> {
 		match &self.next {
 			Some(jump) => match jump {
 				Jump::Conditional(conditional_jump) => vec![conditional_jump.goto.clone(),
 															conditional_jump.otherwise.clone()],
-				Jump::Unconditional(unconditional_jump) => vec![unconditional_jump.clone()]
+				Jump::Unconditional(unconditional_jump) => vec![unconditional_jump.clone()],
+				// cases often share a target, which is a single edge
+				Jump::Switch(switch_jump) => {
+					let mut succs: Vec<Weak<RefCell<BasicBlock>>> = Vec::new();
+					for target in switch_jump.cases.iter().map(|(_, target)| target).chain([&switch_jump.default]) {
+						if !succs.iter().any(|succ| succ.ptr_eq(target)) {
+							succs.push(target.clone());
+						}
+					}
+					succs
+				},
 			},
 			None => vec![]
 		}
@@ -116,7 +139,7 @@ impl BasicBlock {
 			lines.push(format!("        {}_BB{} -> {}_BB{};", function_name.clone(),
 							   self.id, function_name.clone(), succ.upgrade().unwrap().borrow().id));
 		}
-		if self.successors().len() == 0 {
+		if self.successors().is_empty() {
 			lines.push(format!("        {}_BB{} -> {}_EXIT;", function_name.clone(),
 							   self.id, function_name.clone()));
 		}
@@ -127,23 +150,30 @@ impl BasicBlock {
 		for inst_ref in self.insts.iter() {
 			body.push(inst_ref.borrow().clone());
 		}
-		match &self.next {
-			Some(jump) => match jump {
+		if let Some(jump) = &self.next {
+			match jump {
 				Jump::Unconditional(basic_block_ref) => {
 					let name: String = basic_block_ref.upgrade().unwrap().borrow().label();
-					body.push(AstNode::Goto(GotoAstNode{name: name, loc: Loc::null()}));
+					body.push(AstNode::Goto(GotoAstNode{name, loc: Loc::null()}));
 				},
 				Jump::Conditional(conditional_jump) => {
 					let goto_name: String = conditional_jump.goto.upgrade().unwrap().borrow().label();
 					let otherwise_name: String = conditional_jump.otherwise.upgrade().unwrap().borrow().label();
 					body.push(AstNode::If(IfAstNode{condition: Box::new(conditional_jump.condition.clone()),
-													label: goto_name, loc: Loc::null()}));
-					body.push(AstNode::Goto(GotoAstNode{name: otherwise_name, loc: Loc::null()}));
+													label: goto_name, otherwise: Some(otherwise_name),
+													negated: false, loc: Loc::null()}));
 				},
-			},
-			None => {},
+				Jump::Switch(switch_jump) => {
+					let cases: Vec<SwitchCase> = switch_jump.cases.iter().map(|(value, target)| SwitchCase{
+						value: value.clone(), label: target.upgrade().unwrap().borrow().label(),
+					}).collect();
+					let default: String = switch_jump.default.upgrade().unwrap().borrow().label();
+					body.push(AstNode::Switch(SwitchAstNode{scrutinee: Box::new(switch_jump.scrutinee.clone()),
+															cases, default, loc: switch_jump.loc.clone()}));
+				},
+			}
 		}
-		LabelAstNode{name: self.label(), body: body, loc: Loc::null()}
+		LabelAstNode{name: self.label(), body, lowered: false, loc: Loc::null()}
 	}
 }
 
@@ -160,12 +190,15 @@ pub struct ControlFlowGraph {
 	pub function: FunctionAstNode,
 	pub entry: usize,
 	pub basic_blocks: Vec<Rc<RefCell<BasicBlock>>>,
+	/// Names of the program's globals, which any call may modify.
+	pub globals: HashSet<String>,
 	label_table: HashMap<String, usize>,
 }
 
 impl ControlFlowGraph {
 	fn new(function: FunctionAstNode, entry: Rc<RefCell<BasicBlock>>) -> Self {
-		Self{function: function, entry: 0, basic_blocks: vec![entry], label_table: HashMap::new()}
+		Self{function, entry: 0, basic_blocks: vec![entry],
+			 globals: HashSet::new(), label_table: HashMap::new()}
 	}
 	fn add_basic_block(&mut self, basic_block: Rc<RefCell<BasicBlock>>) {
 		self.basic_blocks.push(basic_block);
@@ -177,6 +210,16 @@ impl ControlFlowGraph {
 	fn get_basic_block(&self, label: String) -> Option<Rc<RefCell<BasicBlock>>> {
 		self.label_table.get(&label).and_then(|&ix| self.basic_blocks.get(ix).cloned())
 	}
+	/// Basic block starting at `label`, added the first time the label is seen.
+	fn get_or_add_basic_block(&mut self, label: String) -> Rc<RefCell<BasicBlock>> {
+		if let Some(bb) = self.get_basic_block(label.clone()) {
+			return bb;
+		}
+		let new_bb: Rc<RefCell<BasicBlock>> = Rc::new(RefCell::new(BasicBlock::new(self.get_new_id())));
+		new_bb.borrow_mut().label = Some(label.clone());
+		self.add_label_with_basic_block(label, Rc::clone(&new_bb));
+		new_bb
+	}
 	fn get_new_id(&self) -> usize {
 		self.basic_blocks.len()
 	}
@@ -185,9 +228,10 @@ impl ControlFlowGraph {
 			basic_block.borrow_mut().id = ix;
 		}
 	}
+	#[allow(clippy::mutable_key_type)]
 	pub fn get_uses(&self, inst: Rc<RefCell<AstNode>>) -> Vec<Rc<RefCell<AstNode>>> {
 		let production: Option<String> = inst.borrow().production();
-		if let None = production {
+		if production.is_none() {
 			return vec![];
 		}
 		let mut res: Vec<Rc<RefCell<AstNode>>> = Vec::new();
@@ -199,7 +243,7 @@ impl ControlFlowGraph {
 		let mut lines: Vec<String> = Vec::new();
 		lines.push(format!("    subgraph cluster_{} {{", self.function.name));
 		lines.push(format!("        label=\"{}\";", self.function.name));
-		lines.push(format!("        graph [style=filled];"));
+		lines.push(String::from("        graph [style=filled];"));
 		lines.push(format!("        {}_ENTRY [label=\"ENTRY\"];", self.function.name));
 		lines.push(format!("        {}_EXIT [label=\"EXIT\"];", self.function.name));
 		lines.push(format!("        {}_ENTRY -> {}_BB{};", self.function.name, self.function.name,
@@ -224,10 +268,35 @@ impl ControlFlowGraph {
 				}));
 			}			
 		}
-		return AstNode::Function(function_node);
+		AstNode::Function(function_node)
+	}
+}
+
+/// Replaces the switch ending `basic_block` with a jump to the target its scrutinee selects
+/// when `state` makes it a constant, and drops the block from the predecessors of the rest.
+pub fn fold_switch(basic_block: &Rc<RefCell<BasicBlock>>, state: &HashMap<String, Value>) {
+	let (target, succs): (Weak<RefCell<BasicBlock>>, Vec<Weak<RefCell<BasicBlock>>>) = {
+		let basic_block_ref: Ref<'_, BasicBlock> = basic_block.borrow();
+		let switch_jump: &SwitchJump = match &basic_block_ref.next {
+			Some(Jump::Switch(switch_jump)) => switch_jump,
+			_ => return,
+		};
+		let scrutinee: i64 = match switch_jump.scrutinee.reduced_version(state).evaluate() {
+			Value::Constant(c) => c,
+			_ => return,
+		};
+		let target: &Weak<RefCell<BasicBlock>> = switch_jump.cases.iter()
+			.find(|(value, _)| value.num == scrutinee).map_or(&switch_jump.default, |(_, target)| target);
+		(target.clone(), basic_block_ref.successors())
+	};
+	for succ in succs.iter().filter(|succ| !succ.ptr_eq(&target)) {
+		let succ: Rc<RefCell<BasicBlock>> = succ.upgrade().unwrap();
+		succ.borrow_mut().prevs.retain(|prev| !prev.ptr_eq(&Rc::downgrade(basic_block)));
 	}
+	basic_block.borrow_mut().set_unconditional_jump(target);
 }
 
+#[allow(clippy::mutable_key_type)]
 fn get_uses_(basic_block: BasicBlockRef, production: String,
 			 vis: &mut HashSet<BasicBlockRef>, res: &mut Vec<Rc<RefCell<AstNode>>>) {
 	vis.insert(basic_block.clone());
@@ -243,7 +312,7 @@ fn get_uses_(basic_block: BasicBlockRef, production: String,
 	}
 }
 
-pub fn dump_cfg_table_to_svg(cfg_table: &Vec<ControlFlowGraph>, filepath: String) {
+pub fn dump_cfg_table_to_svg(cfg_table: &[ControlFlowGraph], filepath: String) {
 	let mut file = File::create(filepath.clone()).expect("could not create a file");
 	let _  = file.write_all(format!("digraph \"{}\" {{\n", filepath.clone()).as_bytes());
 	for cfg in cfg_table.iter() {
@@ -258,30 +327,30 @@ pub fn dump_cfg_table_to_svg(cfg_table: &Vec<ControlFlowGraph>, filepath: String
 
 impl std::fmt::Display for ControlFlowGraph {
 	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-		let _ = write!(f, "=====================================\n");
+		let _ = writeln!(f, "=====================================");
 		for (ix, basic_block) in self.basic_blocks.iter().enumerate() {
 			if ix == self.entry {
 				let _ = write!(f, "[Entry] ");
 			}
 			let _ = write!(f, "{}", basic_block.borrow().properties());
 			let _ = write!(f, "{}", basic_block.borrow());
-			if basic_block.borrow().prevs.len() > 0 {
+			if !basic_block.borrow().prevs.is_empty() {
 				let _ = write!(f, "[Predecessors=");
 				for pred in basic_block.borrow().prevs.iter() {
 					let _ = write!(f, "{},", pred.upgrade().unwrap().borrow().name());
 				}
 				let _ = write!(f, "] ");
 			}
-			if basic_block.borrow().successors().len() > 0 {
+			if !basic_block.borrow().successors().is_empty() {
 				let _ = write!(f, "[Successors=");
 				for succ in basic_block.borrow().successors().iter() {
 					let _ = write!(f, "{},", succ.upgrade().unwrap().borrow().name());
 				}
-				let _ = write!(f, "]\n");
+				let _ = writeln!(f, "]");
 			} else {
-				let _ = write!(f, "\n");
+				let _ = writeln!(f);
 			}
-			let _ = write!(f, "=====================================\n");
+			let _ = writeln!(f, "=====================================");
 		}
 		write!(f, "")
 	}
@@ -292,23 +361,13 @@ enum Context {
 	InsideFunction,
 }
 
-fn process_body(body: &Vec<AstNode>, mut cur_bb: Rc<RefCell<BasicBlock>>,
+fn process_body(body: &[AstNode], mut cur_bb: Rc<RefCell<BasicBlock>>,
 				cfg: &mut ControlFlowGraph, context: Context) {
 	let mut skip_inst: bool = false; 
 	for node in body.iter() {
 		match node {
 			AstNode::Label(label) => {
-				let new_bb: Rc<RefCell<BasicBlock>> = if let Some(bb) = cfg.get_basic_block(label.name.clone()) {
-					bb
-				} else {
-					let new_bb_t: Rc<RefCell<BasicBlock>> = Rc::new(
-						RefCell::new(BasicBlock::new(cfg.get_new_id())));
-					new_bb_t.borrow_mut().label = Some(label.name.clone());
-					cfg.add_label_with_basic_block(label.name.clone(), Rc::clone(&new_bb_t));
-					// cur_bb.borrow_mut().set_unconditional_jump(Rc::downgrade(&new_bb_t));
-					// new_bb_t.borrow_mut().add_prev(Rc::downgrade(&cur_bb));
-					new_bb_t
-				};
+				let new_bb: Rc<RefCell<BasicBlock>> = cfg.get_or_add_basic_block(label.name.clone());
 				process_body(label.body.clone().as_ref(), new_bb, cfg, Context::InsideLabel);
 				skip_inst = false;
 			},
@@ -316,15 +375,7 @@ fn process_body(body: &Vec<AstNode>, mut cur_bb: Rc<RefCell<BasicBlock>>,
 				if skip_inst {
 					continue;
 				}
-				let new_bb: Rc<RefCell<BasicBlock>> = if let Some(bb) = cfg.get_basic_block(goto.name.clone()) {
-					bb
-				} else {
-					let new_bb_t: Rc<RefCell<BasicBlock>> = Rc::new(
-						RefCell::new(BasicBlock::new(cfg.get_new_id())));
-					new_bb_t.borrow_mut().label = Some(goto.name.clone());
-					cfg.add_label_with_basic_block(goto.name.clone(), Rc::clone(&new_bb_t));
-					new_bb_t
-				};
+				let new_bb: Rc<RefCell<BasicBlock>> = cfg.get_or_add_basic_block(goto.name.clone());
 				new_bb.borrow_mut().add_prev(Rc::downgrade(&cur_bb));
 				cur_bb.borrow_mut().set_unconditional_jump(Rc::downgrade(&new_bb));
 				if let Context::InsideLabel = context {
@@ -348,24 +399,62 @@ fn process_body(body: &Vec<AstNode>, mut cur_bb: Rc<RefCell<BasicBlock>>,
 				if skip_inst {
 					continue;
 				}
-				let then_bb: Rc<RefCell<BasicBlock>> =
-					if let Some(bb) = cfg.get_basic_block(if_inst.label.clone()) {bb} else {
-						let new_bb_t: Rc<RefCell<BasicBlock>> = Rc::new(
-							RefCell::new(BasicBlock::new(cfg.get_new_id())));
-						new_bb_t.borrow_mut().label = Some(if_inst.label.clone());
-						cfg.add_label_with_basic_block(if_inst.label.clone(), Rc::clone(&new_bb_t));
-						new_bb_t
-					};
-				let else_bb: Rc<RefCell<BasicBlock>> = Rc::new(RefCell::new(BasicBlock::new(cfg.get_new_id())));
-				cfg.add_basic_block(Rc::clone(&else_bb));
+				let then_bb: Rc<RefCell<BasicBlock>> = cfg.get_or_add_basic_block(if_inst.label.clone());
+				// without an 'else' the branch falls through to a new block
+				let else_bb: Rc<RefCell<BasicBlock>> = match &if_inst.otherwise {
+					Some(otherwise) => cfg.get_or_add_basic_block(otherwise.clone()),
+					None => {
+						let new_bb: Rc<RefCell<BasicBlock>> = Rc::new(RefCell::new(BasicBlock::new(cfg.get_new_id())));
+						cfg.add_basic_block(Rc::clone(&new_bb));
+						new_bb
+					},
+				};
+				// 'ifnot' is the same jump with its targets swapped
+				let (goto, otherwise) = if if_inst.negated {(&else_bb, &then_bb)} else {(&then_bb, &else_bb)};
 				cur_bb.borrow_mut().set_conditional_jump(ConditionalJump{
 					condition: *if_inst.condition.clone(),
-					goto: Rc::downgrade(&then_bb),
-					otherwise: Rc::downgrade(&else_bb),
+					goto: Rc::downgrade(goto),
+					otherwise: Rc::downgrade(otherwise),
 				});
 				then_bb.borrow_mut().add_prev(Rc::downgrade(&cur_bb));
 				else_bb.borrow_mut().add_prev(Rc::downgrade(&cur_bb));
-				cur_bb = else_bb;
+				if if_inst.otherwise.is_none() {
+					cur_bb = else_bb;
+				} else if let Context::InsideLabel = context {
+					return;
+				} else {
+					skip_inst = true;
+				}
+			},
+			AstNode::Switch(switch_inst) => {
+				if skip_inst {
+					continue;
+				}
+				let cases: Vec<(NumAstNode, Weak<RefCell<BasicBlock>>)> = switch_inst.cases.iter().map(|case| {
+					(case.value.clone(), Rc::downgrade(&cfg.get_or_add_basic_block(case.label.clone())))
+				}).collect();
+				let default_bb: Rc<RefCell<BasicBlock>> = cfg.get_or_add_basic_block(switch_inst.default.clone());
+				cur_bb.borrow_mut().set_switch_jump(SwitchJump{
+					scrutinee: *switch_inst.scrutinee.clone(),
+					cases,
+					default: Rc::downgrade(&default_bb),
+					loc: switch_inst.loc.clone(),
+				});
+				let succs: Vec<Weak<RefCell<BasicBlock>>> = cur_bb.borrow().successors();
+				for succ in succs.iter() {
+					succ.upgrade().unwrap().borrow_mut().add_prev(Rc::downgrade(&cur_bb));
+				}
+				if let Context::InsideLabel = context {
+					return;
+				} else {
+					skip_inst = true;
+				}
+			},
+			AstNode::Comment(comment_node) => if !skip_inst {
+				let mut comment_node: CommentAstNode = comment_node.clone();
+				// a comment trailing a jump has nothing left to trail in the block that follows it
+				comment_node.trailing &= !cur_bb.borrow().insts.is_empty() || cur_bb.borrow().label.is_some();
+				cur_bb.borrow_mut().add_inst(AstNode::Comment(comment_node));
 			},
 			_ => if !skip_inst {
 				cur_bb.borrow_mut().add_inst(node.clone());
@@ -385,11 +474,17 @@ fn cfg_from_function(node: &FunctionAstNode) -> ControlFlowGraph {
 	cfg
 }
 
-pub fn cfg_table_from_program(nodes: &Vec<AstNode>) -> Vec<ControlFlowGraph> {
+pub fn cfg_table_from_program(nodes: &[AstNode]) -> Vec<ControlFlowGraph> {
 	let mut cfg_table: Vec<ControlFlowGraph> = Vec::new();
+	let globals: HashSet<String> = nodes.iter().filter_map(|node| match node {
+		AstNode::Global(global_node) => Some(global_node.name.clone()),
+		_ => None,
+	}).collect();
 	for node in nodes.iter() {
 		if let AstNode::Function(function_node) = node {
-			cfg_table.push(cfg_from_function(function_node));
+			let mut cfg: ControlFlowGraph = cfg_from_function(function_node);
+			cfg.globals = globals.clone();
+			cfg_table.push(cfg);
 		}
 	}
 	cfg_table
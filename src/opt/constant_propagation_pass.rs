@@ -1,56 +1,89 @@
 use std::collections::{HashMap, HashSet};
 use std::{rc::Weak, cell::RefCell};
 use crate::opt::{cfg::*, pass::CompilerPass};
-use crate::fe::ast::*;
+use crate::fe::{ast::*, diagnostic::Diagnostics};
  
 pub struct ConstantPropagationPass;
 
 impl CompilerPass for ConstantPropagationPass {
-	fn run_on_function(&self, cfg: &mut ControlFlowGraph) {
+	#[allow(clippy::mutable_key_type)]
+	fn run_on_function(&self, cfg: &mut ControlFlowGraph, diagnostics: &mut Diagnostics) {
 		let mut vis: HashSet<BasicBlockRef> = HashSet::new();
 		let mut context: HashMap<BasicBlockRef, HashMap<String, Value>> = HashMap::new();
 		loop {
 			let mut new_context: HashMap<BasicBlockRef, HashMap<String, Value>> = context.clone();
 			vis.clear();
-			update_evaluations(BasicBlockRef(cfg.basic_blocks[cfg.entry].clone()), &mut vis, &mut new_context);
+			update_evaluations(BasicBlockRef(cfg.basic_blocks[cfg.entry].clone()), &cfg.globals,
+							   &mut vis, &mut new_context);
 			if context == new_context {
 				break;
 			}
 			context = new_context;
 		}
 		vis.clear();
-		update_cfg(BasicBlockRef(cfg.basic_blocks[cfg.entry].clone()), &mut vis, &context);
+		update_cfg(BasicBlockRef(cfg.basic_blocks[cfg.entry].clone()), &cfg.globals, &mut vis, &context, diagnostics);
 	}
+	fn name(&self) -> String {String::from("constant_propagation_pass")}
 }
 
-fn update_evaluations(basic_block: BasicBlockRef, vis: &mut HashSet<BasicBlockRef>,
+/// Globals are never folded as any call, including recursive ones, may write them.
+#[allow(clippy::mutable_key_type)]
+fn update_evaluations(basic_block: BasicBlockRef, globals: &HashSet<String>, vis: &mut HashSet<BasicBlockRef>,
 					  context: &mut HashMap<BasicBlockRef, HashMap<String, Value>>) {
 	vis.insert(basic_block.clone());
 	let basic_block_context = meet_operator(basic_block.borrow().prevs.clone(), &*context);
 	context.insert(basic_block.clone(), basic_block_context);	
 	for inst in basic_block.borrow().insts.iter() {
-		inst.borrow_mut().update_evaluations(context.get_mut(&basic_block.clone()).unwrap());
+		let state: &mut HashMap<String, Value> = context.get_mut(&basic_block.clone()).unwrap();
+		inst.borrow_mut().update_evaluations(state);
+		for global in globals.iter() {
+			state.insert(global.clone(), Value::Nac);
+		}
 	}
 	for succ in basic_block.borrow().successors().iter() {
 		if !vis.contains(&BasicBlockRef(succ.upgrade().unwrap())) {
-			update_evaluations(BasicBlockRef(succ.upgrade().unwrap()), vis, context);
+			update_evaluations(BasicBlockRef(succ.upgrade().unwrap()), globals, vis, context);
 		}
 	}
 }
 
-fn update_cfg(basic_block: BasicBlockRef, vis: &mut HashSet<BasicBlockRef>,
-			  context: &HashMap<BasicBlockRef, HashMap<String, Value>>) {
+/// Every instruction is reduced with the state right before it, replaying the block from
+/// the state its predecessors meet in, as later instructions may reassign its operands; a
+/// switch on a constant is then folded into a jump to the one target it can take.
+#[allow(clippy::mutable_key_type)]
+fn update_cfg(basic_block: BasicBlockRef, globals: &HashSet<String>, vis: &mut HashSet<BasicBlockRef>,
+			  context: &HashMap<BasicBlockRef, HashMap<String, Value>>, diagnostics: &mut Diagnostics) {
 	vis.insert(basic_block.clone());
+	let mut state: HashMap<String, Value> = meet_operator(basic_block.borrow().prevs.clone(), context);
 	for inst in basic_block.borrow().insts.iter() {
-		inst.borrow_mut().reduce(context.get(&basic_block.clone()).unwrap());
+		inst.borrow_mut().reduce(&state);
+		check_division_by_zero(&inst.borrow(), diagnostics);
+		inst.borrow_mut().update_evaluations(&mut state);
+		for global in globals.iter() {
+			state.insert(global.clone(), Value::Nac);
+		}
 	}
+	fold_switch(&basic_block.0, &state);
 	for succ in basic_block.borrow().successors().iter() {
 		if !vis.contains(&BasicBlockRef(succ.upgrade().unwrap())) {
-			update_cfg(BasicBlockRef(succ.upgrade().unwrap()), vis, context);
+			update_cfg(BasicBlockRef(succ.upgrade().unwrap()), globals, vis, context, diagnostics);
+		}
+	}
+}
+
+fn check_division_by_zero(node: &AstNode, diagnostics: &mut Diagnostics) {
+	if let AstNode::Assignment(assignment_node) = node {
+		if let AstNode::Arith(arith_node) = &*assignment_node.var {
+			if matches!(arith_node.op, ArithOp::Div | ArithOp::Rem) && arith_node.rhs.evaluate() == Value::Constant(0) {
+				diagnostics.push(arith_node.loc.warning(String::from("division by zero"))
+								 .with_code("W0001")
+								 .with_label(String::from("divisor is always zero here")));
+			}
 		}
 	}
 }
 
+#[allow(clippy::mutable_key_type)]
 fn meet_operator(basic_blocks: Vec<Weak<RefCell<BasicBlock>>>,
 				 context: &HashMap<BasicBlockRef, HashMap<String, Value>>
 ) -> HashMap<String, Value>{
@@ -1,12 +1,15 @@
 use crate::opt::{cfg::ControlFlowGraph, pass::*};
+use crate::fe::diagnostic::Diagnostics;
 use crate::opt::reduce_pass::ReducePass;
 use crate::opt::constant_propagation_pass::ConstantPropagationPass;
 
-pub fn run_default_compiler_pass_manager(cfg_table: &mut Vec<ControlFlowGraph>) {
+pub fn run_default_compiler_pass_manager(cfg_table: &mut [ControlFlowGraph], diagnostics: &mut Diagnostics) {
 	let mut pass_manager: CompilerPassManager = CompilerPassManager::new();
 	pass_manager.add(ReducePass{});
 	pass_manager.add(ConstantPropagationPass{});
+	// drops the blocks only reachable through a switch it folded
+	pass_manager.add(ReducePass{});
 	for cfg in cfg_table.iter_mut() {
-		pass_manager.run(cfg);
+		pass_manager.run(cfg, diagnostics);
 	}
 }
@@ -1,23 +1,28 @@
 use crate::opt::cfg::ControlFlowGraph;
+use crate::fe::diagnostic::Diagnostics;
 
 pub trait CompilerPass {
-	fn run_on_function(&self, cfg: &mut ControlFlowGraph);
+	fn run_on_function(&self, cfg: &mut ControlFlowGraph, diagnostics: &mut Diagnostics);
+	fn name(&self) -> String;
 }
 
+#[derive(Default)]
 pub struct CompilerPassManager {
 	passes: Vec<Box<dyn CompilerPass>>,
 }
 
 impl CompilerPassManager {
 	pub fn new() -> Self {
-		Self{passes: Vec::new()}
+		Self::default()
 	}
 	pub fn add<T: CompilerPass + 'static>(&mut self, pass: T) {
 		self.passes.push(Box::new(pass));
 	}
-	pub fn run(&self, cfg: &mut ControlFlowGraph) {
+	pub fn run(&self, cfg: &mut ControlFlowGraph, diagnostics: &mut Diagnostics) {
 		for pass in self.passes.iter() {
-			pass.run_on_function(cfg);
+			let diagnostics_len: usize = diagnostics.diagnostics.len();
+			pass.run_on_function(cfg, diagnostics);
+			diagnostics.set_pass_from(diagnostics_len, pass.name());
 		}
 	} 
 }
@@ -1,11 +1,14 @@
 use std::collections::HashSet;
+use std::{rc::Weak, cell::RefCell};
 use crate::opt::pass::CompilerPass;
-use crate::opt::cfg::{ControlFlowGraph, BasicBlockRef};
+use crate::opt::cfg::{ControlFlowGraph, BasicBlock, BasicBlockRef};
+use crate::fe::diagnostic::Diagnostics;
 
 pub struct ReducePass;
 
 impl CompilerPass for ReducePass {
-	fn run_on_function(&self, cfg: &mut ControlFlowGraph) {
+	#[allow(clippy::mutable_key_type)]
+	fn run_on_function(&self, cfg: &mut ControlFlowGraph, _diagnostics: &mut Diagnostics) {
 		let mut vis: HashSet<BasicBlockRef> = HashSet::new();
 		dfs(BasicBlockRef(cfg.basic_blocks[cfg.entry].clone()), &mut vis);
 		let mut unvisited_ixs: Vec<usize> = Vec::new();
@@ -18,10 +21,20 @@ impl CompilerPass for ReducePass {
 		for &ix in unvisited_ixs.iter().rev() {
 			cfg.basic_blocks.remove(ix);
 		}
+		// unreachable blocks may still jump to reachable ones, which must forget them; looking
+		// blocks up in `vis` borrows them, so the block is only borrowed mutably afterwards
+		for bb in cfg.basic_blocks.iter() {
+			let prevs: Vec<Weak<RefCell<BasicBlock>>> = bb.borrow().prevs.iter()
+				.filter(|prev| prev.upgrade().is_some_and(|prev| vis.contains(&BasicBlockRef(prev))))
+				.cloned().collect();
+			bb.borrow_mut().prevs = prevs;
+		}
 		cfg.reindex_basic_blocks();
 	}
+	fn name(&self) -> String {String::from("reduce_pass")}
 }
 
+#[allow(clippy::mutable_key_type)]
 fn dfs(bb: BasicBlockRef, vis: &mut HashSet<BasicBlockRef>) {
 	vis.insert(bb.clone());
 	for succ in bb.borrow().successors().iter() {
@@ -1,15 +1,19 @@
-use std::collections::HashMap;
-use std::io::{prelude::*, BufReader};
-use crate::fe::ast::*;
+use std::collections::{HashMap, HashSet};
+use crate::fe::{ast::*, loc::Loc, types::Type, target::Target, diagnostic::Diagnostic};
 use crate::trn::transpiler::*;
 
 pub struct FasmTranspiler;
 
+/// Functions implemented by `fasm_footer.fasm`, see `src/fe/prelude.irl`.
+const RUNTIME_FUNCTIONS: &[&str] = &["print", "print_str"];
+
 #[derive(Clone)]
 enum Operand {
 	Register(String),
 	Memory(String),
-	Immediate(i32),
+	Immediate(i64),
+	/// Address of a label in the data segments, taken with `lea`.
+	Address(String),
 }
 
 impl Operand {
@@ -18,41 +22,181 @@ impl Operand {
 			Operand::Register(reg) => reg.clone(),
 			Operand::Memory(mem) => mem.clone(),
 			Operand::Immediate(val) => format!("{}", val),
+			Operand::Address(label) => format!("[{}]", label),
 		}
 	}
+	/// Memory operand with an explicit size, e.g. `dword [rsp+8]`.
+	fn sized(&self, ty: Type) -> Operand {
+		match self {
+			Operand::Memory(mem) => Operand::Memory(format!("{} {}", size_directive(ty), mem)),
+			_ => self.clone(),
+		}
+	}
+}
+
+fn size_directive(ty: Type) -> &'static str {
+	match ty.size() {
+		1 => "byte",
+		2 => "word",
+		4 => "dword",
+		_ => "qword",
+	}
+}
+
+/// The part of the 64-bit register `reg` holding a value of type `ty`, e.g. `rdi` as `dil`.
+fn register(reg: &str, ty: Type) -> String {
+	if reg[1..].chars().all(|c| c.is_ascii_digit()) {
+		return format!("{}{}", reg, match ty.size() {1 => "b", 2 => "w", 4 => "d", _ => ""});
+	}
+	let base: &str = &reg[1..];
+	match ty.size() {
+		1 => format!("{}l", base.strip_suffix('x').unwrap_or(base)),
+		2 => base.to_string(),
+		4 => format!("e{}", base),
+		_ => reg.to_string(),
+	}
 }
 
+/// The frame of a function holds a slot per value followed by the memory
+/// reserved by its `alloca`s.
 struct Context {
 	operands: HashMap<String, Operand>,
-	value_operands_len: usize,
+	allocas: HashMap<String, usize>,
+	frame_size: usize,
 	entry_point: bool,
 	function_name: String,
+	strings: HashMap<Vec<u8>, String>,
+	/// externs the runtime does not implement, there is nothing to link them against
+	missing_externs: HashSet<String>,
 }
 
 impl Context {
 	fn new() -> Self {
-		Self{operands: HashMap::new(), value_operands_len: 0,
-			 entry_point: false, function_name: String::new()}
+		Self{operands: HashMap::new(), allocas: HashMap::new(), frame_size: 0,
+			 entry_point: false, function_name: String::new(), strings: HashMap::new(),
+			 missing_externs: HashSet::new()}
+	}
+}
+
+/// Names made of `[A-Za-z0-9_]` are kept as is; any other name is prefixed with
+/// `__irl_` and every byte outside `[A-Za-z0-9]` is escaped as `_xx`.
+fn symbol(name: &str) -> String {
+	if name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_') {
+		return name.to_string();
+	}
+	let mut symbol: String = String::from("__irl_");
+	for b in name.bytes() {
+		if b.is_ascii_alphanumeric() {
+			symbol.push(b as char);
+		} else {
+			symbol.push_str(&format!("_{:02x}", b));
+		}
 	}
+	symbol
 }
 
-const INTERIM_REG: &str = "r15d";
-const INTERIM_REG_2: &str = "r14d";
+/// Integers are held sign-extended to 64 bits in registers and truncated to
+/// their width when stored to their stack slot; floats live in SSE registers.
+const INTERIM_REG: &str = "r15";
+const INTERIM_REG_2: &str = "r14";
+const FLOAT_INTERIM_REG: &str = "xmm15";
+const FLOAT_INTERIM_REG_2: &str = "xmm14";
+/// Float constants are materialized through this register.
+const SCRATCH_REG: &str = "r11";
+const SLOT_SIZE: usize = 8;
 
 #[inline]
 fn call_convention() -> Vec<String> {
-    vec![String::from("edi"), 
-         String::from("esi"), 
-         String::from("edx"), 
-         String::from("ecx"), 
-         String::from("r8d"),  
-         String::from("r9d")]
+    vec![String::from("rdi"),
+         String::from("rsi"),
+         String::from("rdx"),
+         String::from("rcx"),
+         String::from("r8"),
+         String::from("r9")]
+}
+
+#[inline]
+fn float_call_convention() -> Vec<String> {
+	(0..8).map(|ix| format!("xmm{}", ix)).collect()
+}
+
+/// Registers carrying values of `types` per the System V calling convention,
+/// `None` if they do not all fit in registers.
+fn argument_registers(types: &[Type]) -> Option<Vec<String>> {
+	let (mut ints, mut floats) = (call_convention().into_iter(), float_call_convention().into_iter());
+	types.iter().map(|ty| if ty.is_float() {floats.next()} else {ints.next()}).collect()
+}
+
+fn interim(ty: Type) -> &'static str {
+	if ty.is_float() {FLOAT_INTERIM_REG} else {INTERIM_REG}
+}
+
+fn interim_2(ty: Type) -> &'static str {
+	if ty.is_float() {FLOAT_INTERIM_REG_2} else {INTERIM_REG_2}
+}
+
+fn return_register(ty: Type) -> &'static str {
+	if ty.is_float() {"xmm0"} else {"rax"}
+}
+
+/// Suffix of scalar SSE instructions, e.g. `addss` and `addsd`.
+fn sse_suffix(ty: Type) -> &'static str {
+	if ty == Type::F32 {"ss"} else {"sd"}
+}
+
+fn indent_lines(indent_sz: usize, lines: Vec<String>) -> Vec<String> {
+	lines.into_iter().map(|line| indent(indent_sz, line)).collect()
 }
 
 fn inst(opcode: &str, to: Operand, from: Operand) -> String {
 	format!("{} {}, {}", opcode, to.value(), from.value())
 }
 
+/// Loads an operand into the 64-bit register `reg`, extending narrower values;
+/// floats are loaded into the SSE register `reg`.
+fn load(reg: &str, node: &AstNode, context: &Context) -> Vec<String> {
+	load_operand(reg, ast_node_to_operand(node.clone(), context), node.ty())
+}
+
+fn load_operand(reg: &str, operand: Operand, ty: Type) -> Vec<String> {
+	let to: Operand = Operand::Register(String::from(reg));
+	match (&operand, ty) {
+		(Operand::Address(_), _) => vec![inst("lea", to, operand)],
+		(Operand::Memory(_), Type::I1) => vec![inst("movzx", to, operand.sized(Type::I1))],
+		(Operand::Memory(_), Type::I32) => vec![inst("movsxd", to, operand.sized(Type::I32))],
+		(Operand::Memory(_), Type::I64) | (Operand::Memory(_), Type::Ptr) => vec![inst("mov", to, operand.sized(ty))],
+		(Operand::Memory(_), Type::F32) => vec![inst("movss", to, operand.sized(Type::F32))],
+		(Operand::Memory(_), Type::F64) => vec![inst("movsd", to, operand.sized(Type::F64))],
+		(Operand::Memory(_), ty) => vec![inst("movsx", to, operand.sized(ty))],
+		(_, Type::F32) => vec![
+			inst("mov", Operand::Register(register(SCRATCH_REG, Type::F32)), operand),
+			inst("movd", to, Operand::Register(register(SCRATCH_REG, Type::F32)))],
+		(_, Type::F64) => vec![
+			inst("mov", Operand::Register(String::from(SCRATCH_REG)), operand),
+			inst("movq", to, Operand::Register(String::from(SCRATCH_REG)))],
+		_ => vec![inst("mov", to, operand)],
+	}
+}
+
+/// Stores the low `ty` bits of the 64-bit register `reg` into the slot of `name`,
+/// or the float in the SSE register `reg`.
+fn store(name: &String, ty: Type, reg: &str, context: &Context) -> Vec<String> {
+	store_operand(context.operands.get(name).unwrap().clone(), ty, reg)
+}
+
+fn store_operand(operand: Operand, ty: Type, reg: &str) -> Vec<String> {
+	let mut lines: Vec<String> = Vec::new();
+	if ty.is_float() {
+		lines.push(inst(&format!("mov{}", sse_suffix(ty)), operand.sized(ty), Operand::Register(String::from(reg))));
+		return lines;
+	}
+	if ty == Type::I1 {
+		lines.push(inst("and", Operand::Register(String::from(reg)), Operand::Immediate(1)));
+	}
+	lines.push(inst("mov", operand.sized(ty), Operand::Register(register(reg, ty))));
+	lines
+}
+
 fn ast_node_to_operand(node: AstNode, context: &Context) -> Operand {
 	match node {
 		AstNode::Iden(iden_node) => match context.operands.get(&iden_node.name) {
@@ -60,6 +204,12 @@ fn ast_node_to_operand(node: AstNode, context: &Context) -> Operand {
 			None => unreachable!()
 		},
 		AstNode::Num(num_node) => Operand::Immediate(num_node.num),
+		AstNode::Str(str_node) => Operand::Address(context.strings.get(&str_node.bytes).unwrap().clone()),
+		// the bit pattern of the float, see `load`
+		AstNode::Float(float_node) => Operand::Immediate(match float_node.ty {
+			Type::F32 => (float_node.num as f32).to_bits() as i64,
+			_ => float_node.num.to_bits() as i64,
+		}),
 		_ => {
 			println!("debug: {}", node);
 			unreachable!()
@@ -68,13 +218,17 @@ fn ast_node_to_operand(node: AstNode, context: &Context) -> Operand {
 }
 
 impl Transpiler for FasmTranspiler {
-	fn transpile(&self, nodes: &Vec<AstNode>) -> Vec<String> {
+	fn transpile(&self, module: &ModuleAstNode) -> Result<Vec<String>, Diagnostic> {
+		let nodes: &Vec<AstNode> = &module.nodes;
 		let mut lines: Vec<String> = Vec::new();
 		lines.append(&mut header());
-		lines.append(&mut top_level_transpilation(nodes, 0));
+		lines.append(&mut top_level_transpilation(nodes, 0)?);
 		lines.append(&mut footer());
-		lines
+		lines.append(&mut global_data(nodes));
+		lines.append(&mut string_data(nodes));
+		Ok(lines)
 	}
+	fn target(&self) -> Target {Target::X86_64Linux}
 }
 
 fn header() -> Vec<String> {
@@ -84,82 +238,355 @@ fn header() -> Vec<String> {
 		 String::from("segment executable")]
 }
 
+/// Runtime defining the functions the prelude declares, embedded in the binary like it.
 fn footer() -> Vec<String> {
-	let footer_path = format!("{}/src/trn/fasm_footer.fasm", env!("CARGO_MANIFEST_DIR"));
-	BufReader ::new(std::fs::File::open(&footer_path).expect("could not open file"))
-		.lines().map(|line| line.expect("could not parse file")).collect()
+	include_str!("fasm_footer.fasm").lines().map(String::from).collect()
+}
+
+fn global_label(name: &str) -> String {
+	format!("global_{}", symbol(name))
+}
+
+/// Globals are placed after the footer, i.e. in its `segment readable writeable`.
+fn global_data(nodes: &[AstNode]) -> Vec<String> {
+	nodes.iter().filter_map(|node| match node {
+		AstNode::Global(global_node) => {
+			let ty: Type = node.ty();
+			let directive: &str = match ty.size() {
+				1 => "db",
+				2 => "dw",
+				4 => "dd",
+				_ => "dq",
+			};
+			// floats are written as their bit pattern, see `load`
+			let init: String = match &*global_node.init {
+				AstNode::Float(float_node) if ty == Type::F32 => (float_node.num as f32).to_bits().to_string(),
+				AstNode::Float(float_node) => float_node.num.to_bits().to_string(),
+				AstNode::Num(num_node) => ty.wrap(num_node.num).to_string(),
+				_ => unreachable!(),
+			};
+			Some(format!("{} {} {}", global_label(&global_node.name), directive, init))
+		},
+		_ => None,
+	}).collect()
+}
+
+fn string_label(ix: usize) -> String {
+	format!("str_{}", ix)
+}
+
+/// String literals are NUL terminated bytes in a segment of their own that is only readable.
+fn string_data(nodes: &[AstNode]) -> Vec<String> {
+	let strings: Vec<Vec<u8>> = string_literals(nodes);
+	if strings.is_empty() {
+		return vec![];
+	}
+	let mut lines: Vec<String> = vec![String::from("segment readable")];
+	for (ix, bytes) in strings.iter().enumerate() {
+		let bytes: Vec<String> = bytes.iter().chain([0u8].iter()).map(|b| b.to_string()).collect();
+		lines.push(format!("{} db {}", string_label(ix), bytes.join(", ")));
+	}
+	lines
 }
 
-fn top_level_transpilation(nodes: &Vec<AstNode>, indent_sz: usize) -> Vec<String> {
+fn top_level_transpilation(nodes: &[AstNode], indent_sz: usize) -> Result<Vec<String>, Diagnostic> {
 	let mut lines: Vec<String> = Vec::new();
+	let globals: HashMap<String, Operand> = nodes.iter().filter_map(|node| match node {
+		AstNode::Global(global_node) => Some((global_node.name.clone(),
+											  Operand::Memory(format!("[{}]", global_label(&global_node.name))))),
+		_ => None,
+	}).collect();
+	let strings: HashMap<Vec<u8>, String> = string_literals(nodes).into_iter().enumerate()
+		.map(|(ix, bytes)| (bytes, string_label(ix))).collect();
+	let defined: HashSet<String> = nodes.iter().filter_map(|node| match node {
+		AstNode::Function(function_node) => Some(function_node.name.clone()),
+		_ => None,
+	}).collect();
+	let missing_externs: HashSet<String> = nodes.iter().filter_map(|node| match node {
+		AstNode::Extern(extern_node) if !defined.contains(&extern_node.name)
+			&& !RUNTIME_FUNCTIONS.contains(&extern_node.name.as_str()) => Some(extern_node.name.clone()),
+		_ => None,
+	}).collect();
 	for node in nodes.iter() {
 		if let AstNode::Function(function_node) = node {
 			let mut context: Context = Context::new();
-			let value_operands = node.value_operands();
-			context.value_operands_len = value_operands.len();
+			context.strings = strings.clone();
+			context.missing_externs = missing_externs.clone();
+			let mut value_operands: Vec<String> = node.value_operands().into_iter()
+				.filter(|name| !globals.contains_key(name)).collect();
+			value_operands.sort();
+			let (allocas, allocas_size) = alloca_regions(function_node);
+			context.allocas = allocas.into_iter()
+				.map(|(name, offset)| (name, value_operands.len()*SLOT_SIZE + offset)).collect();
+			context.frame_size = value_operands.len()*SLOT_SIZE + allocas_size;
 			context.entry_point = function_node.name == "main";
-			context.function_name = function_node.name.clone();
-			lines.push(indent(indent_sz, format!("{}:", function_node.name)));
+			context.function_name = symbol(&function_node.name);
+			lines.push(indent(indent_sz, format!("{}:", symbol(&function_node.name))));
 			lines.push(indent(indent_sz+1, inst(
 				"sub",
 				Operand::Register(String::from("rsp")),
-				Operand::Immediate(context.value_operands_len as i32 * 4 as i32))));
-			for (ix, value_operand) in node.value_operands().iter().enumerate() {
-				context.operands.insert(value_operand.to_string(), Operand::Memory(format!("[rsp+{}]", ix*4)));
-			}
-			let call_convention: Vec<String> = call_convention();
-			if function_node.args.len() > call_convention.len() {
-				function_node.loc.error(format!("fasm target currently only supports '{}' arguments at max",
-												call_convention.len()));
+				Operand::Immediate(context.frame_size as i64))));
+			for (ix, value_operand) in value_operands.iter().enumerate() {
+				context.operands.insert(value_operand.to_string(), Operand::Memory(format!("[rsp+{}]", ix*SLOT_SIZE)));
 			}
+			context.operands.extend(globals.clone());
+			let registers: Vec<String> = match argument_registers(&function_node.args.iter().map(|arg| arg.ty()).collect::<Vec<Type>>()) {
+				Some(registers) => registers,
+				None => return Err(function_node.loc.error(format!(
+					"fasm target currently only supports '{}' integer and '{}' float arguments at max",
+					call_convention().len(), float_call_convention().len())).with_code("E0300")),
+			};
 			for (ix, arg) in function_node.args.iter().enumerate() {
-				lines.push(indent(indent_sz+1, inst(
-					"mov",
-					ast_node_to_operand(arg.clone(), &context),
-					Operand::Register(call_convention[ix].clone()))));
+				if let AstNode::Iden(iden_node) = arg {
+					lines.append(&mut indent_lines(indent_sz+1, store(&iden_node.name, iden_node.ty, &registers[ix], &context)));
+				}
 			}
-			lines.append(&mut transpile_nodes(&function_node.body, indent_sz+1, &context));
+			lines.append(&mut transpile_nodes(&function_node.body, indent_sz+1, &context)?);
+		} else if let AstNode::Comment(comment_node) = node {
+			lines.append(&mut transpile_comment(comment_node, indent_sz));
+		} else if let AstNode::Global(_) | AstNode::Const(_) | AstNode::Extern(_) = node {
+			continue;
 		} else {
 			panic!("only function nodes are allowed in top level scope");
 		}
 	}	
+	Ok(lines)
+}
+
+fn transpile_comment(comment_node: &CommentAstNode, indent_sz: usize) -> Vec<String> {
+	comment_node.lines().iter().map(|line| indent(indent_sz, format!("; {}", line))).collect()
+}
+
+fn transpile_nodes(nodes: &[AstNode], indent_sz: usize, context: &Context) -> Result<Vec<String>, Diagnostic> {
+	let mut lines: Vec<String> = Vec::new();
+	for node in nodes.iter() {lines.append(&mut transpile_node(node, indent_sz, context)?)}
+	Ok(lines)
+}
+
+/// Sets the flags from the condition of a branch and returns the jump taken when it holds;
+/// integer comparisons are tested directly, anything else is compared against zero.
+fn branch_condition(condition: &AstNode, indent_sz: usize,
+					context: &Context) -> Result<(Vec<String>, &'static str), Diagnostic> {
+	let mut lines: Vec<String> = Vec::new();
+	if let AstNode::Relop(relop_node) = condition {
+		if !relop_node.lhs.ty().is_float() {
+			lines.append(&mut indent_lines(indent_sz, load(INTERIM_REG, &relop_node.lhs, context)));
+			lines.append(&mut indent_lines(indent_sz, load(INTERIM_REG_2, &relop_node.rhs, context)));
+			lines.push(indent(indent_sz, inst(
+				"cmp",
+				Operand::Register(String::from(INTERIM_REG)),
+				Operand::Register(String::from(INTERIM_REG_2)))));
+			return Ok((lines, match relop_node.op {
+				RelOp::Eq => "je",
+				RelOp::Neq => "jne",
+				RelOp::Gt => "jg",
+				RelOp::Lt => "jl",
+				RelOp::Ge => "jge",
+				RelOp::Le => "jle",
+			}));
+		}
+	}
+	lines.append(&mut transpile_node(condition, indent_sz, context)?);
+	lines.push(indent(indent_sz, inst(
+		"cmp",
+		Operand::Register(String::from(INTERIM_REG)),
+		Operand::Immediate(0))));
+	Ok((lines, "jne"))
+}
+
+/// Jump taken when the condition of `jump` does not hold.
+fn inverse_jump(jump: &str) -> &'static str {
+	match jump {
+		"je" => "jne",
+		"jne" => "je",
+		"jg" => "jle",
+		"jle" => "jg",
+		"jl" => "jge",
+		"jge" => "jl",
+		_ => unreachable!(),
+	}
+}
+
+/// Externs the runtime does not implement can neither be called nor have their address taken.
+fn check_available(name: &String, loc: &Loc, context: &Context) -> Result<(), Diagnostic> {
+	if context.missing_externs.contains(name) {
+		return Err(loc.error(format!("extern '{}' is not available on the fasm target", name))
+				   .with_code("E0301")
+				   .with_note(format!("the fasm target builds a static executable whose runtime only provides '{}'",
+									  RUNTIME_FUNCTIONS.join("', '"))));
+	}
+	Ok(())
+}
+
+fn label(name: &str, context: &Context) -> String {
+	format!("{}_label_{}", context.function_name, symbol(name))
+}
+
+/// Binary search over the sorted `cases` for the scrutinee held in the interim register;
+/// `base` is the index of the first of them among all the cases of the switch.
+fn switch_search(cases: &[(i64, &String)], base: usize, default: &String, table: &String,
+				 indent_sz: usize, context: &Context) -> Vec<String> {
+	let mut lines: Vec<String> = Vec::new();
+	let compare = |value: i64, lines: &mut Vec<String>| {
+		lines.push(indent(indent_sz, inst("mov", Operand::Register(String::from(INTERIM_REG_2)), Operand::Immediate(value))));
+		lines.push(indent(indent_sz, inst(
+			"cmp",
+			Operand::Register(String::from(INTERIM_REG)),
+			Operand::Register(String::from(INTERIM_REG_2)))));
+	};
+	if cases.len() <= 3 {
+		for (value, target) in cases.iter() {
+			compare(*value, &mut lines);
+			lines.push(indent(indent_sz, format!("je {}", label(target, context))));
+		}
+		lines.push(indent(indent_sz, format!("jmp {}", label(default, context))));
+		return lines;
+	}
+	let mid: usize = cases.len() / 2;
+	compare(cases[mid].0, &mut lines);
+	lines.push(indent(indent_sz, format!("je {}", label(cases[mid].1, context))));
+	lines.push(indent(indent_sz, format!("jg {}_{}", table, base+mid)));
+	lines.append(&mut switch_search(&cases[..mid], base, default, table, indent_sz, context));
+	lines.push(indent(indent_sz, format!("{}_{}:", table, base+mid)));
+	lines.append(&mut switch_search(&cases[mid+1..], base+mid+1, default, table, indent_sz, context));
 	lines
 }
 
-fn transpile_nodes(nodes: &Vec<AstNode>, indent_sz: usize, context: &Context) -> Vec<String> {
+/// A dense switch jumps through a table of label addresses laid out right after the
+/// jump, indexed by the scrutinee minus the smallest case; a sparse one is a binary search.
+fn transpile_switch(switch_node: &SwitchAstNode, indent_sz: usize, context: &Context) -> Vec<String> {
 	let mut lines: Vec<String> = Vec::new();
-	for node in nodes.iter() {lines.append(&mut transpile_node(node, indent_sz, context))}
+	let table: String = format!("{}_switch_{}_{}", context.function_name, switch_node.loc.row, switch_node.loc.col);
+	lines.append(&mut indent_lines(indent_sz, load(INTERIM_REG, &switch_node.scrutinee, context)));
+	let mut cases: Vec<(i64, &String)> = switch_node.cases.iter().map(|case| (case.value.num, &case.label)).collect();
+	cases.sort();
+	if !is_dense_switch(&switch_node.cases) {
+		lines.append(&mut switch_search(&cases, 0, &switch_node.default, &table, indent_sz, context));
+		return lines;
+	}
+	let (min, max): (i64, i64) = (cases[0].0, cases[cases.len()-1].0);
+	let entries: Vec<String> = (min..=max).map(|value| {
+		label(cases.iter().find(|(case, _)| *case == value).map_or(&switch_node.default, |(_, target)| *target), context)
+	}).collect();
+	lines.push(indent(indent_sz, inst("mov", Operand::Register(String::from(INTERIM_REG_2)), Operand::Immediate(min))));
+	lines.push(indent(indent_sz, inst(
+		"sub",
+		Operand::Register(String::from(INTERIM_REG)),
+		Operand::Register(String::from(INTERIM_REG_2)))));
+	// values below the smallest case wrap around and fail the unsigned check as well
+	lines.push(indent(indent_sz, inst("mov", Operand::Register(String::from(INTERIM_REG_2)),
+									  Operand::Immediate(entries.len() as i64))));
+	lines.push(indent(indent_sz, inst(
+		"cmp",
+		Operand::Register(String::from(INTERIM_REG)),
+		Operand::Register(String::from(INTERIM_REG_2)))));
+	lines.push(indent(indent_sz, format!("jae {}", label(&switch_node.default, context))));
+	lines.push(indent(indent_sz, inst("lea", Operand::Register(String::from(INTERIM_REG_2)), Operand::Address(table.clone()))));
+	lines.push(indent(indent_sz, format!("jmp qword [{}+{}*8]", INTERIM_REG_2, INTERIM_REG)));
+	lines.push(indent(indent_sz, format!("{} dq {}", table, entries.join(", "))));
 	lines
 }
 
-fn transpile_node(node: &AstNode, indent_sz: usize, context: &Context) -> Vec<String> {
+fn transpile_node(node: &AstNode, indent_sz: usize, context: &Context) -> Result<Vec<String>, Diagnostic> {
 	let mut lines: Vec<String> = Vec::new();
 	match node {
-		AstNode::Iden(_) => {
-			lines.push(indent(indent_sz, inst(
-				"mov",
-				Operand::Register(String::from(INTERIM_REG)),
-				ast_node_to_operand(node.clone(), context))));
-		},
-		AstNode::Num(num_node) => {
-			lines.push(indent(indent_sz, inst(
-				"mov",
-				Operand::Register(String::from(INTERIM_REG)),
-				Operand::Immediate(num_node.num))));
+		AstNode::Iden(_) | AstNode::Num(_) | AstNode::Float(_) | AstNode::Str(_) => {
+			lines.append(&mut indent_lines(indent_sz, load(interim(node.ty()), node, context)));
 		},
 		AstNode::Arith(arith_node) => {
-			lines.append(&mut transpile_node(&*arith_node.lhs, indent_sz, context));
-			lines.push(indent(indent_sz, inst(
-				arith_node.op.to_string().as_str(),
-				Operand::Register(String::from(INTERIM_REG)),
-				ast_node_to_operand(*arith_node.rhs.clone(), context))));
+			let ty: Type = node.ty();
+			lines.append(&mut indent_lines(indent_sz, load(interim(ty), &arith_node.lhs, context)));
+			lines.append(&mut indent_lines(indent_sz, load(interim_2(ty), &arith_node.rhs, context)));
+			match arith_node.op {
+				_ if ty.is_float() => lines.push(indent(indent_sz, inst(
+					&format!("{}{}", arith_node.op.opcode(), sse_suffix(ty)),
+					Operand::Register(String::from(FLOAT_INTERIM_REG)),
+					Operand::Register(String::from(FLOAT_INTERIM_REG_2))))),
+				ArithOp::Sum | ArithOp::Sub | ArithOp::And | ArithOp::Or | ArithOp::Xor => lines.push(indent(indent_sz, inst(
+					arith_node.op.opcode().as_str(),
+					Operand::Register(String::from(INTERIM_REG)),
+					Operand::Register(String::from(INTERIM_REG_2))))),
+				ArithOp::Mul => lines.push(indent(indent_sz, inst(
+					"imul",
+					Operand::Register(String::from(INTERIM_REG)),
+					Operand::Register(String::from(INTERIM_REG_2))))),
+				ArithOp::Div | ArithOp::Rem => {
+					lines.push(indent(indent_sz, inst(
+						"mov",
+						Operand::Register(String::from("rax")),
+						Operand::Register(String::from(INTERIM_REG)))));
+					lines.push(indent(indent_sz, String::from("cqo")));
+					lines.push(indent(indent_sz, format!("idiv {}", INTERIM_REG_2)));
+					// quotient in rax, remainder in rdx
+					lines.push(indent(indent_sz, inst(
+						"mov",
+						Operand::Register(String::from(INTERIM_REG)),
+						Operand::Register(String::from(if arith_node.op == ArithOp::Div {"rax"} else {"rdx"})))));
+				},
+				ArithOp::Shl | ArithOp::Shr | ArithOp::LShr => {
+					// narrower values are held sign extended, a logical shift needs them zero extended
+					if arith_node.op == ArithOp::LShr && ty.bits() == 32 {
+						lines.push(indent(indent_sz, inst(
+							"mov",
+							Operand::Register(register(INTERIM_REG, ty)),
+							Operand::Register(register(INTERIM_REG, ty)))));
+					} else if arith_node.op == ArithOp::LShr && ty.bits() < 32 {
+						lines.push(indent(indent_sz, inst(
+							"and",
+							Operand::Register(String::from(INTERIM_REG)),
+							Operand::Immediate(ty.mask() as i64))));
+					}
+					// the count goes in cl and is taken modulo the width of the type
+					lines.push(indent(indent_sz, inst(
+						"mov",
+						Operand::Register(String::from("rcx")),
+						Operand::Register(String::from(INTERIM_REG_2)))));
+					lines.push(indent(indent_sz, inst(
+						"and",
+						Operand::Register(String::from("rcx")),
+						Operand::Immediate(ty.bits() as i64 - 1))));
+					lines.push(indent(indent_sz, inst(
+						arith_node.op.opcode().as_str(),
+						Operand::Register(String::from(INTERIM_REG)),
+						Operand::Register(String::from("cl")))));
+				},
+			}
 		},
 		AstNode::Relop(relop_node) => {
-			lines.append(&mut transpile_node(&*relop_node.lhs, indent_sz, context));
-			lines.push(indent(indent_sz, inst(
-				"cmp",
-				Operand::Register(String::from(INTERIM_REG)),
-				ast_node_to_operand(*relop_node.rhs.clone(), context))));
+			let ty: Type = relop_node.lhs.ty();
+			lines.append(&mut indent_lines(indent_sz, load(interim(ty), &relop_node.lhs, context)));
+			lines.append(&mut indent_lines(indent_sz, load(interim_2(ty), &relop_node.rhs, context)));
+			let cmov: &str = if ty.is_float() {
+				// `ucomis` sets the flags as an unsigned comparison would and raises
+				// the parity flag for unordered operands; `<` and `<=` are tested
+				// with swapped operands so a NaN makes every ordering false
+				let (lhs, rhs, cmov) = match relop_node.op {
+					RelOp::Eq => (FLOAT_INTERIM_REG, FLOAT_INTERIM_REG_2, "cmove"),
+					RelOp::Neq => (FLOAT_INTERIM_REG, FLOAT_INTERIM_REG_2, "cmovne"),
+					RelOp::Gt => (FLOAT_INTERIM_REG, FLOAT_INTERIM_REG_2, "cmova"),
+					RelOp::Ge => (FLOAT_INTERIM_REG, FLOAT_INTERIM_REG_2, "cmovae"),
+					RelOp::Lt => (FLOAT_INTERIM_REG_2, FLOAT_INTERIM_REG, "cmova"),
+					RelOp::Le => (FLOAT_INTERIM_REG_2, FLOAT_INTERIM_REG, "cmovae"),
+				};
+				lines.push(indent(indent_sz, inst(
+					&format!("ucomi{}", sse_suffix(ty)),
+					Operand::Register(String::from(lhs)),
+					Operand::Register(String::from(rhs)))));
+				cmov
+			} else {
+				lines.push(indent(indent_sz, inst(
+					"cmp",
+					Operand::Register(String::from(INTERIM_REG)),
+					Operand::Register(String::from(INTERIM_REG_2)))));
+				match relop_node.op {
+					RelOp::Eq => "cmove",
+					RelOp::Neq => "cmovne",
+					RelOp::Gt => "cmovg",
+					RelOp::Lt => "cmovl",
+					RelOp::Ge => "cmovge",
+					RelOp::Le => "cmovle",
+				}
+			};
 			lines.push(indent(indent_sz, inst(
 				"mov",
 				Operand::Register(String::from(INTERIM_REG)),
@@ -169,91 +596,158 @@ fn transpile_node(node: &AstNode, indent_sz: usize, context: &Context) -> Vec<St
 				Operand::Register(String::from(INTERIM_REG_2)),
 				Operand::Immediate(1))));
 			lines.push(indent(indent_sz, inst(
-				match relop_node.op {
-					RelOp::Eq => "cmove",
-					RelOp::Neq => "cmovne",
-					RelOp::Gt => "cmovg",
-					RelOp::Lt => "cmovl",
-					RelOp::Ge => "cmovge",
-					RelOp::Le => "cmovle",
-				},
+				cmov,
 				Operand::Register(String::from(INTERIM_REG)),
 				Operand::Register(String::from(INTERIM_REG_2)))));
+			if ty.is_float() && matches!(relop_node.op, RelOp::Eq | RelOp::Neq) {
+				// unordered operands are never equal
+				lines.push(indent(indent_sz, inst(
+					"mov",
+					Operand::Register(String::from(INTERIM_REG_2)),
+					Operand::Immediate((relop_node.op == RelOp::Neq) as i64))));
+				lines.push(indent(indent_sz, inst(
+					"cmovp",
+					Operand::Register(String::from(INTERIM_REG)),
+					Operand::Register(String::from(INTERIM_REG_2)))));
+			}
 		},
 		AstNode::Unary(unary_node) => {
-			lines.push(indent(indent_sz, inst(
-				"neg",
-				Operand::Register(String::from(INTERIM_REG)),
-				ast_node_to_operand(*unary_node.var.clone(), context))));
+			let ty: Type = unary_node.var.ty();
+			lines.append(&mut indent_lines(indent_sz, load(interim(ty), &unary_node.var, context)));
+			match unary_node.op {
+				UnaryOp::Neg if ty.is_float() => {
+					// flip the sign bit
+					let sign: AstNode = AstNode::Float(FloatAstNode{num: -0.0, ty, loc: unary_node.loc.clone()});
+					lines.append(&mut indent_lines(indent_sz, load(FLOAT_INTERIM_REG_2, &sign, context)));
+					lines.push(indent(indent_sz, inst(
+						if ty == Type::F32 {"xorps"} else {"xorpd"},
+						Operand::Register(String::from(FLOAT_INTERIM_REG)),
+						Operand::Register(String::from(FLOAT_INTERIM_REG_2)))));
+				},
+				UnaryOp::Neg => lines.push(indent(indent_sz, format!("neg {}", INTERIM_REG))),
+				UnaryOp::Not => {
+					lines.push(indent(indent_sz, inst(
+						"cmp",
+						Operand::Register(String::from(INTERIM_REG)),
+						Operand::Immediate(0))));
+					lines.push(indent(indent_sz, inst(
+						"mov",
+						Operand::Register(String::from(INTERIM_REG)),
+						Operand::Immediate(0))));
+					lines.push(indent(indent_sz, inst(
+						"mov",
+						Operand::Register(String::from(INTERIM_REG_2)),
+						Operand::Immediate(1))));
+					lines.push(indent(indent_sz, inst(
+						"cmove",
+						Operand::Register(String::from(INTERIM_REG)),
+						Operand::Register(String::from(INTERIM_REG_2)))));
+				},
+				UnaryOp::BitNot => lines.push(indent(indent_sz, format!("not {}", INTERIM_REG))),
+			}
 		},
 		AstNode::If(if_node) => {
-			lines.append(&mut transpile_node(&*if_node.condition, indent_sz, context));
-			lines.push(indent(indent_sz, inst(
-				"cmp",
-				Operand::Register(String::from(INTERIM_REG)),
-				Operand::Immediate(1))));
-			lines.push(indent(indent_sz, format!("je {}_label_{}", context.function_name, if_node.label)))
+			let (mut condition_lines, jump) = branch_condition(&if_node.condition, indent_sz, context)?;
+			lines.append(&mut condition_lines);
+			let jump: &str = if if_node.negated {inverse_jump(jump)} else {jump};
+			lines.push(indent(indent_sz, format!("{} {}_label_{}", jump, context.function_name, symbol(&if_node.label))));
+			if let Some(otherwise) = &if_node.otherwise {
+				lines.push(indent(indent_sz, format!("jmp {}_label_{}", context.function_name, symbol(otherwise))));
+			}
 		},
 		AstNode::Assignment(assignment_node) => {
-			lines.append(&mut transpile_node(&*assignment_node.var, indent_sz, context));
-			lines.push(indent(indent_sz, inst(
-				"mov",
-				context.operands.get(&assignment_node.name).unwrap().clone(),
-				Operand::Register(String::from(INTERIM_REG)))));
+			if let AstNode::Alloca(_) = &*assignment_node.var {
+				lines.push(indent(indent_sz, inst(
+					"lea",
+					Operand::Register(String::from(INTERIM_REG)),
+					Operand::Memory(format!("[rsp+{}]", context.allocas.get(&assignment_node.name).unwrap())))));
+			} else {
+				lines.append(&mut transpile_node(&assignment_node.var, indent_sz, context)?);
+			}
+			lines.append(&mut indent_lines(indent_sz, store(&assignment_node.name, node.ty(), interim(node.ty()), context)));
 		}
+		AstNode::Load(load_node) => {
+			lines.append(&mut indent_lines(indent_sz, load(INTERIM_REG_2, &load_node.ptr, context)));
+			lines.append(&mut indent_lines(indent_sz, load_operand(
+				interim(load_node.ty), Operand::Memory(format!("[{}]", INTERIM_REG_2)), load_node.ty)));
+		},
+		AstNode::Store(store_node) => {
+			let ty: Type = store_node.var.ty();
+			lines.append(&mut indent_lines(indent_sz, load(INTERIM_REG_2, &store_node.ptr, context)));
+			lines.append(&mut indent_lines(indent_sz, load(interim(ty), &store_node.var, context)));
+			lines.append(&mut indent_lines(indent_sz, store_operand(
+				Operand::Memory(format!("[{}]", INTERIM_REG_2)), ty, interim(ty))));
+		},
 		AstNode::Call(call_node) => {
-			let call_convention: Vec<String> = call_convention();
-			if call_node.params.len() > call_convention.len() {
-				call_node.loc.error(format!("fasm target currently only supports '{}' parameters at max",
-											call_convention.len()));
+			if let Callee::Direct(name) = &call_node.callee {
+				check_available(name, &call_node.loc, context)?;
 			}
+			let registers: Vec<String> = match argument_registers(&call_node.params.iter().map(|param| param.ty()).collect::<Vec<Type>>()) {
+				Some(registers) => registers,
+				None => return Err(call_node.loc.error(format!(
+					"fasm target currently only supports '{}' integer and '{}' float parameters at max",
+					call_convention().len(), float_call_convention().len())).with_code("E0300")),
+			};
 			for (ix, param) in call_node.params.iter().enumerate() {
-				lines.push(indent(indent_sz, inst(
-					"mov",
-					Operand::Register(call_convention[ix].clone()),
-					ast_node_to_operand(param.clone(), context))));
+				lines.append(&mut indent_lines(indent_sz, load(&registers[ix], param, context)));
+			}
+			match &call_node.callee {
+				Callee::Direct(name) => lines.push(indent(indent_sz, format!("call {}", symbol(name)))),
+				// loaded last as the parameters may be materialized through any other register
+				Callee::Indirect(ptr) => {
+					lines.append(&mut indent_lines(indent_sz, load(INTERIM_REG, ptr, context)));
+					lines.push(indent(indent_sz, format!("call {}", INTERIM_REG)));
+				},
+			}
+			if let Some(id) = &call_node.id {
+				lines.append(&mut indent_lines(indent_sz, store(id, node.ty(), return_register(node.ty()), context)));
 			}
-			lines.push(indent(indent_sz, format!("call {}", call_node.name)));
-			lines.push(indent(indent_sz, inst(
-				"mov",
-				context.operands.get(&call_node.id).unwrap().clone(),
-				Operand::Register(String::from("eax")))));
 		}
+		AstNode::FuncAddr(func_addr_node) => {
+			check_available(&func_addr_node.name, &func_addr_node.loc, context)?;
+			lines.append(&mut indent_lines(indent_sz, load_operand(
+				INTERIM_REG, Operand::Address(symbol(&func_addr_node.name)), Type::Ptr)));
+		},
 		AstNode::Label(label_node) => {
-			lines.push(indent(indent_sz, format!("{}_label_{}:", context.function_name, label_node.name)));
-			lines.append(&mut transpile_nodes(&label_node.body, indent_sz+1, context));
+			lines.push(indent(indent_sz, format!("{}_label_{}:", context.function_name, symbol(&label_node.name))));
+			lines.append(&mut transpile_nodes(&label_node.body, indent_sz+1, context)?);
 		}
 		AstNode::Goto(goto_node) => {
-			lines.push(indent(indent_sz, format!("jmp {}_label_{}", context.function_name, goto_node.name)));
+			lines.push(indent(indent_sz, format!("jmp {}_label_{}", context.function_name, symbol(&goto_node.name))));
 		}
+		AstNode::Switch(switch_node) => lines.append(&mut transpile_switch(switch_node, indent_sz, context)),
+		AstNode::Comment(comment_node) => lines.append(&mut transpile_comment(comment_node, indent_sz)),
 		AstNode::Ret(ret_node) => {
 			if context.entry_point {
 				lines.push(indent(indent_sz, inst(
 					"mov",
 					Operand::Register(String::from("eax")),
 					Operand::Immediate(60))));
-				lines.push(indent(indent_sz, inst(
-					"mov",
-					Operand::Register(String::from("edi")),
-					ast_node_to_operand(*ret_node.var.clone(), context))));
+				match &ret_node.var {
+					Some(var) => lines.append(&mut indent_lines(indent_sz, load("rdi", var, context))),
+					// returning nothing from 'main' exits successfully
+					None => lines.push(indent(indent_sz, inst(
+						"mov",
+						Operand::Register(String::from("rdi")),
+						Operand::Immediate(0)))),
+				}
 				lines.push(indent(indent_sz, inst(
 					"add",
 					Operand::Register(String::from("rsp")),
-					Operand::Immediate(context.value_operands_len as i32 * 4 as i32))));
-				lines.push(indent(indent_sz, format!("syscall")));
+					Operand::Immediate(context.frame_size as i64))));
+				lines.push(indent(indent_sz, String::from("syscall")));
 			} else {
-				lines.push(indent(indent_sz, inst(
-					"mov",
-					Operand::Register(String::from("eax")),
-					ast_node_to_operand(*ret_node.var.clone(), context))));
+				if let Some(var) = &ret_node.var {
+					lines.append(&mut indent_lines(indent_sz, load(return_register(var.ty()), var, context)));
+				}
 				lines.push(indent(indent_sz, inst(
 					"add",
 					Operand::Register(String::from("rsp")),
-					Operand::Immediate(context.value_operands_len as i32 * 4 as i32))));
-				lines.push(indent(indent_sz, format!("ret")));
+					Operand::Immediate(context.frame_size as i64))));
+				lines.push(indent(indent_sz, String::from("ret")));
 			}
 		}
 		_ => {}
 	}
-	lines
+	Ok(lines)
 }
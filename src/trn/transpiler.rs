@@ -1,10 +1,11 @@
-use std::{fs::File, io::Write};
-use crate::{fe::ast::*, cli::*};
+use std::{fs::File, io::Write, collections::HashMap};
+use crate::{fe::{ast::*, target::Target, diagnostic::{Diagnostic, Diagnostics}}, cli::*};
 use crate::trn::wat_transpiler::WatTranspiler;
 use crate::trn::fasm_transpiler::FasmTranspiler;
 
 pub trait Transpiler {
-	fn transpile(&self, nodes: &Vec<AstNode>) -> Vec<String>;
+	fn transpile(&self, module: &ModuleAstNode) -> Result<Vec<String>, Diagnostic>;
+	fn target(&self) -> Target;
 }
 
 pub fn remove_extension(filepath: String, ext: &str) -> String {
@@ -22,27 +23,191 @@ pub fn indent(indent_sz: usize, text: String) -> String {
 	line
 }
 
+fn alloca_sizes(nodes: &[AstNode], sizes: &mut Vec<(String, usize)>) {
+	for node in nodes.iter() {
+		match node {
+			AstNode::Label(label_node) => alloca_sizes(&label_node.body, sizes),
+			AstNode::Assignment(assignment_node) => if let AstNode::Alloca(alloca_node) = &*assignment_node.var {
+				match sizes.iter_mut().find(|(name, _)| *name == assignment_node.name) {
+					Some((_, size)) => *size = (*size).max(alloca_node.size),
+					None => sizes.push((assignment_node.name.clone(), alloca_node.size)),
+				}
+			},
+			_ => {},
+		}
+	}
+}
+
+/// Offsets of the memory reserved by the `alloca`s of a function within its
+/// frame, along with their total size. Like an `alloca` in the entry block of
+/// an LLVM function, each pointer gets a single 8 byte aligned region sized for
+/// the largest `alloca` assigned to it, no matter how often it runs.
+pub fn alloca_regions(function_node: &FunctionAstNode) -> (HashMap<String, usize>, usize) {
+	let mut sizes: Vec<(String, usize)> = Vec::new();
+	alloca_sizes(&function_node.body, &mut sizes);
+	let mut regions: HashMap<String, usize> = HashMap::new();
+	let mut frame_size: usize = 0;
+	for (name, size) in sizes.into_iter() {
+		regions.insert(name, frame_size);
+		frame_size += size.div_ceil(8) * 8;
+	}
+	(regions, frame_size)
+}
+
+/// Whether a switch is lowered to a table indexed by the scrutinee rather than a
+/// chain of comparisons; the table may hold at most twice as many entries as there
+/// are cases, plus a few, the rest of them pointing at the default.
+pub fn is_dense_switch(cases: &[SwitchCase]) -> bool {
+	let min: Option<i64> = cases.iter().map(|case| case.value.num).min();
+	let max: Option<i64> = cases.iter().map(|case| case.value.num).max();
+	match (min, max) {
+		(Some(min), Some(max)) => (max as i128 - min as i128 + 1) <= 2*cases.len() as i128 + 8,
+		_ => false,
+	}
+}
+
+fn collect_strings(node: &AstNode, strings: &mut Vec<Vec<u8>>) {
+	let children: Vec<&AstNode> = match node {
+		AstNode::Str(str_node) => {
+			if !strings.contains(&str_node.bytes) {
+				strings.push(str_node.bytes.clone());
+			}
+			vec![]
+		},
+		AstNode::Function(function_node) => function_node.body.iter().collect(),
+		AstNode::Label(label_node) => label_node.body.iter().collect(),
+		AstNode::Call(call_node) => call_node.params.iter().collect(),
+		AstNode::Arith(arith_node) => vec![&arith_node.lhs, &arith_node.rhs],
+		AstNode::Relop(relop_node) => vec![&relop_node.lhs, &relop_node.rhs],
+		AstNode::Unary(unary_node) => vec![&unary_node.var],
+		AstNode::Assignment(assignment_node) => vec![&assignment_node.var],
+		AstNode::If(if_node) => vec![&if_node.condition],
+		AstNode::Ret(ret_node) => ret_node.var.iter().map(|var| &**var).collect(),
+		AstNode::Load(load_node) => vec![&load_node.ptr],
+		AstNode::Store(store_node) => vec![&store_node.ptr, &store_node.var],
+		_ => vec![],
+	};
+	for child in children.into_iter() {
+		collect_strings(child, strings);
+	}
+}
+
+/// Distinct string literals of the program in order of appearance; targets lay
+/// them out as read-only data and refer to each by its index.
+pub fn string_literals(nodes: &[AstNode]) -> Vec<Vec<u8>> {
+	let mut strings: Vec<Vec<u8>> = Vec::new();
+	for node in nodes.iter() {
+		collect_strings(node, &mut strings);
+	}
+	strings
+}
 
-pub fn transpilation_mode<T: Transpiler + 'static>(transpiler: T, nodes: &Vec<AstNode>,
-										  output_filepath: String) {
+/// A program can only be compiled for the target its `target` directive names, and only
+/// if its `datalayout` directive lays out data as that target does.
+fn check_directives(target: Target, module: &ModuleAstNode) -> Result<(), Diagnostic> {
+	if let Some((program_target, loc)) = module.target() {
+		if program_target != target {
+			return Err(loc.error(format!("program targets '{}'; but is compiled for '{}'", program_target, target))
+					   .with_code("E0302")
+					   .with_label(String::from("target given here"))
+					   .with_help(format!("compile it with '{}'", program_target.flag())));
+		}
+	}
+	if let Some((layout, loc)) = module.datalayout() {
+		let message: Option<String> = if layout.big_endian {
+			Some(format!("target '{}' is little endian; but the data layout is big endian", target))
+		} else {
+			layout.pointer_bits.filter(|bits| *bits != target.pointer_bits())
+				.map(|bits| format!("target '{}' has {}-bit pointers; but the data layout has {}-bit pointers",
+									target, target.pointer_bits(), bits))
+		};
+		if let Some(message) = message {
+			return Err(loc.error(message)
+					   .with_code("E0303")
+					   .with_label(String::from("data layout given here")));
+		}
+	}
+	Ok(())
+}
+
+pub fn transpilation_mode<T: Transpiler + 'static>(transpiler: T, module: &ModuleAstNode,
+										  output_filepath: String, diagnostics: &mut Diagnostics) -> bool {
+	let lines: Vec<String> = match check_directives(transpiler.target(), module)
+		.and_then(|_| transpiler.transpile(module)) {
+		Ok(lines) => lines,
+		Err(diagnostic) => {
+			diagnostics.push(diagnostic);
+			return false;
+		},
+	};
 	let mut file = File::create(output_filepath).expect("could not create file");
-	for line in transpiler.transpile(nodes).iter() {
+	for line in lines.iter() {
 		file.write_all(line.as_bytes()).expect("could not write line");
 		file.write_all(b"\n").expect("could not write new line");
 	}
+	true
 }
 
-pub fn transpile(options: &CliOptions, nodes: &Vec<AstNode>, filepath: String) {
-	if options.wat || options.wasm {
+/// Generates the targets asked for on the command line or, when none is, the one named by
+/// the `target` directive of the program.
+pub fn transpile(options: &CliOptions, module: &ModuleAstNode, filepath: String,
+				 diagnostics: &mut Diagnostics) {
+	let flagged: bool = options.wat || options.wasm || options.fasm;
+	let target: Option<Target> = module.target().map(|(target, _)| target);
+	if options.wat || options.wasm || (!flagged && target == Some(Target::Wasm32)) {
 		let wat_filepath: String = replace_extension(filepath.clone(), "irl", "wat");
-		transpilation_mode(WatTranspiler{}, nodes, wat_filepath.clone());
-		if !options.wasm {return;}
-		let wasm_filepath: String = replace_extension(filepath.clone(), "irl", "wasm");
-		options.run_command(&["wat2wasm", wat_filepath.as_str(), "-o", wasm_filepath.as_str()]);
+		if !transpilation_mode(WatTranspiler{}, module, wat_filepath.clone(), diagnostics) {return;}
+		if options.wasm {
+			let wasm_filepath: String = replace_extension(filepath.clone(), "irl", "wasm");
+			options.run_command(&["wat2wasm", wat_filepath.as_str(), "-o", wasm_filepath.as_str()]);
+		}
 	}
-	if options.fasm {
+	if options.fasm || (!flagged && target == Some(Target::X86_64Linux)) {
 		let fasm_filepath: String = replace_extension(filepath.clone(), "irl", "fasm");
-		transpilation_mode(FasmTranspiler{}, nodes, fasm_filepath.clone());
+		if !transpilation_mode(FasmTranspiler{}, module, fasm_filepath.clone(), diagnostics) {return;}
 		options.run_command(&["fasm", fasm_filepath.as_str()]);
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::fe::{lexer::Lexer, parser::Parser};
+
+	/// Checks the directives of `source`, given one per line, against `target` and returns
+	/// the code of the diagnostic reported, if any.
+	fn check(target: Target, source: &[&str]) -> Option<String> {
+		let mut diagnostics: Diagnostics = Diagnostics::new();
+		let lexer: Lexer = Lexer::from_source(&source.join("\n"), String::from("test.irl"), &mut diagnostics);
+		let directives: Vec<DirectiveAstNode> = Parser::new(lexer.tokens, &mut diagnostics).nodes.into_iter()
+			.filter_map(|node| match node {
+				AstNode::Directive(node) => Some(node),
+				_ => None,
+			}).collect();
+		assert!(diagnostics.diagnostics.is_empty(), "unexpected diagnostics parsing {:?}", source);
+		check_directives(target, &ModuleAstNode{directives, nodes: vec![]}).err()
+			.map(|diagnostic| diagnostic.code.clone().unwrap_or_default())
+	}
+
+	#[test]
+	fn matching_directives() {
+		assert_eq!(check(Target::Wasm32, &[]), None);
+		assert_eq!(check(Target::Wasm32, &["target \"wasm32\"", "datalayout \"e-p:32:32\""]), None);
+		assert_eq!(check(Target::X86_64Linux, &["target \"x86_64-linux\"", "datalayout \"e-p:64:64\""]), None);
+		// a data layout without a pointer size fits either target
+		assert_eq!(check(Target::X86_64Linux, &["module \"m\"", "datalayout \"e-i64:64\""]), None);
+	}
+
+	#[test]
+	fn target_mismatch() {
+		assert_eq!(check(Target::X86_64Linux, &["target \"wasm32\""]), Some(String::from("E0302")));
+		assert_eq!(check(Target::Wasm32, &["target \"x86_64-unknown-linux-gnu\""]), Some(String::from("E0302")));
+	}
+
+	#[test]
+	fn datalayout_mismatch() {
+		assert_eq!(check(Target::Wasm32, &["datalayout \"E\""]), Some(String::from("E0303")));
+		assert_eq!(check(Target::Wasm32, &["datalayout \"e-p:64:64\""]), Some(String::from("E0303")));
+		assert_eq!(check(Target::X86_64Linux, &["datalayout \"p:32\""]), Some(String::from("E0303")));
+	}
+}
@@ -1,62 +1,567 @@
-use std::collections::HashSet;
-use crate::fe::ast::*;
-use crate::trn::transpiler::Transpiler;
+use std::collections::{HashMap, HashSet};
+use crate::fe::{ast::*, types::Type, target::Target, diagnostic::Diagnostic};
+use crate::trn::transpiler::{Transpiler, alloca_regions, string_literals, is_dense_switch};
 
 pub struct WatTranspiler;
 
+/// Function bodies are lowered to a dispatch loop: every label (and the code
+/// preceding the first label) becomes a segment, `$^pc` holds the segment to
+/// run next and a goto sets it before branching back to the loop header.
+/// Memory reserved by `alloca` lives in a frame carved off a downward growing
+/// stack in linear memory whose top is held in `$^sp`, string literals sit
+/// at its bottom. A function pointer is an index into the module's table of
+/// the functions whose address is taken.
+struct Context {
+	segments: HashMap<String, usize>,
+	table: HashMap<String, usize>,
+	allocas: HashMap<String, usize>,
+	frame_size: usize,
+	globals: HashSet<String>,
+	strings: HashMap<Vec<u8>, usize>,
+}
+
+impl Context {
+	/// `local.get`/`local.set` or their `global` counterparts when `name` is a global.
+	fn access(&self, op: &str, name: &str) -> String {
+		match self.globals.contains(name) {
+			true => format!("global.{} {}", op, wat_iden(name)),
+			false => format!("local.{} {}", op, wat_iden(name)),
+		}
+	}
+}
+
+const PC: &str = "$^pc";
+const DISPATCH: &str = "$^dispatch";
+const SP: &str = "$^sp";
+const STACK_SIZE: usize = 65536;
+/// memory below it is left unused so that no string is at the null pointer
+const DATA_START: usize = 8;
+
 impl Transpiler for WatTranspiler {
-	fn transpile(&self, nodes: &Vec<AstNode>) -> Vec<String> {
+	fn transpile(&self, module: &ModuleAstNode) -> Result<Vec<String>, Diagnostic> {
+		let nodes: &Vec<AstNode> = &module.nodes;
 		let mut lines: Vec<String> = Vec::new();
-		lines.push(String::from("(module"));
-		lines.append(&mut transpile_nodes_to_wat(nodes, 1, &mut HashSet::new()));
+		lines.push(match module.name() {
+			Some(name) => format!("(module {}", wat_iden(name)),
+			None => String::from("(module"),
+		});
+		lines.append(&mut imports(nodes));
+		let strings: Vec<Vec<u8>> = string_literals(nodes);
+		let has_allocas: bool = nodes.iter().any(|node| matches!(node, AstNode::Function(function_node)
+																 if alloca_regions(function_node).1 > 0));
+		if has_allocas || !strings.is_empty() {
+			lines.push(make_line(1, String::from("(memory (export \"memory\") 1)")));
+		}
+		if has_allocas {
+			lines.push(make_line(1, format!("(global {} (mut i32) (i32.const {}))", SP, STACK_SIZE)));
+		}
+		// the table starts at 1 so that calling a null function pointer traps
+		let mut func_addrs: Vec<String> = Vec::new();
+		function_refs(nodes, &mut func_addrs, true);
+		let mut table: HashMap<String, usize> = HashMap::new();
+		for name in func_addrs.into_iter() {
+			if !table.contains_key(&name) {
+				table.insert(name, table.len() + 1);
+			}
+		}
+		if !table.is_empty() {
+			let mut elems: Vec<(&String, &usize)> = table.iter().collect();
+			elems.sort_by_key(|(_, ix)| **ix);
+			lines.push(make_line(1, format!("(table {} funcref)", table.len() + 1)));
+			lines.push(make_line(1, format!("(elem (i32.const 1) func {})",
+											elems.iter().map(|(name, _)| wat_iden(name)).collect::<Vec<String>>().join(" "))));
+		}
+		// string literals are laid out NUL terminated from the bottom of memory, the stack grows down from the top
+		let mut offsets: HashMap<Vec<u8>, usize> = HashMap::new();
+		let mut offset: usize = DATA_START;
+		for bytes in strings.into_iter() {
+			let data: Vec<u8> = bytes.iter().copied().chain([0u8]).collect();
+			lines.push(make_line(1, format!("(data (i32.const {}) {})", offset, wat_string(&data))));
+			offsets.insert(bytes, offset);
+			offset += data.len();
+		}
+		let mut globals: HashSet<String> = HashSet::new();
+		for node in nodes.iter() {
+			if let AstNode::Global(global_node) = node {
+				let ty: &str = wat_type(node.ty());
+				lines.push(make_line(1, format!("(global {} (mut {}) ({}.const {}))", wat_iden(&global_node.name), ty, ty,
+												match &*global_node.init {
+													AstNode::Float(float_node) => format!("{:?}", float_node.num),
+													init => init.to_string(),
+												})));
+				globals.insert(global_node.name.clone());
+			}
+		}
+		for node in nodes.iter() {
+			match node {
+				AstNode::Function(function_node) => lines.append(&mut transpile_function(function_node, &globals, &offsets,
+																						   &table, 1)),
+				AstNode::Comment(comment_node) => lines.append(&mut transpile_comment(comment_node, 1)),
+				_ => {},
+			}
+		}
 		lines.push(String::from(")"));
-		lines
+		Ok(lines)
 	}
+	fn target(&self) -> Target {Target::Wasm32}
 }
 
-fn transpile_nodes_to_wat(nodes: &Vec<AstNode>, indent_sz: usize,
-						  vis_labels: &mut HashSet<String>) -> Vec<String> {
+fn make_line(indent_sz: usize, text: String) -> String {let mut line: String = String::new();
+	for _ in 0..indent_sz {line.push_str("    ");}
+	line.push_str(&text);
+	line
+}
 
-	let mut lines: Vec<String> = Vec::new();
+/// WAT `$id`; bytes outside the `idchar` set, and `^` itself, are escaped as `^xx`.
+fn wat_iden(name: &str) -> String {
+	let mut iden: String = String::from("$");
+	for b in name.bytes() {
+		if b.is_ascii_alphanumeric() || b"!#$%&'*+-./:<=>?@\\_`|~".contains(&b) {
+			iden.push(b as char);
+		} else {
+			iden.push_str(&format!("^{:02x}", b));
+		}
+	}
+	iden
+}
+
+fn wat_string(text: &[u8]) -> String {
+	let mut string: String = String::from("\"");
+	for b in text.iter().copied() {
+		match b {
+			b'"' | b'\\' => string.push_str(&format!("\\{}", b as char)),
+			0x20..=0x7e => string.push(b as char),
+			_ => string.push_str(&format!("\\{:02x}", b)),
+		}
+	}
+	string.push('"');
+	string
+}
+
+/// `i64`, `f32` and `f64` map onto their wasm namesakes; narrower integers are held in an `i32`.
+fn wat_type(ty: Type) -> &'static str {
+	match ty {
+		Type::I64 => "i64",
+		Type::F32 => "f32",
+		Type::F64 => "f64",
+		// linear memory addresses are 32-bit
+		_ => "i32",
+	}
+}
+
+/// `(result ty)` clause of a function type, left out for functions returning nothing.
+fn wat_result(ret_ty: Option<Type>) -> String {
+	match ret_ty {
+		Some(ty) => format!(" (result {})", wat_type(ty)),
+		None => String::new(),
+	}
+}
+
+/// Moves `$^sp` by `offset` bytes; a frame is pushed with a negative one.
+fn move_sp(offset: i64, indent_sz: usize) -> Vec<String> {
+	vec![make_line(indent_sz, format!("global.get {}", SP)),
+		 make_line(indent_sz, format!("i32.const {}", offset)),
+		 make_line(indent_sz, String::from("i32.add")),
+		 make_line(indent_sz, format!("global.set {}", SP))]
+}
+
+/// Load and store instructions accessing `ty` in memory, narrow integers only occupy their width.
+fn memory_access(ty: Type) -> (String, String) {
+	let wat_type: &str = wat_type(ty);
+	match ty {
+		Type::I1 => (String::from("i32.load8_u"), String::from("i32.store8")),
+		Type::I8 => (String::from("i32.load8_s"), String::from("i32.store8")),
+		Type::I16 => (String::from("i32.load16_s"), String::from("i32.store16")),
+		_ => (format!("{}.load", wat_type), format!("{}.store", wat_type)),
+	}
+}
+
+/// Brings the `i32` on top of the stack back into the range of a narrow type.
+fn normalize(ty: Type, indent_sz: usize) -> Vec<String> {
+	match ty {
+		Type::I1 => vec![make_line(indent_sz, String::from("i32.const 1")),
+						 make_line(indent_sz, String::from("i32.and"))],
+		Type::I8 => vec![make_line(indent_sz, String::from("i32.extend8_s"))],
+		Type::I16 => vec![make_line(indent_sz, String::from("i32.extend16_s"))],
+		Type::I32 | Type::I64 | Type::F32 | Type::F64 | Type::Ptr => vec![],
+	}
+}
+
+/// Functions called by name, or with `addrs_only` only those whose address is taken.
+fn function_refs(nodes: &[AstNode], refs: &mut Vec<String>, addrs_only: bool) {
 	for node in nodes.iter() {
-		lines.append(&mut transpile_node_to_wat(node, indent_sz, vis_labels));
+		match node {
+			AstNode::Function(function_node) => function_refs(&function_node.body, refs, addrs_only),
+			AstNode::Label(label_node) => function_refs(&label_node.body, refs, addrs_only),
+			AstNode::Call(CallAstNode{callee: Callee::Direct(name), ..}) if !addrs_only => refs.push(name.clone()),
+			AstNode::Assignment(assignment_node) => if let AstNode::FuncAddr(func_addr_node) = &*assignment_node.var {
+				refs.push(func_addr_node.name.clone());
+			},
+			_ => {},
+		}
+	}
+}
+
+fn local_types(nodes: &[AstNode], types: &mut HashMap<String, Type>) {
+	for node in nodes.iter() {
+		match node {
+			AstNode::Label(label_node) => local_types(&label_node.body, types),
+			AstNode::Assignment(assignment_node) => {types.insert(assignment_node.name.clone(), node.ty());},
+			AstNode::Call(CallAstNode{id: Some(id), ..}) => {types.insert(id.clone(), node.ty());},
+			_ => {},
+		}
+	}
+}
+
+/// Externs that are called or whose address is taken but are not defined in the module are
+/// imported from the host's `env`.
+fn imports(nodes: &[AstNode]) -> Vec<String> {
+	let defined: HashSet<String> = nodes.iter().filter_map(|node| match node {
+		AstNode::Function(function_node) => Some(function_node.name.clone()),
+		_ => None,
+	}).collect();
+	let externs: HashMap<String, &ExternAstNode> = nodes.iter().filter_map(|node| match node {
+		AstNode::Extern(extern_node) => Some((extern_node.name.clone(), extern_node)),
+		_ => None,
+	}).collect();
+	let mut refs: Vec<String> = Vec::new();
+	function_refs(nodes, &mut refs, false);
+	let mut lines: Vec<String> = Vec::new();
+	let mut imported: HashSet<String> = HashSet::new();
+	for name in refs.into_iter() {
+		if defined.contains(&name) || !imported.insert(name.clone()) {continue;}
+		let extern_node: &ExternAstNode = externs.get(&name).unwrap();
+		let params: String = extern_node.args.iter().map(|arg| format!(" (param {})", wat_type(arg.ty()))).collect();
+		lines.push(make_line(1, format!("(import \"env\" {} (func {}{}{}))",
+										wat_string(name.as_bytes()), wat_iden(&name), params,
+										wat_result(extern_node.ret_ty))));
 	}
 	lines
 }
 
-fn make_line(indent_sz: usize, text: String) -> String {let mut line: String = String::new();
-	for _ in 0..indent_sz {line.push_str("    ");}
-	line.push_str(&text);
-	line
+fn transpile_function(function_node: &FunctionAstNode, globals: &HashSet<String>,
+					  strings: &HashMap<Vec<u8>, usize>, table: &HashMap<String, usize>,
+					  indent_sz: usize) -> Vec<String> {
+	let mut lines: Vec<String> = Vec::new();
+	let mut line: String = make_line(indent_sz, format!("(func {} (export {})",
+														wat_iden(&function_node.name),
+														wat_string(function_node.name.as_bytes())));
+	let mut args: HashSet<String> = HashSet::new();
+	for arg in function_node.args.iter() {
+		if let AstNode::Iden(iden_node) = arg {
+			line += &format!(" (param {} {})", wat_iden(&iden_node.name), wat_type(iden_node.ty));
+			args.insert(iden_node.name.clone());
+		}
+	}
+	line += &wat_result(function_node.ret_ty);
+	lines.push(line);
+
+	let mut types: HashMap<String, Type> = HashMap::new();
+	local_types(&function_node.body, &mut types);
+	let mut locals: Vec<String> = AstNode::Function(function_node.clone()).value_operands()
+		.into_iter().filter(|name| !args.contains(name) && !globals.contains(name)).collect();
+	locals.sort();
+	for local in locals.iter() {
+		let ty: Type = types.get(local).copied().unwrap_or(Type::I32);
+		lines.push(make_line(indent_sz+1, format!("(local {} {})", wat_iden(local), wat_type(ty))));
+	}
+	lines.push(make_line(indent_sz+1, format!("(local {} i32)", PC)));
+
+	let mut segments: Vec<&Vec<AstNode>> = Vec::new();
+	let (allocas, frame_size) = alloca_regions(function_node);
+	let mut context: Context = Context{segments: HashMap::new(), table: table.clone(),
+									   allocas, frame_size,
+									   globals: globals.clone(), strings: strings.clone()};
+	let mut first: Vec<AstNode> = Vec::new();
+	for node in function_node.body.iter() {
+		match node {
+			AstNode::Label(label_node) => {
+				context.segments.insert(label_node.name.clone(), segments.len() + 1);
+				segments.push(&label_node.body);
+			},
+			_ => first.push(node.clone()),
+		}
+	}
+	segments.insert(0, &first);
+
+	if context.frame_size > 0 {
+		lines.append(&mut move_sp(-(context.frame_size as i64), indent_sz+1));
+	}
+	lines.push(make_line(indent_sz+1, format!("(loop {}", DISPATCH)));
+	for ix in (0..segments.len()).rev() {
+		lines.push(make_line(indent_sz+2+segments.len()-1-ix, format!("(block $^seg.{}", ix)));
+	}
+	let targets: Vec<String> = (0..segments.len()).map(|ix| format!("$^seg.{}", ix)).collect();
+	lines.push(make_line(indent_sz+2+segments.len(), format!("(br_table {} (local.get {}))",
+															  targets.join(" "), PC)));
+	for (ix, segment) in segments.iter().enumerate() {
+		let segment_indent_sz: usize = indent_sz+2+segments.len()-1-ix;
+		lines.push(make_line(segment_indent_sz, String::from(")")));
+		for node in segment.iter() {
+			lines.append(&mut transpile_node(node, segment_indent_sz, &context));
+		}
+	}
+	lines.push(make_line(indent_sz+1, String::from(")")));
+	// falling off the end of the function pops its frame like a 'ret' does
+	if context.frame_size > 0 {
+		lines.append(&mut move_sp(context.frame_size as i64, indent_sz+1));
+	}
+	if let Some(ret_ty) = function_node.ret_ty {
+		lines.push(make_line(indent_sz+1, format!("{}.const 0", wat_type(ret_ty))));
+	}
+	lines.push(make_line(indent_sz, String::from(")")));
+	lines
+}
+
+fn transpile_operand(node: &AstNode, indent_sz: usize, context: &Context) -> Vec<String> {
+	match node {
+		AstNode::Iden(iden_node) => vec![make_line(indent_sz, context.access("get", &iden_node.name))],
+		AstNode::Num(num_node) => vec![make_line(indent_sz, format!("{}.const {}", wat_type(num_node.ty), num_node.num))],
+		AstNode::Float(float_node) => vec![make_line(indent_sz, format!("{}.const {:?}", wat_type(float_node.ty), float_node.num))],
+		AstNode::Str(str_node) => vec![make_line(indent_sz, format!("i32.const {}", context.strings.get(&str_node.bytes).unwrap()))],
+		_ => unreachable!(),
+	}
 }
 
-fn transpile_node_to_wat(node: &AstNode, indent_sz: usize, vis_labels: &mut HashSet<String>) -> Vec<String> {
+fn transpile_comment(comment_node: &CommentAstNode, indent_sz: usize) -> Vec<String> {
+	comment_node.lines().iter().map(|line| make_line(indent_sz, format!(";; {}", line))).collect()
+}
+
+fn goto(label: &String, indent_sz: usize, context: &Context) -> Vec<String> {
+	vec![make_line(indent_sz, format!("i32.const {}", context.segments.get(label).unwrap())),
+		 make_line(indent_sz, format!("local.set {}", PC)),
+		 make_line(indent_sz, format!("br {}", DISPATCH))]
+}
+
+/// A dense switch branches through a `br_table` out of nested blocks, one per distinct
+/// target with the first innermost, each followed by the goto to its target; a sparse
+/// one compares the scrutinee against every case in turn.
+fn transpile_switch(switch_node: &SwitchAstNode, indent_sz: usize, context: &Context) -> Vec<String> {
+	let mut lines: Vec<String> = Vec::new();
+	let t: &str = wat_type(switch_node.scrutinee.ty());
+	if !is_dense_switch(&switch_node.cases) {
+		for case in switch_node.cases.iter() {
+			lines.append(&mut transpile_operand(&switch_node.scrutinee, indent_sz, context));
+			lines.push(make_line(indent_sz, format!("{}.const {}", t, case.value.num)));
+			lines.push(make_line(indent_sz, format!("{}.eq", t)));
+			lines.push(make_line(indent_sz, String::from("(if (then")));
+			lines.append(&mut goto(&case.label, indent_sz+1, context));
+			lines.push(make_line(indent_sz, String::from("))")));
+		}
+		lines.append(&mut goto(&switch_node.default, indent_sz, context));
+		return lines;
+	}
+	let mut targets: Vec<&String> = Vec::new();
+	for label in switch_node.cases.iter().map(|case| &case.label).chain([&switch_node.default]) {
+		if !targets.contains(&label) {
+			targets.push(label);
+		}
+	}
+	let block = |label: &String| format!("$^case.{}", targets.iter().position(|target| *target == label).unwrap());
+	let min: i64 = switch_node.cases.iter().map(|case| case.value.num).min().unwrap();
+	let max: i64 = switch_node.cases.iter().map(|case| case.value.num).max().unwrap();
+	let table: Vec<String> = (min..=max).map(|value| {
+		block(switch_node.cases.iter().find(|case| case.value.num == value).map_or(&switch_node.default, |case| &case.label))
+	}).collect();
+	for ix in (0..targets.len()).rev() {
+		lines.push(make_line(indent_sz+targets.len()-1-ix, format!("(block $^case.{}", ix)));
+	}
+	let body_indent_sz: usize = indent_sz+targets.len();
+	let mut index: Vec<String> = transpile_operand(&switch_node.scrutinee, body_indent_sz, context);
+	index.push(make_line(body_indent_sz, format!("{}.const {}", t, min)));
+	index.push(make_line(body_indent_sz, format!("{}.sub", t)));
+	if t == "i64" {
+		// the table is indexed by an `i32`, so out of range values are sent past its end first
+		lines.append(&mut index.clone());
+		lines.push(make_line(body_indent_sz, String::from("i32.wrap_i64")));
+		lines.push(make_line(body_indent_sz, format!("i32.const {}", table.len())));
+		lines.append(&mut index);
+		lines.push(make_line(body_indent_sz, format!("i64.const {}", table.len())));
+		lines.push(make_line(body_indent_sz, String::from("i64.lt_u")));
+		lines.push(make_line(body_indent_sz, String::from("select")));
+	} else {
+		lines.append(&mut index);
+	}
+	lines.push(make_line(body_indent_sz, format!("br_table {} {}", table.join(" "), block(&switch_node.default))));
+	for (ix, target) in targets.iter().enumerate() {
+		let target_indent_sz: usize = indent_sz+targets.len()-1-ix;
+		lines.push(make_line(target_indent_sz, String::from(")")));
+		lines.append(&mut goto(target, target_indent_sz, context));
+	}
+	lines
+}
+
+fn transpile_node(node: &AstNode, indent_sz: usize, context: &Context) -> Vec<String> {
 	let mut lines: Vec<String> = Vec::new();
 	match node {
-		AstNode::Function(function_node) => {
-			let mut line: String = make_line(indent_sz, format!("(func ${} (export \"{}\")",
-																function_node.name, function_node.name));
-			for arg_name in function_node.args.iter() {
-				line += &format!(" (param ${} i32)", arg_name);
-			}
-			line += " (result i32)";
-			lines.push(line);
-			lines.append(&mut transpile_nodes_to_wat(&function_node.body, indent_sz+1, vis_labels));
-			lines.push(make_line(indent_sz+1, String::from("i32.const 0")));
-			lines.push(make_line(indent_sz, String::from("    )")));
-		}
-		AstNode::Label(label_node) => {
-			lines.push(make_line(indent_sz, format!("(block ${}", label_node.name)));
-			vis_labels.insert(label_node.name.clone());
-			lines.append(&mut transpile_nodes_to_wat(&label_node.body, indent_sz+1, vis_labels));
-			lines.push(make_line(indent_sz, String::from(")")));
-		}
-		AstNode::Goto(goto_node) => {
-			if vis_labels.contains(&goto_node.name) {
-				lines.push(make_line(indent_sz, format!("br ${}", goto_node.name)));
-			}
-		}
-		_ => {}
+		AstNode::Iden(_) | AstNode::Num(_) | AstNode::Float(_) | AstNode::Str(_) => {
+			lines.append(&mut transpile_operand(node, indent_sz, context));
+		},
+		AstNode::Arith(arith_node) => {
+			let ty: Type = node.ty();
+			let t: &str = wat_type(ty);
+			// narrower integers are held sign extended in an `i32`: a logical shift needs
+			// them zero extended and shift counts are taken modulo their own width
+			let narrow: bool = !ty.is_float() && ty.bits() < 32;
+			lines.append(&mut transpile_operand(&arith_node.lhs, indent_sz, context));
+			if narrow && arith_node.op == ArithOp::LShr {
+				lines.push(make_line(indent_sz, format!("i32.const {}", ty.mask())));
+				lines.push(make_line(indent_sz, String::from("i32.and")));
+			}
+			lines.append(&mut transpile_operand(&arith_node.rhs, indent_sz, context));
+			if narrow && matches!(arith_node.op, ArithOp::Shl | ArithOp::Shr | ArithOp::LShr) {
+				lines.push(make_line(indent_sz, format!("i32.const {}", ty.bits() - 1)));
+				lines.push(make_line(indent_sz, String::from("i32.and")));
+			}
+			lines.push(make_line(indent_sz, format!("{}.{}", t, match arith_node.op {
+				ArithOp::Sum => "add",
+				ArithOp::Sub => "sub",
+				ArithOp::Mul => "mul",
+				ArithOp::Div => if ty.is_float() {"div"} else {"div_s"},
+				ArithOp::Rem => "rem_s",
+				ArithOp::And => "and",
+				ArithOp::Or => "or",
+				ArithOp::Xor => "xor",
+				ArithOp::Shl => "shl",
+				ArithOp::Shr => "shr_s",
+				ArithOp::LShr => "shr_u",
+			})));
+		},
+		AstNode::Relop(relop_node) => {
+			lines.append(&mut transpile_operand(&relop_node.lhs, indent_sz, context));
+			lines.append(&mut transpile_operand(&relop_node.rhs, indent_sz, context));
+			let ty: Type = relop_node.lhs.ty();
+			lines.push(make_line(indent_sz, format!("{}.{}{}", wat_type(ty), match relop_node.op {
+				RelOp::Eq => "eq",
+				RelOp::Neq => "ne",
+				RelOp::Gt => "gt",
+				RelOp::Lt => "lt",
+				RelOp::Ge => "ge",
+				RelOp::Le => "le",
+			}, if ty.is_float() || matches!(relop_node.op, RelOp::Eq | RelOp::Neq) {""} else {"_s"})));
+		},
+		AstNode::Unary(unary_node) => {
+			let t: &str = wat_type(unary_node.var.ty());
+			match unary_node.op {
+				UnaryOp::Neg if unary_node.var.ty().is_float() => {
+					lines.append(&mut transpile_operand(&unary_node.var, indent_sz, context));
+					lines.push(make_line(indent_sz, format!("{}.neg", t)));
+				},
+				UnaryOp::Neg => {
+					lines.push(make_line(indent_sz, format!("{}.const 0", t)));
+					lines.append(&mut transpile_operand(&unary_node.var, indent_sz, context));
+					lines.push(make_line(indent_sz, format!("{}.sub", t)));
+				},
+				UnaryOp::Not => {
+					lines.append(&mut transpile_operand(&unary_node.var, indent_sz, context));
+					lines.push(make_line(indent_sz, format!("{}.eqz", t)));
+				},
+				UnaryOp::BitNot => {
+					lines.append(&mut transpile_operand(&unary_node.var, indent_sz, context));
+					lines.push(make_line(indent_sz, format!("{}.const -1", t)));
+					lines.push(make_line(indent_sz, format!("{}.xor", t)));
+				},
+			}
+		},
+		AstNode::Assignment(assignment_node) if matches!(*assignment_node.var, AstNode::Alloca(_)) => {
+			lines.push(make_line(indent_sz, format!("global.get {}", SP)));
+			lines.push(make_line(indent_sz, format!("i32.const {}", context.allocas.get(&assignment_node.name).unwrap())));
+			lines.push(make_line(indent_sz, String::from("i32.add")));
+			lines.push(make_line(indent_sz, context.access("set", &assignment_node.name)));
+		},
+		AstNode::Assignment(assignment_node) => {
+			lines.append(&mut transpile_node(&assignment_node.var, indent_sz, context));
+			match (&*assignment_node.var, node.ty()) {
+				// comparisons and '!' yield an `i32` holding 0 or 1
+				(AstNode::Relop(_), Type::I64) => lines.push(make_line(indent_sz, String::from("i64.extend_i32_u"))),
+				(AstNode::Relop(_), _) => {},
+				(AstNode::Unary(unary_node), Type::I64) if unary_node.op == UnaryOp::Not =>
+					lines.push(make_line(indent_sz, String::from("i64.extend_i32_u"))),
+				(AstNode::Unary(unary_node), _) if unary_node.op == UnaryOp::Not => {},
+				(_, ty) => lines.append(&mut normalize(ty, indent_sz)),
+			}
+			lines.push(make_line(indent_sz, context.access("set", &assignment_node.name)));
+		},
+		AstNode::FuncAddr(func_addr_node) => {
+			lines.push(make_line(indent_sz, format!("i32.const {}", context.table.get(&func_addr_node.name).unwrap())));
+		},
+		AstNode::Load(load_node) => {
+			lines.append(&mut transpile_operand(&load_node.ptr, indent_sz, context));
+			lines.push(make_line(indent_sz, memory_access(load_node.ty).0));
+		},
+		AstNode::Store(store_node) => {
+			lines.append(&mut transpile_operand(&store_node.ptr, indent_sz, context));
+			lines.append(&mut transpile_operand(&store_node.var, indent_sz, context));
+			lines.push(make_line(indent_sz, memory_access(store_node.var.ty()).1));
+		},
+		AstNode::Call(call_node) => {
+			for param in call_node.params.iter() {
+				lines.append(&mut transpile_operand(param, indent_sz, context));
+			}
+			match &call_node.callee {
+				Callee::Direct(name) => lines.push(make_line(indent_sz, format!("call {}", wat_iden(name)))),
+				// the signature is the one implied by the call, a mismatch traps
+				Callee::Indirect(ptr) => {
+					lines.append(&mut transpile_operand(ptr, indent_sz, context));
+					let params: String = call_node.params.iter().map(|param| format!(" (param {})", wat_type(param.ty()))).collect();
+					lines.push(make_line(indent_sz, format!("call_indirect{}{}", params, wat_result(call_node.ty))));
+				},
+			}
+			match (&call_node.id, call_node.ty) {
+				(Some(id), _) => {
+					lines.append(&mut normalize(node.ty(), indent_sz));
+					lines.push(make_line(indent_sz, context.access("set", id)));
+				},
+				// the result of a bare call is discarded
+				(None, Some(_)) => lines.push(make_line(indent_sz, String::from("drop"))),
+				(None, None) => {},
+			}
+		},
+		AstNode::If(if_node) => {
+			let mut condition: Vec<String> = transpile_node(&if_node.condition, indent_sz, context);
+			let mut negated: bool = if_node.negated;
+			// branches test an `i32`, so a wide operand is compared against zero
+			if !matches!(*if_node.condition, AstNode::Relop(_)) && if_node.condition.ty() == Type::I64 {
+				condition.push(make_line(indent_sz, String::from("i64.eqz")));
+				negated = !negated;
+			}
+			match &if_node.otherwise {
+				Some(otherwise) => {
+					let (label, otherwise) = if negated {(otherwise, &if_node.label)} else {(&if_node.label, otherwise)};
+					lines.push(make_line(indent_sz, format!("i32.const {}", context.segments.get(label).unwrap())));
+					lines.push(make_line(indent_sz, format!("i32.const {}", context.segments.get(otherwise).unwrap())));
+					lines.append(&mut condition);
+					lines.push(make_line(indent_sz, String::from("select")));
+					lines.push(make_line(indent_sz, format!("local.set {}", PC)));
+					lines.push(make_line(indent_sz, format!("br {}", DISPATCH)));
+				},
+				None => {
+					lines.append(&mut condition);
+					if negated {
+						lines.push(make_line(indent_sz, String::from("i32.eqz")));
+					}
+					lines.push(make_line(indent_sz, String::from("(if (then")));
+					lines.append(&mut goto(&if_node.label, indent_sz+1, context));
+					lines.push(make_line(indent_sz, String::from("))")));
+				},
+			}
+		},
+		AstNode::Switch(switch_node) => lines.append(&mut transpile_switch(switch_node, indent_sz, context)),
+		AstNode::Goto(goto_node) => lines.append(&mut goto(&goto_node.name, indent_sz, context)),
+		AstNode::Ret(ret_node) => {
+			if let Some(var) = &ret_node.var {
+				lines.append(&mut transpile_operand(var, indent_sz, context));
+			}
+			if context.frame_size > 0 {
+				lines.append(&mut move_sp(context.frame_size as i64, indent_sz));
+			}
+			lines.push(make_line(indent_sz, String::from("return")));
+		},
+		AstNode::Comment(comment_node) => lines.append(&mut transpile_comment(comment_node, indent_sz)),
+		AstNode::Function(_) | AstNode::Label(_) | AstNode::Alloca(_) | AstNode::Global(_)
+			| AstNode::Const(_) | AstNode::Import(_) | AstNode::Directive(_) | AstNode::Extern(_) | AstNode::Error(_) => unreachable!(),
+		// lowered to labels and jumps by lower_control_flow_pass
+		AstNode::While(_) | AstNode::IfBlock(_) | AstNode::LoopJump(_) => unreachable!(),
 	}
 	lines
 }
@@ -0,0 +1,72 @@
+//! Compiles every program in `eg/` for both backends and compares the generated code with
+//! the files checked in under `tests/golden/`. Run with `IRL_BLESS=1` to rewrite them after
+//! an intended change of the output. Files in the subdirectories of `eg/` are only compiled
+//! as imports of the programs.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const BACKENDS: [&str; 2] = ["wat", "fasm"];
+
+fn examples(dir: &Path) -> Vec<PathBuf> {
+	let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+		.expect("could not read examples")
+		.map(|entry| entry.unwrap().path())
+		.filter(|path| path.extension().is_some_and(|extension| extension == "irl"))
+		.collect();
+	paths.sort();
+	paths
+}
+
+/// Copies the `.irl` files of `from` and its subdirectories to `to`.
+fn copy_sources(from: &Path, to: &Path) {
+	std::fs::create_dir_all(to).expect("could not create directory");
+	for entry in std::fs::read_dir(from).expect("could not read directory") {
+		let path: PathBuf = entry.unwrap().path();
+		if path.is_dir() {
+			copy_sources(&path, &to.join(path.file_name().unwrap()));
+		} else if path.extension().is_some_and(|extension| extension == "irl") {
+			std::fs::copy(&path, to.join(path.file_name().unwrap())).expect("could not copy example");
+		}
+	}
+}
+
+/// Compiles `filepath`, a copy of an example, and returns the code generated by every backend.
+fn compile(filepath: &Path) -> Vec<String> {
+	let output: Output = Command::new(env!("CARGO_BIN_EXE_irl"))
+		.args(["compile", "--wat", "--fasm-linux-x86_64", "--color", "never", "-f"])
+		.arg(filepath)
+		.output()
+		.expect("could not run irl");
+	assert!(output.status.success(), "{} does not compile:\n{}", filepath.display(),
+			String::from_utf8_lossy(&output.stderr));
+	BACKENDS.iter()
+		.map(|backend| std::fs::read_to_string(filepath.with_extension(backend))
+			 .unwrap_or_else(|_| panic!("{} generated no {} output", filepath.display(), backend)))
+		.collect()
+}
+
+#[test]
+fn examples_match_golden_output() {
+	// compiled in a copy, so that the outputs do not land next to the examples
+	let dir: PathBuf = std::env::temp_dir().join(format!("irl-golden-{}", std::process::id()));
+	copy_sources(&Path::new(env!("CARGO_MANIFEST_DIR")).join("eg"), &dir);
+	let golden_dir: PathBuf = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden");
+	let bless: bool = std::env::var_os("IRL_BLESS").is_some();
+	let mut mismatches: Vec<String> = vec![];
+	for source in examples(&dir) {
+		let outputs: Vec<String> = compile(&source);
+		assert_eq!(outputs, compile(&source), "{} compiles differently each time", source.display());
+		for (backend, output) in BACKENDS.iter().zip(outputs.iter()) {
+			let golden: PathBuf = golden_dir.join(source.file_name().unwrap()).with_extension(backend);
+			if bless {
+				std::fs::write(&golden, output).expect("could not write golden file");
+			} else if std::fs::read_to_string(&golden).ok().as_ref() != Some(output) {
+				mismatches.push(format!("{}:\n{}", golden.display(), output));
+			}
+		}
+	}
+	let _ = std::fs::remove_dir_all(&dir);
+	assert!(mismatches.is_empty(), "output differs from the golden files, rerun with IRL_BLESS=1 if intended\n\n{}",
+			mismatches.join("\n"));
+}
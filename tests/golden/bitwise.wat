@@ -0,0 +1,104 @@
+(module
+    (import "env" "print" (func $print (param i32) (result i32)))
+    ;; Remainder, bitwise and shift operators; '%' directly after an operand is
+    ;; the remainder, elsewhere it starts an identifier.
+    (func $main (export "main") (result i32)
+        (local $%and i32)
+        (local $%or i32)
+        (local $%xor i32)
+        (local $a i32)
+        (local $b i32)
+        (local $m i32)
+        (local $neg i32)
+        (local $ok i32)
+        (local $r i32)
+        (local $s i32)
+        (local $sar i32)
+        (local $shr i32)
+        (local $x i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            i32.const 12
+            local.set $a
+            i32.const 10
+            local.set $b
+            i32.const 12
+            i32.const 10
+            i32.and
+            local.set $%and
+            i32.const 12
+            i32.const 10
+            i32.or
+            local.set $%or
+            i32.const 12
+            i32.const 10
+            i32.xor
+            local.set $%xor
+            local.get $%and
+            call $print
+            drop
+            local.get $%or
+            call $print
+            drop
+            local.get $%xor
+            call $print
+            drop
+            i32.const 17
+            i32.const 5
+            i32.rem_s
+            local.set $r
+            local.get $r
+            call $print
+            drop
+            i32.const -17
+            i32.const 5
+            i32.rem_s
+            local.set $m
+            i32.const -2
+            i32.const 0
+            i32.lt_s
+            local.set $neg
+            local.get $neg
+            call $print
+            drop
+            i32.const 1
+            i32.const 10
+            i32.shl
+            local.set $s
+            local.get $s
+            call $print
+            drop
+            i32.const -64
+            local.set $x
+            i32.const -64
+            i32.const 2
+            i32.shr_s
+            local.set $sar
+            i32.const -16
+            i32.const -16
+            i32.eq
+            local.set $ok
+            local.get $ok
+            call $print
+            drop
+            i32.const -64
+            i32.const 28
+            i32.shr_u
+            local.set $shr
+            local.get $shr
+            call $print
+            drop
+            i32.const 0
+            return
+        )
+        i32.const 0
+    )
+)
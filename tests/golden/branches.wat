@@ -0,0 +1,101 @@
+(module
+    (import "env" "print" (func $print (param i32) (result i32)))
+    ;; Branches on a comparison or a single operand, negated with ifnot, or
+    ;; two-way with an else label.
+    (func $sign (export "sign") (param $x i32) (result i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.5
+                (block $^seg.4
+                    (block $^seg.3
+                        (block $^seg.2
+                            (block $^seg.1
+                                (block $^seg.0
+                                    (br_table $^seg.0 $^seg.1 $^seg.2 $^seg.3 $^seg.4 $^seg.5 (local.get $^pc))
+                                )
+                                i32.const 1
+                                local.set $^pc
+                                br $^dispatch
+                            )
+                            i32.const 2
+                            i32.const 3
+                            local.get $x
+                            i32.const 0
+                            i32.lt_s
+                            select
+                            local.set $^pc
+                            br $^dispatch
+                        )
+                        i32.const 1
+                        return
+                    )
+                    i32.const 5
+                    i32.const 4
+                    local.get $x
+                    select
+                    local.set $^pc
+                    br $^dispatch
+                )
+                i32.const 2
+                return
+            )
+            i32.const 3
+            return
+        )
+        i32.const 0
+    )
+    (func $main (export "main") (result i32)
+        (local $a i32)
+        (local $b i32)
+        (local $c i32)
+        (local $flag i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.3
+                (block $^seg.2
+                    (block $^seg.1
+                        (block $^seg.0
+                            (br_table $^seg.0 $^seg.1 $^seg.2 $^seg.3 (local.get $^pc))
+                        )
+                        i32.const 1
+                        local.set $^pc
+                        br $^dispatch
+                    )
+                    i32.const -7
+                    call $sign
+                    local.set $a
+                    local.get $a
+                    call $print
+                    drop
+                    i32.const 0
+                    call $sign
+                    local.set $b
+                    local.get $b
+                    call $print
+                    drop
+                    i32.const 9
+                    call $sign
+                    local.set $c
+                    local.get $c
+                    call $print
+                    drop
+                    local.get $c
+                    i32.const 3
+                    i32.eq
+                    local.set $flag
+                    i32.const 2
+                    i32.const 3
+                    local.get $flag
+                    select
+                    local.set $^pc
+                    br $^dispatch
+                )
+                i32.const 0
+                return
+            )
+            i32.const 1
+            return
+        )
+        i32.const 0
+    )
+)
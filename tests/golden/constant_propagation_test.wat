@@ -0,0 +1,93 @@
+(module
+    (import "env" "print" (func $print (param i32) (result i32)))
+    (func $foo (export "foo") (param $a i32) (result i32)
+        (local $c i32)
+        (local $x i32)
+        (local $y i32)
+        (local $z i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.4
+                (block $^seg.3
+                    (block $^seg.2
+                        (block $^seg.1
+                            (block $^seg.0
+                                (br_table $^seg.0 $^seg.1 $^seg.2 $^seg.3 $^seg.4 (local.get $^pc))
+                            )
+                            i32.const 1
+                            local.set $^pc
+                            br $^dispatch
+                        )
+                        i32.const 3
+                        local.set $c
+                        i32.const 7
+                        local.set $y
+                        i32.const 3
+                        local.set $z
+                        i32.const 2
+                        i32.const 3
+                        local.get $a
+                        i32.const 2
+                        i32.gt_s
+                        select
+                        local.set $^pc
+                        br $^dispatch
+                    )
+                    i32.const 3
+                    i32.const 3
+                    i32.add
+                    local.set $c
+                    i32.const 7
+                    i32.const 3
+                    i32.sub
+                    local.set $x
+                    ;; goto end
+                    i32.const 4
+                    local.set $^pc
+                    br $^dispatch
+                )
+                i32.const 7
+                i32.const 3
+                i32.add
+                local.set $x
+                i32.const 3
+                i32.const 3
+                i32.mul
+                local.set $z
+                i32.const 4
+                local.set $^pc
+                br $^dispatch
+            )
+            local.get $c
+            local.get $c
+            i32.mul
+            local.set $a
+            local.get $a
+            return
+        )
+        i32.const 0
+    )
+    (func $main (export "main") (result i32)
+        (local $x i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            i32.const 5
+            call $foo
+            local.set $x
+            local.get $x
+            call $print
+            drop
+            i32.const 0
+            return
+        )
+        i32.const 0
+    )
+)
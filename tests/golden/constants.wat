@@ -0,0 +1,88 @@
+(module
+    (import "env" "print" (func $print (param i32) (result i32)))
+    (global $half (mut i32) (i32.const 5))
+    ;; Constants are evaluated at compile time and take the type of the context
+    ;; they are used in, including the initializer of a global.
+    (func $main (export "main") (result i32)
+        (local $big i32)
+        (local $f f64)
+        (local $five i32)
+        (local $i i32)
+        (local $s i32)
+        (local $x i64)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.4
+                (block $^seg.3
+                    (block $^seg.2
+                        (block $^seg.1
+                            (block $^seg.0
+                                (br_table $^seg.0 $^seg.1 $^seg.2 $^seg.3 $^seg.4 (local.get $^pc))
+                            )
+                            i32.const 1
+                            local.set $^pc
+                            br $^dispatch
+                        )
+                        i32.const 0
+                        local.set $s
+                        i32.const 0
+                        local.set $i
+                        i32.const 2
+                        local.set $^pc
+                        br $^dispatch
+                    )
+                    i32.const 3
+                    i32.const 4
+                    local.get $i
+                    i32.const 10
+                    i32.eq
+                    select
+                    local.set $^pc
+                    br $^dispatch
+                )
+                local.get $s
+                call $print
+                drop
+                global.get $half
+                call $print
+                drop
+                i64.const 1099511627776
+                i64.const 1
+                i64.add
+                local.set $x
+                i64.const 1099511627777
+                i64.const 1099511627776
+                i64.gt_s
+                local.set $big
+                local.get $big
+                call $print
+                drop
+                f64.const 2.5
+                f64.const 2.0
+                f64.mul
+                local.set $f
+                f64.const 5.0
+                f64.const 5.0
+                f64.eq
+                local.set $five
+                local.get $five
+                call $print
+                drop
+                i32.const 0
+                return
+            )
+            local.get $s
+            local.get $i
+            i32.add
+            local.set $s
+            local.get $i
+            i32.const 1
+            i32.add
+            local.set $i
+            i32.const 2
+            local.set $^pc
+            br $^dispatch
+        )
+        i32.const 0
+    )
+)
@@ -0,0 +1,24 @@
+(module $directives
+    (import "env" "print" (func $print (param i32) (result i32)))
+    ;; Directives say what a program is for; without a `target` it compiles for every backend,
+    ;; and a data layout without a pointer size fits both of them.
+    (func $main (export "main") (result i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            i32.const 7
+            call $print
+            drop
+            i32.const 0
+            return
+        )
+        i32.const 0
+    )
+)
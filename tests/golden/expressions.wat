@@ -0,0 +1,126 @@
+(module
+    (import "env" "print" (func $print (param i32) (result i32)))
+    ;; Nested expressions are flattened into one operation per instruction.
+    (func $main (export "main") (result i32)
+        (local $%t0 i32)
+        (local $%t1 i32)
+        (local $%t10 i32)
+        (local $%t11 i32)
+        (local $%t12 i32)
+        (local $%t2 i32)
+        (local $%t3 i64)
+        (local $%t4 i64)
+        (local $%t5 i32)
+        (local $%t6 i32)
+        (local $%t7 i32)
+        (local $%t8 i32)
+        (local $%t9 i32)
+        (local $a i32)
+        (local $b i32)
+        (local $big i32)
+        (local $c i32)
+        (local $d i64)
+        (local $x i32)
+        (local $y i64)
+        (local $z i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            i32.const 3
+            local.set $a
+            i32.const 4
+            local.set $b
+            i32.const 5
+            local.set $c
+            i32.const 3
+            i32.const 4
+            i32.add
+            local.set $%t0
+            i32.const 7
+            i32.const 5
+            i32.mul
+            local.set $%t1
+            i32.const 10
+            i32.const 2
+            i32.div_s
+            local.set $%t2
+            i32.const 35
+            i32.const 5
+            i32.sub
+            local.set $x
+            local.get $x
+            call $print
+            drop
+            i64.const 7
+            local.set $d
+            i64.const 7
+            i64.const 1000000000
+            i64.mul
+            local.set $%t3
+            i64.const 7
+            i64.const 1
+            i64.sub
+            local.set $%t4
+            i64.const 7000000000
+            i64.const 6
+            i64.add
+            local.set $y
+            i64.const 7000000006
+            i64.const 4294967295
+            i64.gt_s
+            local.set $%t5
+            i32.const 3
+            i32.const 4
+            i32.lt_s
+            local.set $%t6
+            i32.const 1
+            i32.const 1
+            i32.and
+            local.set $big
+            local.get $big
+            call $print
+            drop
+            i32.const 3
+            i32.const 4
+            i32.sub
+            local.set $%t7
+            i32.const 0
+            i32.const -1
+            i32.sub
+            local.set $%t8
+            i32.const 1
+            i32.const 2
+            i32.mul
+            local.set $%t9
+            i32.const 5
+            i32.const 6
+            i32.and
+            local.set $%t10
+            i32.const 4
+            i32.const -1
+            i32.xor
+            local.set $%t11
+            i32.const 2
+            i32.const -5
+            i32.add
+            local.set $%t12
+            i32.const -3
+            i32.const 8
+            i32.add
+            local.set $z
+            local.get $z
+            call $print
+            drop
+            i32.const 0
+            return
+        )
+        i32.const 0
+    )
+)
@@ -0,0 +1,32 @@
+(module
+    (import "env" "print_str" (func $print_str (param i32) (result i32)))
+    (import "env" "print" (func $print (param i32) (result i32)))
+    (memory (export "memory") 1)
+    (data (i32.const 8) "externs\0a\00")
+    ;; Externs are declared like function headers. print and print_str come from
+    ;; the prelude; host_log is only provided by a wasm host, so calling it would
+    ;; not compile for fasm, but declaring it does.
+    (func $main (export "main") (result i32)
+        (local $n i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            i32.const 8
+            call $print_str
+            local.set $n
+            local.get $n
+            call $print
+            drop
+            i32.const 0
+            return
+        )
+        i32.const 0
+    )
+)
@@ -0,0 +1,104 @@
+(module
+    (import "env" "print" (func $print (param i32) (result i32)))
+    ;; Area of a circle in f64 and the same in f32, compared as floats.
+    (func $area (export "area") (param $r f64) (result f64)
+        (local $%t0 f64)
+        (local $a f64)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            local.get $r
+            local.get $r
+            f64.mul
+            local.set $%t0
+            local.get $%t0
+            f64.const 3.141592653589793
+            f64.mul
+            local.set $a
+            local.get $a
+            return
+        )
+        f64.const 0
+    )
+    (func $area32 (export "area32") (param $r f32) (result f32)
+        (local $%t0 f32)
+        (local $a f32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            local.get $r
+            local.get $r
+            f32.mul
+            local.set $%t0
+            local.get $%t0
+            f32.const 3.1415927
+            f32.mul
+            local.set $a
+            local.get $a
+            return
+        )
+        f32.const 0
+    )
+    (func $main (export "main") (result i32)
+        (local $a f64)
+        (local $b f32)
+        (local $big i32)
+        (local $close i32)
+        (local $h f32)
+        (local $q f32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            f64.const 2.0
+            call $area
+            local.set $a
+            local.get $a
+            f64.const 12.5
+            f64.gt
+            local.set $big
+            local.get $big
+            call $print
+            drop
+            f32.const 0.5
+            local.set $h
+            local.get $h
+            call $area32
+            local.set $b
+            local.get $b
+            f32.const 0.25
+            f32.div
+            local.set $q
+            local.get $q
+            f32.const 3.1416
+            f32.lt
+            local.set $close
+            local.get $close
+            call $print
+            drop
+            i32.const 0
+            return
+        )
+        i32.const 0
+    )
+)
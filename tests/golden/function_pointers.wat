@@ -0,0 +1,150 @@
+(module
+    (import "env" "print" (func $print (param i32) (result i32)))
+    (memory (export "memory") 1)
+    (global $^sp (mut i32) (i32.const 65536))
+    (table 4 funcref)
+    (elem (i32.const 1) func $double $square $print)
+    ;; Functions called through pointers, passed as arguments and kept in memory.
+    (func $double (export "double") (param $x i32) (result i32)
+        (local $r i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            local.get $x
+            i32.const 2
+            i32.mul
+            local.set $r
+            local.get $r
+            return
+        )
+        i32.const 0
+    )
+    (func $square (export "square") (param $x i32) (result i32)
+        (local $r i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            local.get $x
+            local.get $x
+            i32.mul
+            local.set $r
+            local.get $r
+            return
+        )
+        i32.const 0
+    )
+    (func $apply (export "apply") (param $f i32) (param $v i32) (result i32)
+        (local $r i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            local.get $v
+            local.get $f
+            call_indirect (param i32) (result i32)
+            local.set $r
+            local.get $r
+            return
+        )
+        i32.const 0
+    )
+    (func $main (export "main") (result i32)
+        (local $a i32)
+        (local $b i32)
+        (local $f i32)
+        (local $g i32)
+        (local $h i32)
+        (local $n i32)
+        (local $p i32)
+        (local $slot i32)
+        (local $table i32)
+        (local $^pc i32)
+        global.get $^sp
+        i32.const -16
+        i32.add
+        global.set $^sp
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            i32.const 1
+            local.set $f
+            local.get $f
+            i32.const 21
+            call $apply
+            local.set $a
+            local.get $a
+            call $print
+            drop
+            global.get $^sp
+            i32.const 0
+            i32.add
+            local.set $table
+            local.get $table
+            local.get $f
+            i32.store
+            local.get $table
+            i32.const 8
+            i32.add
+            local.set $slot
+            i32.const 2
+            local.set $g
+            local.get $slot
+            local.get $g
+            i32.store
+            local.get $slot
+            i32.load
+            local.set $h
+            i32.const 9
+            local.get $h
+            call_indirect (param i32) (result i32)
+            local.set $b
+            local.get $b
+            call $print
+            drop
+            ;; the call's own parameters and result make up the signature
+            i32.const 3
+            local.set $p
+            i32.const 7
+            local.get $p
+            call_indirect (param i32) (result i32)
+            local.set $n
+            i32.const 0
+            global.get $^sp
+            i32.const 16
+            i32.add
+            global.set $^sp
+            return
+        )
+        global.get $^sp
+        i32.const 16
+        i32.add
+        global.set $^sp
+        i32.const 0
+    )
+)
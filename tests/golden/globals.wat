@@ -0,0 +1,65 @@
+(module
+    (import "env" "print" (func $print (param i32) (result i32)))
+    (global $count (mut i32) (i32.const 0))
+    (global $step (mut i64) (i64.const 4294967296))
+    ;; A counter kept in a global, which every call to bump updates.
+    (func $bump (export "bump") (result i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            global.get $count
+            i32.const 1
+            i32.add
+            global.set $count
+            global.get $count
+            return
+        )
+        i32.const 0
+    )
+    (func $main (export "main") (result i32)
+        (local $big i32)
+        (local $n i32)
+        (local $s i64)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            call $bump
+            drop
+            call $bump
+            drop
+            call $bump
+            local.set $n
+            local.get $n
+            call $print
+            drop
+            global.get $step
+            i64.const 2
+            i64.mul
+            local.set $s
+            local.get $s
+            global.get $step
+            i64.gt_s
+            local.set $big
+            local.get $big
+            call $print
+            drop
+            i32.const 0
+            return
+        )
+        i32.const 0
+    )
+)
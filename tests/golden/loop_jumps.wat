@@ -0,0 +1,74 @@
+(module
+    (import "env" "print" (func $print (param i32) (result i32)))
+    (func $main (export "main") (result i32)
+        (local $i i32)
+        (local $n i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.6
+                (block $^seg.5
+                    (block $^seg.4
+                        (block $^seg.3
+                            (block $^seg.2
+                                (block $^seg.1
+                                    (block $^seg.0
+                                        (br_table $^seg.0 $^seg.1 $^seg.2 $^seg.3 $^seg.4 $^seg.5 $^seg.6 (local.get $^pc))
+                                    )
+                                    i32.const 1
+                                    local.set $^pc
+                                    br $^dispatch
+                                )
+                                i32.const 0
+                                local.set $i
+                                i32.const 0
+                                local.set $n
+                                i32.const 2
+                                local.set $^pc
+                                br $^dispatch
+                            )
+                            i32.const 4
+                            i32.const 3
+                            local.get $i
+                            i32.const 5
+                            i32.lt_s
+                            select
+                            local.set $^pc
+                            br $^dispatch
+                        )
+                        local.get $i
+                        call $print
+                        drop
+                        local.get $n
+                        call $print
+                        drop
+                        i32.const 0
+                        return
+                    )
+                    i32.const 6
+                    i32.const 5
+                    local.get $i
+                    i32.const 3
+                    i32.eq
+                    select
+                    local.set $^pc
+                    br $^dispatch
+                )
+                local.get $i
+                i32.const 1
+                i32.add
+                local.set $i
+                local.get $n
+                i32.const 10
+                i32.add
+                local.set $n
+                i32.const 2
+                local.set $^pc
+                br $^dispatch
+            )
+            i32.const 3
+            local.set $^pc
+            br $^dispatch
+        )
+        i32.const 0
+    )
+)
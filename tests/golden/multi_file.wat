@@ -0,0 +1,108 @@
+(module
+    (import "env" "print" (func $print (param i32) (result i32)))
+    (global $shapes (mut i32) (i32.const 0))
+    ;; A program split over two files; geometry.irl is loaded from the subdirectory next to this
+    ;; file and shares its exported functions and its global with it.
+    (func $main (export "main") (result i32)
+        (local $a i32)
+        (local $s i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            i32.const 3
+            i32.const 4
+            call $rect_area
+            local.set $a
+            local.get $a
+            call $print
+            drop
+            i32.const 5
+            call $square_area
+            local.set $s
+            local.get $s
+            call $print
+            drop
+            global.get $shapes
+            call $print
+            drop
+            i32.const 0
+            return
+        )
+        i32.const 0
+    )
+    ;; Areas of shapes; `multiply` is private to this file and becomes `geometry.multiply`.
+    (func $geometry.multiply (export "geometry.multiply") (param $a i32) (param $b i32) (result i32)
+        (local $c i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            local.get $a
+            local.get $b
+            i32.mul
+            local.set $c
+            local.get $c
+            return
+        )
+        i32.const 0
+    )
+    (func $rect_area (export "rect_area") (param $w i32) (param $h i32) (result i32)
+        (local $area i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            global.get $shapes
+            i32.const 1
+            i32.add
+            global.set $shapes
+            local.get $w
+            local.get $h
+            call $geometry.multiply
+            local.set $area
+            local.get $area
+            return
+        )
+        i32.const 0
+    )
+    (func $square_area (export "square_area") (param $side i32) (result i32)
+        (local $area i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            local.get $side
+            local.get $side
+            call $rect_area
+            local.set $area
+            local.get $area
+            return
+        )
+        i32.const 0
+    )
+)
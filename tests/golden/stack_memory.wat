@@ -0,0 +1,125 @@
+(module
+    (import "env" "print" (func $print (param i32) (result i32)))
+    (memory (export "memory") 1)
+    (global $^sp (mut i32) (i32.const 65536))
+    ;; Fills an array of four i32 on the stack with squares and sums it.
+    (func $main (export "main") (result i32)
+        (local $arr i32)
+        (local $i i32)
+        (local $offset i32)
+        (local $p i32)
+        (local $sq i32)
+        (local $total i32)
+        (local $v i32)
+        (local $^pc i32)
+        global.get $^sp
+        i32.const -16
+        i32.add
+        global.set $^sp
+        (loop $^dispatch
+            (block $^seg.6
+                (block $^seg.5
+                    (block $^seg.4
+                        (block $^seg.3
+                            (block $^seg.2
+                                (block $^seg.1
+                                    (block $^seg.0
+                                        (br_table $^seg.0 $^seg.1 $^seg.2 $^seg.3 $^seg.4 $^seg.5 $^seg.6 (local.get $^pc))
+                                    )
+                                    i32.const 1
+                                    local.set $^pc
+                                    br $^dispatch
+                                )
+                                global.get $^sp
+                                i32.const 0
+                                i32.add
+                                local.set $arr
+                                i32.const 0
+                                local.set $i
+                                i32.const 0
+                                local.set $total
+                                i32.const 2
+                                local.set $^pc
+                                br $^dispatch
+                            )
+                            i32.const 3
+                            i32.const 4
+                            local.get $i
+                            i32.const 4
+                            i32.eq
+                            select
+                            local.set $^pc
+                            br $^dispatch
+                        )
+                        i32.const 5
+                        i32.const 6
+                        local.get $i
+                        i32.const 0
+                        i32.eq
+                        select
+                        local.set $^pc
+                        br $^dispatch
+                    )
+                    local.get $i
+                    i32.const 4
+                    i32.mul
+                    local.set $offset
+                    local.get $arr
+                    local.get $offset
+                    i32.add
+                    local.set $p
+                    local.get $i
+                    local.get $i
+                    i32.mul
+                    local.set $sq
+                    local.get $p
+                    local.get $sq
+                    i32.store
+                    local.get $i
+                    i32.const 1
+                    i32.add
+                    local.set $i
+                    i32.const 2
+                    local.set $^pc
+                    br $^dispatch
+                )
+                local.get $total
+                call $print
+                drop
+                i32.const 0
+                global.get $^sp
+                i32.const 16
+                i32.add
+                global.set $^sp
+                return
+            )
+            local.get $i
+            i32.const 1
+            i32.sub
+            local.set $i
+            local.get $i
+            i32.const 4
+            i32.mul
+            local.set $offset
+            local.get $arr
+            local.get $offset
+            i32.add
+            local.set $p
+            local.get $p
+            i32.load
+            local.set $v
+            local.get $total
+            local.get $v
+            i32.add
+            local.set $total
+            i32.const 3
+            local.set $^pc
+            br $^dispatch
+        )
+        global.get $^sp
+        i32.const 16
+        i32.add
+        global.set $^sp
+        i32.const 0
+    )
+)
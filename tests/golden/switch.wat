@@ -0,0 +1,185 @@
+(module
+    (import "env" "print" (func $print (param i32) (result i32)))
+    ;; Dense cases become a jump table, sparse ones a search; a switch on a
+    ;; constant is folded into a goto.
+    (func $dense (export "dense") (param $x i32) (result i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.6
+                (block $^seg.5
+                    (block $^seg.4
+                        (block $^seg.3
+                            (block $^seg.2
+                                (block $^seg.1
+                                    (block $^seg.0
+                                        (br_table $^seg.0 $^seg.1 $^seg.2 $^seg.3 $^seg.4 $^seg.5 $^seg.6 (local.get $^pc))
+                                    )
+                                    i32.const 1
+                                    local.set $^pc
+                                    br $^dispatch
+                                )
+                                (block $^case.4
+                                    (block $^case.3
+                                        (block $^case.2
+                                            (block $^case.1
+                                                (block $^case.0
+                                                    local.get $x
+                                                    i32.const 0
+                                                    i32.sub
+                                                    br_table $^case.0 $^case.1 $^case.2 $^case.4 $^case.3 $^case.4
+                                                )
+                                                i32.const 2
+                                                local.set $^pc
+                                                br $^dispatch
+                                            )
+                                            i32.const 3
+                                            local.set $^pc
+                                            br $^dispatch
+                                        )
+                                        i32.const 4
+                                        local.set $^pc
+                                        br $^dispatch
+                                    )
+                                    i32.const 5
+                                    local.set $^pc
+                                    br $^dispatch
+                                )
+                                i32.const 6
+                                local.set $^pc
+                                br $^dispatch
+                            )
+                            i32.const 100
+                            return
+                        )
+                        i32.const 101
+                        return
+                    )
+                    i32.const 102
+                    return
+                )
+                i32.const 104
+                return
+            )
+            i32.const 199
+            return
+        )
+        i32.const 0
+    )
+    (func $sparse (export "sparse") (param $x i64) (result i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.6
+                (block $^seg.5
+                    (block $^seg.4
+                        (block $^seg.3
+                            (block $^seg.2
+                                (block $^seg.1
+                                    (block $^seg.0
+                                        (br_table $^seg.0 $^seg.1 $^seg.2 $^seg.3 $^seg.4 $^seg.5 $^seg.6 (local.get $^pc))
+                                    )
+                                    i32.const 1
+                                    local.set $^pc
+                                    br $^dispatch
+                                )
+                                local.get $x
+                                i64.const -1000
+                                i64.eq
+                                (if (then
+                                    i32.const 2
+                                    local.set $^pc
+                                    br $^dispatch
+                                ))
+                                local.get $x
+                                i64.const 7
+                                i64.eq
+                                (if (then
+                                    i32.const 3
+                                    local.set $^pc
+                                    br $^dispatch
+                                ))
+                                local.get $x
+                                i64.const 300
+                                i64.eq
+                                (if (then
+                                    i32.const 4
+                                    local.set $^pc
+                                    br $^dispatch
+                                ))
+                                local.get $x
+                                i64.const 100000
+                                i64.eq
+                                (if (then
+                                    i32.const 5
+                                    local.set $^pc
+                                    br $^dispatch
+                                ))
+                                i32.const 6
+                                local.set $^pc
+                                br $^dispatch
+                            )
+                            i32.const 1
+                            return
+                        )
+                        i32.const 2
+                        return
+                    )
+                    i32.const 3
+                    return
+                )
+                i32.const 4
+                return
+            )
+            i32.const 5
+            return
+        )
+        i32.const 0
+    )
+    (func $main (export "main") (result i32)
+        (local $a i32)
+        (local $b i32)
+        (local $c i32)
+        (local $k i64)
+        (local $m i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.2
+                (block $^seg.1
+                    (block $^seg.0
+                        (br_table $^seg.0 $^seg.1 $^seg.2 (local.get $^pc))
+                    )
+                    i32.const 1
+                    local.set $^pc
+                    br $^dispatch
+                )
+                i32.const 4
+                call $dense
+                local.set $a
+                local.get $a
+                call $print
+                drop
+                i32.const 3
+                call $dense
+                local.set $b
+                local.get $b
+                call $print
+                drop
+                i64.const 300
+                local.set $k
+                local.get $k
+                call $sparse
+                local.set $c
+                local.get $c
+                call $print
+                drop
+                i32.const 2
+                local.set $m
+                i32.const 2
+                local.set $^pc
+                br $^dispatch
+            )
+            i32.const 0
+            return
+        )
+        i32.const 0
+    )
+)
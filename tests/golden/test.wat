@@ -0,0 +1,84 @@
+(module
+    (import "env" "print" (func $print (param i32) (result i32)))
+    (func $fib (export "fib") (param $n i32) (result i32)
+        (local $a i32)
+        (local $b i32)
+        (local $i i32)
+        (local $t i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.4
+                (block $^seg.3
+                    (block $^seg.2
+                        (block $^seg.1
+                            (block $^seg.0
+                                (br_table $^seg.0 $^seg.1 $^seg.2 $^seg.3 $^seg.4 (local.get $^pc))
+                            )
+                            i32.const 1
+                            local.set $^pc
+                            br $^dispatch
+                        )
+                        i32.const 0
+                        local.set $a
+                        i32.const 1
+                        local.set $b
+                        i32.const 1
+                        local.set $i
+                        i32.const 2
+                        local.set $^pc
+                        br $^dispatch
+                    )
+                    i32.const 3
+                    i32.const 4
+                    local.get $i
+                    local.get $n
+                    i32.eq
+                    select
+                    local.set $^pc
+                    br $^dispatch
+                )
+                local.get $b
+                return
+            )
+            local.get $b
+            local.set $t
+            local.get $a
+            local.get $b
+            i32.add
+            local.set $b
+            local.get $t
+            local.set $a
+            local.get $i
+            i32.const 1
+            i32.add
+            local.set $i
+            i32.const 2
+            local.set $^pc
+            br $^dispatch
+        )
+        i32.const 0
+    )
+    (func $main (export "main") (result i32)
+        (local $a i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            i32.const 6
+            call $fib
+            local.set $a
+            local.get $a
+            call $print
+            drop
+            i32.const 0
+            return
+        )
+        i32.const 0
+    )
+)
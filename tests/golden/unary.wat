@@ -0,0 +1,66 @@
+(module
+    (import "env" "print" (func $print (param i32) (result i32)))
+    ;; Negation, logical not and bitwise complement.
+    (func $main (export "main") (result i32)
+        (local $a i32)
+        (local $b i32)
+        (local $c i32)
+        (local $d i32)
+        (local $n i32)
+        (local $o i32)
+        (local $z i32)
+        (local $zero i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            i32.const 5
+            local.set $a
+            i32.const 0
+            i32.const 5
+            i32.sub
+            local.set $n
+            i32.const -5
+            i32.const 12
+            i32.add
+            local.set $b
+            local.get $b
+            call $print
+            drop
+            i32.const 5
+            i32.eqz
+            local.set $z
+            local.get $z
+            call $print
+            drop
+            i32.const 0
+            local.set $zero
+            i32.const 0
+            i32.eqz
+            local.set $o
+            local.get $o
+            call $print
+            drop
+            i32.const 5
+            i32.const -1
+            i32.xor
+            local.set $c
+            i32.const -6
+            i32.const 10
+            i32.add
+            local.set $d
+            local.get $d
+            call $print
+            drop
+            i32.const 0
+            return
+        )
+        i32.const 0
+    )
+)
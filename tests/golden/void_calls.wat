@@ -0,0 +1,69 @@
+(module
+    (import "env" "print" (func $print (param i32) (result i32)))
+    (import "env" "print_str" (func $print_str (param i32) (result i32)))
+    (memory (export "memory") 1)
+    (data (i32.const 8) "void\0a\00")
+    ;; A void function returns nothing; a bare call discards the result, if any.
+    (func $report (export "report") (param $x i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            local.get $x
+            call $print
+            drop
+            return
+        )
+    )
+    (func $twice (export "twice") (param $x i32) (result i32)
+        (local $y i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            local.get $x
+            i32.const 2
+            i32.mul
+            local.set $y
+            local.get $y
+            return
+        )
+        i32.const 0
+    )
+    (func $main (export "main") (result i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            i32.const 42
+            call $report
+            i32.const 21
+            call $twice
+            drop
+            i32.const 8
+            call $print_str
+            drop
+            i32.const 0
+            return
+        )
+        i32.const 0
+    )
+)
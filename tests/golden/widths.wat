@@ -0,0 +1,107 @@
+(module
+    (import "env" "print" (func $print (param i32) (result i32)))
+    ;; Integer arithmetic wraps around at the width of its type; comparisons
+    ;; yield an i1 unless their result is annotated with another type.
+    (func $wrap (export "wrap") (param $x i32) (result i32)
+        (local $y i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            local.get $x
+            i32.const 100
+            i32.add
+            i32.extend8_s
+            local.set $y
+            local.get $y
+            return
+        )
+        i32.const 0
+    )
+    (func $widen (export "widen") (param $x i64) (result i64)
+        (local $y i64)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            local.get $x
+            i64.const 1000000
+            i64.mul
+            local.set $y
+            local.get $y
+            return
+        )
+        i64.const 0
+    )
+    (func $main (export "main") (result i32)
+        (local $a i32)
+        (local $big i32)
+        (local $c i64)
+        (local $d i32)
+        (local $e i32)
+        (local $neg i32)
+        (local $wrapped i32)
+        (local $^pc i32)
+        (loop $^dispatch
+            (block $^seg.1
+                (block $^seg.0
+                    (br_table $^seg.0 $^seg.1 (local.get $^pc))
+                )
+                i32.const 1
+                local.set $^pc
+                br $^dispatch
+            )
+            i32.const 27
+            call $wrap
+            i32.extend8_s
+            local.set $a
+            local.get $a
+            i32.const 127
+            i32.eq
+            local.set $wrapped
+            local.get $wrapped
+            call $print
+            drop
+            i64.const 5000
+            call $widen
+            local.set $c
+            local.get $c
+            i64.const 4294967295
+            i64.gt_s
+            local.set $big
+            local.get $big
+            call $print
+            drop
+            i32.const 32767
+            i32.extend16_s
+            local.set $d
+            i32.const 32767
+            i32.const 1
+            i32.add
+            i32.extend16_s
+            local.set $e
+            i32.const -32768
+            i32.const 0
+            i32.lt_s
+            local.set $neg
+            local.get $neg
+            call $print
+            drop
+            i32.const 0
+            return
+        )
+        i32.const 0
+    )
+)
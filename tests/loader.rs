@@ -0,0 +1,80 @@
+//! Compiles the small programs under `tests/fixtures/loader/`, each spread over several files,
+//! and checks how their imports are resolved and their symbols named.
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+struct Compiled {
+	success: bool,
+	codes: Vec<String>,
+	wat: Option<String>,
+}
+
+/// Copies the `.irl` files of `from` and its subdirectories to `to`.
+fn copy_sources(from: &Path, to: &Path) {
+	std::fs::create_dir_all(to).expect("could not create directory");
+	for entry in std::fs::read_dir(from).expect("could not read directory") {
+		let path: PathBuf = entry.unwrap().path();
+		if path.is_dir() {
+			copy_sources(&path, &to.join(path.file_name().unwrap()));
+		} else if path.extension().is_some_and(|extension| extension == "irl") {
+			std::fs::copy(&path, to.join(path.file_name().unwrap())).expect("could not copy fixture");
+		}
+	}
+}
+
+/// Compiles the `main.irl` of the fixture `name` to wat, in a copy so that the output does
+/// not land next to the fixture, and returns the codes of the reported diagnostics.
+fn compile(name: &str) -> Compiled {
+	let dir: PathBuf = std::env::temp_dir().join(format!("irl-loader-{}-{}", std::process::id(), name));
+	copy_sources(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join("loader").join(name), &dir);
+	let filepath: PathBuf = dir.join("main.irl");
+	let output: Output = Command::new(env!("CARGO_BIN_EXE_irl"))
+		.args(["compile", "--wat", "--color", "never", "--error-format", "json", "-f"])
+		.arg(&filepath)
+		.output()
+		.expect("could not run irl");
+	let codes: Vec<String> = String::from_utf8_lossy(&output.stderr).lines()
+		.filter_map(|line| line.split("\"code\":\"").nth(1))
+		.filter_map(|rest| rest.split('"').next())
+		.map(String::from)
+		.collect();
+	let wat: Option<String> = std::fs::read_to_string(filepath.with_extension("wat")).ok();
+	let _ = std::fs::remove_dir_all(&dir);
+	Compiled{success: output.status.success(), codes, wat}
+}
+
+#[test]
+fn import_cycle() {
+	let compiled: Compiled = compile("cycle");
+	assert!(!compiled.success);
+	assert_eq!(compiled.codes, vec!["E0007"]);
+}
+
+#[test]
+fn duplicate_symbol() {
+	let compiled: Compiled = compile("duplicate_symbol");
+	assert!(!compiled.success);
+	assert_eq!(compiled.codes, vec!["E0008"]);
+}
+
+#[test]
+fn imports_are_relative_to_the_importing_file() {
+	// `shapes/square.irl` imports `helper.irl`, which only exists next to it
+	let compiled: Compiled = compile("relative_path");
+	assert!(compiled.success, "{:?}", compiled.codes);
+	let wat: String = compiled.wat.expect("no wat output");
+	// both imported files define a private `helper`, each renamed after its own file
+	assert!(wat.contains("$square.helper"), "{}", wat);
+	assert!(wat.contains("$helper.helper"), "{}", wat);
+	assert!(wat.contains("$mul"), "{}", wat);
+}
+
+#[test]
+fn globals_are_shared_by_all_files() {
+	let compiled: Compiled = compile("shared_global");
+	assert!(compiled.success, "{:?}", compiled.codes);
+	let compiled: Compiled = compile("clashing_global");
+	assert!(!compiled.success);
+	assert_eq!(compiled.codes, vec!["E0206"]);
+}